@@ -3,13 +3,23 @@ fn main() -> Result<(), std::io::Error> {
         .out_dir("src/genpb")
         .build_server(false);
 
-    let de_effect = "#[cfg_attr(feature = \"serde\", serde(deserialize_with = \"crate::sdk::deser::deserialize_effect\"))]";
+    let de_effect = "#[cfg_attr(feature = \"serde\", serde(deserialize_with = \"crate::sdk::deser::deserialize_effect\", serialize_with = \"crate::sdk::deser::serialize_effect\"))]";
+    let de_scope_permissions = "#[cfg_attr(feature = \"serde\", serde(deserialize_with = \"crate::sdk::deser::deserialize_scope_permissions\", serialize_with = \"crate::sdk::deser::serialize_scope_permissions\"))]";
+    let de_effect_map = "#[cfg_attr(feature = \"serde\", serde(deserialize_with = \"crate::sdk::deser::deserialize_effect_map\", serialize_with = \"crate::sdk::deser::serialize_effect_map\"))]";
+    let de_timestamp = "#[cfg_attr(feature = \"serde\", serde(deserialize_with = \"crate::sdk::deser::deserialize_timestamp\", serialize_with = \"crate::sdk::deser::serialize_timestamp\"))]";
+    let default = "#[cfg_attr(feature = \"serde\", serde(default))]";
     let flatten = "#[cfg_attr(feature = \"serde\", serde(flatten))]";
-    let de =  "#[cfg_attr(feature = \"serde\", derive(serde::Deserialize), serde(rename_all = \"camelCase\"))]";
+    let flatten_ser = "#[cfg_attr(feature = \"serde\", serde(flatten, skip_deserializing))]";
+    let de =  "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize), serde(rename_all = \"camelCase\"))]";
     builder = builder
         .type_attribute(".cerbos.policy.v1", "#[if_struct_macro::serde_default]")
         .enum_attribute(".cerbos.policy.v1", de)
         .type_attribute(".cerbos.engine.v1", de)
+        // Test suites and fixtures omit empty fields.
+        .type_attribute(".cerbos.engine.v1.OutputEntry", default)
+        .type_attribute(".cerbos.engine.v1.Resource", default)
+        .type_attribute(".cerbos.engine.v1.Principal", default)
+        .type_attribute(".cerbos.engine.v1.AuxData", default)
         .type_attribute(".cerbos.effect.v1", de)
         .type_attribute(".cerbos.schema.v1", de)
         .type_attribute(".google.api.expr.v1alpha1", de)
@@ -19,6 +29,15 @@ fn main() -> Result<(), std::io::Error> {
         .type_attribute("google.protobuf.Duration", de)
         .field_attribute("ResourceRule.effect", de_effect)
         .field_attribute("PrincipalRule.Action.effect", de_effect)
+        .field_attribute("ResourcePolicy.scope_permissions", de_scope_permissions)
+        .field_attribute("PrincipalPolicy.scope_permissions", de_scope_permissions)
+        .field_attribute("RolePolicy.scope_permissions", de_scope_permissions)
+        .field_attribute(".cerbos.policy.v1.TestOptions.now", de_timestamp)
+        .field_attribute(".cerbos.policy.v1.TestTable.Expectation.actions", de_effect_map)
+        .field_attribute(".cerbos.policy.v1.Test.expected", de_effect_map)
+        // The policy type is read separately by `read_policy`.
+        .field_attribute("Policy.policy_type", flatten_ser)
+        .field_attribute("RolePolicy.policy_type", flatten)
         .field_attribute("Match.op", flatten)
         .field_attribute("Condition.condition", flatten);
 
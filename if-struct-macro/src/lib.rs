@@ -3,7 +3,8 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::ToTokens;
 use syn::{Attribute, Item};
 
-/// Conditionally applies `serde(default)` only to structs when the "serde" feature is enabled
+/// Conditionally applies `serde(default)` only to structs when the "serde" feature is enabled.
+/// Fields that hold their default value are omitted when serializing.
 #[proc_macro_attribute]
 pub fn serde_default(_args: TokenStream, input: TokenStream) -> TokenStream {
     let input2 = proc_macro2::TokenStream::from(input);
@@ -20,6 +21,9 @@ fn serde_default_impl(input: TokenStream2) -> syn::Result<TokenStream2> {
     if let Item::Struct(mut item_struct) = item {
         item_struct.attrs.push(get_serde_de());
         item_struct.attrs.push(get_serde_default());
+        for field in item_struct.fields.iter_mut() {
+            field.attrs.push(get_serde_skip_default());
+        }
         Ok(item_struct.into_token_stream())
     } else {
         Ok(item.into_token_stream())
@@ -28,7 +32,7 @@ fn serde_default_impl(input: TokenStream2) -> syn::Result<TokenStream2> {
 
 fn get_serde_de() -> Attribute {
     syn::parse_quote! {
-        #[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "camelCase"))]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "camelCase"))]
     }
 }
 
@@ -38,6 +42,12 @@ fn get_serde_default() -> Attribute {
     }
 }
 
+fn get_serde_skip_default() -> Attribute {
+    syn::parse_quote! {
+        #[cfg_attr(feature = "serde", serde(skip_serializing_if = "crate::sdk::deser::is_default"))]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,6 +65,7 @@ mod tests {
         let result_str = result.to_string().replace(" ", "");
 
         assert!(result_str.contains("serde(default)"));
+        assert!(result_str.contains("skip_serializing_if=\"crate::sdk::deser::is_default\""));
     }
 
     #[test]
@@ -1,7 +1,7 @@
 // This file is @generated by prost-build.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -26,7 +26,7 @@ pub struct PlanResourcesInput {
 pub mod plan_resources_input {
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -46,7 +46,7 @@ pub mod plan_resources_input {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -58,7 +58,7 @@ pub struct PlanResourcesAst {
 pub mod plan_resources_ast {
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -70,7 +70,7 @@ pub mod plan_resources_ast {
     pub mod node {
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -85,7 +85,7 @@ pub mod plan_resources_ast {
     }
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -99,7 +99,7 @@ pub mod plan_resources_ast {
     pub mod logical_operation {
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(
@@ -148,7 +148,7 @@ pub mod plan_resources_ast {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -162,7 +162,7 @@ pub struct PlanResourcesFilter {
 pub mod plan_resources_filter {
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -176,7 +176,7 @@ pub mod plan_resources_filter {
     pub mod expression {
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, ::prost::Message)]
@@ -188,7 +188,7 @@ pub mod plan_resources_filter {
         pub mod operand {
             #[cfg_attr(
                 feature = "serde",
-                derive(serde::Deserialize),
+                derive(serde::Serialize, serde::Deserialize),
                 serde(rename_all = "camelCase")
             )]
             #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -204,7 +204,7 @@ pub mod plan_resources_filter {
     }
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(
@@ -252,7 +252,7 @@ pub mod plan_resources_filter {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -286,7 +286,7 @@ pub struct PlanResourcesOutput {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -304,7 +304,7 @@ pub struct CheckInput {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -333,7 +333,7 @@ pub struct CheckOutput {
 pub mod check_output {
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -348,9 +348,10 @@ pub mod check_output {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OutputEntry {
     #[prost(string, tag = "1")]
@@ -360,9 +361,10 @@ pub struct OutputEntry {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Resource {
     #[prost(string, tag = "1")]
@@ -381,9 +383,10 @@ pub struct Resource {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Principal {
     #[prost(string, tag = "1")]
@@ -402,9 +405,10 @@ pub struct Principal {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AuxData {
     #[prost(map = "string, message", tag = "1")]
@@ -415,7 +419,7 @@ pub struct AuxData {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -429,7 +433,7 @@ pub struct Trace {
 pub mod trace {
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -446,7 +450,7 @@ pub mod trace {
     pub mod component {
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -458,7 +462,7 @@ pub mod trace {
         }
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(
@@ -544,7 +548,7 @@ pub mod trace {
         }
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, Eq, Hash, ::prost::Oneof)]
@@ -577,7 +581,7 @@ pub mod trace {
     }
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -599,7 +603,7 @@ pub mod trace {
     pub mod event {
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(
@@ -646,7 +650,7 @@ pub mod trace {
 /// Data from the request, provided to expressions as the top-level `request` variable.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -662,7 +666,7 @@ pub struct Request {
 pub mod request {
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -683,7 +687,7 @@ pub mod request {
     }
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -706,7 +710,7 @@ pub mod request {
 /// Data from the runtime, provided to expressions as the top-level `runtime` variable.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -18,6 +18,7 @@ pub struct Policy {
     #[prost(string, tag = "9")]
     pub json_schema: ::prost::alloc::string::String,
     #[prost(oneof = "policy::PolicyType", tags = "5, 6, 7, 10, 11, 12")]
+    #[cfg_attr(feature = "serde", serde(flatten, skip_deserializing))]
     pub policy_type: ::core::option::Option<policy::PolicyType>,
 }
 /// Nested message and enum types in `Policy`.
@@ -25,7 +26,7 @@ pub mod policy {
     #[if_struct_macro::serde_default]
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -91,6 +92,13 @@ pub struct ResourcePolicy {
     #[prost(message, optional, tag = "7")]
     pub variables: ::core::option::Option<Variables>,
     #[prost(enumeration = "ScopePermissions", tag = "8")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "crate::sdk::deser::deserialize_scope_permissions",
+            serialize_with = "crate::sdk::deser::serialize_scope_permissions"
+        )
+    )]
     pub scope_permissions: i32,
     #[prost(message, optional, tag = "9")]
     pub constants: ::core::option::Option<Constants>,
@@ -109,7 +117,10 @@ pub struct ResourceRule {
     #[prost(enumeration = "super::super::effect::v1::Effect", tag = "5")]
     #[cfg_attr(
         feature = "serde",
-        serde(deserialize_with = "crate::sdk::deser::deserialize_effect")
+        serde(
+            deserialize_with = "crate::sdk::deser::deserialize_effect",
+            serialize_with = "crate::sdk::deser::serialize_effect"
+        )
     )]
     pub effect: i32,
     #[prost(string, tag = "6")]
@@ -129,8 +140,16 @@ pub struct RolePolicy {
     /// Deprecated: no-op.
     #[deprecated]
     #[prost(enumeration = "ScopePermissions", tag = "4")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "crate::sdk::deser::deserialize_scope_permissions",
+            serialize_with = "crate::sdk::deser::serialize_scope_permissions"
+        )
+    )]
     pub scope_permissions: i32,
     #[prost(oneof = "role_policy::PolicyType", tags = "1")]
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub policy_type: ::core::option::Option<role_policy::PolicyType>,
 }
 /// Nested message and enum types in `RolePolicy`.
@@ -138,7 +157,7 @@ pub mod role_policy {
     #[if_struct_macro::serde_default]
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, Eq, Hash, ::prost::Oneof)]
@@ -171,6 +190,13 @@ pub struct PrincipalPolicy {
     #[prost(message, optional, tag = "5")]
     pub variables: ::core::option::Option<Variables>,
     #[prost(enumeration = "ScopePermissions", tag = "6")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "crate::sdk::deser::deserialize_scope_permissions",
+            serialize_with = "crate::sdk::deser::serialize_scope_permissions"
+        )
+    )]
     pub scope_permissions: i32,
     #[prost(message, optional, tag = "7")]
     pub constants: ::core::option::Option<Constants>,
@@ -195,7 +221,10 @@ pub mod principal_rule {
         #[prost(enumeration = "super::super::super::effect::v1::Effect", tag = "3")]
         #[cfg_attr(
             feature = "serde",
-            serde(deserialize_with = "crate::sdk::deser::deserialize_effect")
+            serde(
+                deserialize_with = "crate::sdk::deser::deserialize_effect",
+                serialize_with = "crate::sdk::deser::serialize_effect"
+            )
         )]
         pub effect: i32,
         #[prost(string, tag = "4")]
@@ -282,7 +311,7 @@ pub mod condition {
     #[if_struct_macro::serde_default]
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -311,7 +340,7 @@ pub mod r#match {
     #[if_struct_macro::serde_default]
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -442,6 +471,13 @@ pub mod test_fixture_group {
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TestOptions {
     #[prost(message, optional, tag = "1")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "crate::sdk::deser::deserialize_timestamp",
+            serialize_with = "crate::sdk::deser::serialize_timestamp"
+        )
+    )]
     pub now: ::core::option::Option<super::super::super::google::protobuf::Timestamp>,
     #[prost(bool, tag = "2")]
     pub lenient_scope_search: bool,
@@ -553,6 +589,13 @@ pub mod test_table {
             map = "string, enumeration(super::super::super::effect::v1::Effect)",
             tag = "3"
         )]
+        #[cfg_attr(
+            feature = "serde",
+            serde(
+                deserialize_with = "crate::sdk::deser::deserialize_effect_map",
+                serialize_with = "crate::sdk::deser::serialize_effect_map"
+            )
+        )]
         pub actions: ::std::collections::HashMap<::prost::alloc::string::String, i32>,
         #[prost(message, repeated, tag = "4")]
         pub outputs: ::prost::alloc::vec::Vec<OutputExpectations>,
@@ -580,6 +623,13 @@ pub struct Test {
     #[prost(message, optional, tag = "5")]
     pub input: ::core::option::Option<super::super::engine::v1::CheckInput>,
     #[prost(map = "string, enumeration(super::super::effect::v1::Effect)", tag = "6")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "crate::sdk::deser::deserialize_effect_map",
+            serialize_with = "crate::sdk::deser::serialize_effect_map"
+        )
+    )]
     pub expected: ::std::collections::HashMap<::prost::alloc::string::String, i32>,
     #[prost(message, optional, tag = "7")]
     pub options: ::core::option::Option<TestOptions>,
@@ -709,7 +759,7 @@ pub mod test_results {
         #[if_struct_macro::serde_default]
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -757,7 +807,7 @@ pub mod test_results {
         #[if_struct_macro::serde_default]
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -791,7 +841,7 @@ pub mod test_results {
     #[if_struct_macro::serde_default]
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(
@@ -843,7 +893,7 @@ pub mod test_results {
 #[if_struct_macro::serde_default]
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -890,7 +940,7 @@ impl Kind {
 #[if_struct_macro::serde_default]
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -1,7 +1,7 @@
 // This file is @generated by prost-build.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -17,7 +17,7 @@ pub struct ValidationError {
 pub mod validation_error {
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(
@@ -62,7 +62,7 @@ pub mod validation_error {
 }
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
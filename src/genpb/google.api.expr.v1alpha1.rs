@@ -2,7 +2,7 @@
 /// An expression together with source information as returned by the parser.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -33,7 +33,7 @@ pub struct ParsedExpr {
 /// function declaration `startsWith`.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -52,7 +52,7 @@ pub mod expr {
     /// An identifier expression. e.g. `request`.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -68,7 +68,7 @@ pub mod expr {
     /// A field selection expression. e.g. `request.auth`.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -96,7 +96,7 @@ pub mod expr {
     /// For example, `value == 10`, `size(map_value)`.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -118,7 +118,7 @@ pub mod expr {
     /// `dyn(\[1, 'hello', 2.0\])`
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -142,7 +142,7 @@ pub mod expr {
     /// `types.MyType{field_id: 'value'}`.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -160,7 +160,7 @@ pub mod expr {
         /// Represents an entry.
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, ::prost::Message)]
@@ -189,7 +189,7 @@ pub mod expr {
             /// The `Entry` key kinds.
             #[cfg_attr(
                 feature = "serde",
-                derive(serde::Deserialize),
+                derive(serde::Serialize, serde::Deserialize),
                 serde(rename_all = "camelCase")
             )]
             #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -259,7 +259,7 @@ pub mod expr {
     /// ```
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -306,7 +306,7 @@ pub mod expr {
     /// Required. Variants of expressions.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -350,7 +350,7 @@ pub mod expr {
 /// `true`, `null`.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -364,7 +364,7 @@ pub mod constant {
     /// Required. The valid constant kinds.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -410,7 +410,7 @@ pub mod constant {
 /// Source information collected at parse time.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -462,7 +462,7 @@ pub mod source_info {
     /// An extension that was requested for the source expression.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -486,7 +486,7 @@ pub mod source_info {
         /// Version
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
@@ -503,7 +503,7 @@ pub mod source_info {
         /// CEL component specifier.
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(
@@ -559,7 +559,7 @@ pub mod source_info {
 /// A specific position in source.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -582,7 +582,7 @@ pub struct SourcePosition {
 /// A CEL expression which has been successfully type checked.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -632,7 +632,7 @@ pub struct CheckedExpr {
 /// Represents a CEL type.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -649,7 +649,7 @@ pub mod r#type {
     /// List type with typed elements, e.g. `list<example.proto.MyMessage>`.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -661,7 +661,7 @@ pub mod r#type {
     /// Map type with parameterized key and value types, e.g. `map<string, int>`.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -676,7 +676,7 @@ pub mod r#type {
     /// Function type with result and arg types.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -691,7 +691,7 @@ pub mod r#type {
     /// Application defined abstract type.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -706,7 +706,7 @@ pub mod r#type {
     /// CEL primitive types.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(
@@ -776,7 +776,7 @@ pub mod r#type {
     /// Well-known protobuf types treated with first-class support in CEL.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(
@@ -832,7 +832,7 @@ pub mod r#type {
     /// The kind of type.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -901,7 +901,7 @@ pub mod r#type {
 /// evaluating that expression, and the caller requesting evaluation.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -932,7 +932,7 @@ pub mod decl {
     /// time.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -955,7 +955,7 @@ pub mod decl {
     /// logging which are not observable from CEL).
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Message)]
@@ -978,7 +978,7 @@ pub mod decl {
         /// parameterized type variables (similar as type erasure in Java).
         #[cfg_attr(
             feature = "serde",
-            derive(serde::Deserialize),
+            derive(serde::Serialize, serde::Deserialize),
             serde(rename_all = "camelCase")
         )]
         #[derive(Clone, PartialEq, ::prost::Message)]
@@ -1029,7 +1029,7 @@ pub mod decl {
     /// Required. The declaration kind.
     #[cfg_attr(
         feature = "serde",
-        derive(serde::Deserialize),
+        derive(serde::Serialize, serde::Deserialize),
         serde(rename_all = "camelCase")
     )]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -1045,7 +1045,7 @@ pub mod decl {
 /// Describes a resolved reference to a declaration.
 #[cfg_attr(
     feature = "serde",
-    derive(serde::Deserialize),
+    derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase")
 )]
 #[derive(Clone, PartialEq, ::prost::Message)]
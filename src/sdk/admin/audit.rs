@@ -0,0 +1,374 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures_util::stream::{self, Stream};
+use thiserror::Error;
+use tonic::{service::interceptor::InterceptedService, transport::Channel, Streaming};
+
+use crate::genpb::{
+    cerbos::{
+        audit::v1::{AccessLogEntry, DecisionLogEntry},
+        request::v1::{
+            list_audit_log_entries_request::{Filter, Kind, TimeRange},
+            ListAuditLogEntriesRequest,
+        },
+        response::v1::{list_audit_log_entries_response::Entry, ListAuditLogEntriesResponse},
+        svc::v1::cerbos_admin_service_client::CerbosAdminServiceClient,
+    },
+    google::protobuf::Timestamp,
+};
+
+use super::CerbosBasicAuthInterceptor;
+
+/// Maximum number of entries the PDP returns for a tail query.
+pub const MAX_TAIL: u32 = 1000;
+
+const DEFAULT_PAGE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// Reasons an [`AuditLogQuery`] is rejected before it is sent.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AuditLogQueryError {
+    #[error("no filter specified: use between, lookup or tail")]
+    MissingFilter,
+    #[error("tail must be between 1 and {MAX_TAIL}, got {0}")]
+    InvalidTail(u32),
+    #[error("time range start must not be after its end")]
+    InvalidTimeRange,
+    #[error("time range must not start before the Unix epoch")]
+    TimeBeforeEpoch,
+    #[error("lookup call ID must not be empty")]
+    EmptyLookup,
+    #[error("page duration must not be zero")]
+    ZeroPageDuration,
+    #[error("page duration is too long for the time range")]
+    PageDurationOverflow,
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::AccessLogEntry {}
+    impl Sealed for super::DecisionLogEntry {}
+}
+
+/// Audit log entry types that can be queried. Implemented for [`AccessLogEntry`] and
+/// [`DecisionLogEntry`].
+pub trait AuditLogKind: sealed::Sealed + Send + Sized + 'static {
+    #[doc(hidden)]
+    const KIND: Kind;
+    #[doc(hidden)]
+    fn from_entry(entry: Entry) -> Option<Self>;
+}
+
+impl AuditLogKind for AccessLogEntry {
+    const KIND: Kind = Kind::Access;
+
+    fn from_entry(entry: Entry) -> Option<Self> {
+        match entry {
+            Entry::AccessLogEntry(e) => Some(e),
+            Entry::DecisionLogEntry(_) => None,
+        }
+    }
+}
+
+impl AuditLogKind for DecisionLogEntry {
+    const KIND: Kind = Kind::Decision;
+
+    fn from_entry(entry: Entry) -> Option<Self> {
+        match entry {
+            Entry::DecisionLogEntry(e) => Some(e),
+            Entry::AccessLogEntry(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum QueryFilter {
+    Between(SystemTime, SystemTime),
+    Lookup(String),
+    Tail(u32),
+}
+
+/// Query for audit log entries of type `K`.
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+/// use cerbos::sdk::admin::audit::AuditLogQuery;
+///
+/// let now = SystemTime::now();
+/// let query = AuditLogQuery::decisions()
+///     .between(now - Duration::from_secs(24 * 60 * 60), now)
+///     .with_page_duration(Duration::from_secs(15 * 60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuditLogQuery<K> {
+    filter: Option<QueryFilter>,
+    page_duration: Duration,
+    _kind: PhantomData<fn() -> K>,
+}
+
+impl AuditLogQuery<AccessLogEntry> {
+    /// Query access log entries.
+    pub fn access() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditLogQuery<DecisionLogEntry> {
+    /// Query decision log entries.
+    pub fn decisions() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: AuditLogKind> AuditLogQuery<K> {
+    fn new() -> Self {
+        Self {
+            filter: None,
+            page_duration: DEFAULT_PAGE_DURATION,
+            _kind: PhantomData,
+        }
+    }
+
+    /// Entries recorded between `start` and `end`, inclusive.
+    pub fn between(mut self, start: SystemTime, end: SystemTime) -> Self {
+        self.filter = Some(QueryFilter::Between(start, end));
+        self
+    }
+
+    /// The entry with the given call ID.
+    pub fn lookup(mut self, call_id: impl Into<String>) -> Self {
+        self.filter = Some(QueryFilter::Lookup(call_id.into()));
+        self
+    }
+
+    /// The most recent `n` entries. At most [`MAX_TAIL`].
+    pub fn tail(mut self, n: u32) -> Self {
+        self.filter = Some(QueryFilter::Tail(n));
+        self
+    }
+
+    /// Time ranges longer than this are fetched as consecutive windows of this length. Defaults to
+    /// one hour.
+    pub fn with_page_duration(mut self, page_duration: Duration) -> Self {
+        self.page_duration = page_duration;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), AuditLogQueryError> {
+        self.requests().map(|_| ())
+    }
+
+    /// Requests to send, one per time window for range queries. Windows are generated as the
+    /// requests are sent.
+    fn requests(&self) -> Result<Requests, AuditLogQueryError> {
+        let request = |filter| ListAuditLogEntriesRequest {
+            kind: K::KIND as i32,
+            filter: Some(filter),
+        };
+
+        match self
+            .filter
+            .as_ref()
+            .ok_or(AuditLogQueryError::MissingFilter)?
+        {
+            QueryFilter::Tail(n) if *n == 0 || *n > MAX_TAIL => {
+                Err(AuditLogQueryError::InvalidTail(*n))
+            }
+            QueryFilter::Tail(n) => Ok(Requests::Single(Some(request(Filter::Tail(*n))))),
+            QueryFilter::Lookup(id) if id.is_empty() => Err(AuditLogQueryError::EmptyLookup),
+            QueryFilter::Lookup(id) => {
+                Ok(Requests::Single(Some(request(Filter::Lookup(id.clone())))))
+            }
+            QueryFilter::Between(start, end) => {
+                if start > end {
+                    return Err(AuditLogQueryError::InvalidTimeRange);
+                }
+                if self.page_duration.is_zero() {
+                    return Err(AuditLogQueryError::ZeroPageDuration);
+                }
+                let start = since_epoch(*start)?;
+                let end = since_epoch(*end)?;
+                // No window can start after `end`, so this bounds every window computed below.
+                if end.checked_add(self.page_duration).is_none() {
+                    return Err(AuditLogQueryError::PageDurationOverflow);
+                }
+
+                Ok(Requests::Windows {
+                    kind: K::KIND as i32,
+                    next: Some(start),
+                    end,
+                    page_duration: self.page_duration,
+                })
+            }
+        }
+    }
+}
+
+enum Requests {
+    Single(Option<ListAuditLogEntriesRequest>),
+    Windows {
+        kind: i32,
+        next: Option<Duration>,
+        end: Duration,
+        page_duration: Duration,
+    },
+}
+
+impl Iterator for Requests {
+    type Item = ListAuditLogEntriesRequest;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Requests::Single(request) => request.take(),
+            Requests::Windows {
+                kind,
+                next,
+                end,
+                page_duration,
+            } => {
+                let window_start = next.take()?;
+                let window_next = window_start + *page_duration;
+                // Both ends of a range are inclusive, so windows end just before the next one
+                // starts to avoid returning boundary entries twice.
+                let window_end = if window_next > *end {
+                    *end
+                } else {
+                    *next = Some(window_next);
+                    window_next - Duration::from_nanos(1)
+                };
+                Some(ListAuditLogEntriesRequest {
+                    kind: *kind,
+                    filter: Some(Filter::Between(TimeRange {
+                        start: Some(to_timestamp(window_start)),
+                        end: Some(to_timestamp(window_end)),
+                    })),
+                })
+            }
+        }
+    }
+}
+
+fn since_epoch(t: SystemTime) -> Result<Duration, AuditLogQueryError> {
+    t.duration_since(UNIX_EPOCH)
+        .map_err(|_| AuditLogQueryError::TimeBeforeEpoch)
+}
+
+fn to_timestamp(d: Duration) -> Timestamp {
+    Timestamp {
+        seconds: d.as_secs() as i64,
+        nanos: d.subsec_nanos() as i32,
+    }
+}
+
+type AdminServiceClient =
+    CerbosAdminServiceClient<InterceptedService<Channel, CerbosBasicAuthInterceptor>>;
+
+struct PageState {
+    client: AdminServiceClient,
+    requests: Requests,
+    current: Option<Streaming<ListAuditLogEntriesResponse>>,
+}
+
+pub(super) fn entries<K: AuditLogKind>(
+    client: AdminServiceClient,
+    query: &AuditLogQuery<K>,
+) -> Result<impl Stream<Item = anyhow::Result<K>> + Send + 'static, AuditLogQueryError> {
+    let state = PageState {
+        client,
+        requests: query.requests()?,
+        current: None,
+    };
+
+    Ok(stream::try_unfold(state, |mut state| async move {
+        loop {
+            if let Some(current) = state.current.as_mut() {
+                match current.message().await? {
+                    Some(resp) => {
+                        if let Some(entry) = resp.entry.and_then(K::from_entry) {
+                            return Ok(Some((entry, state)));
+                        }
+                    }
+                    None => state.current = None,
+                }
+                continue;
+            }
+
+            let Some(request) = state.requests.next() else {
+                return Ok(None);
+            };
+            let response = state
+                .client
+                .list_audit_log_entries(request)
+                .await
+                .map_err(|e| anyhow::Error::new(e).context("Failed to get audit logs"))?;
+            state.current = Some(response.into_inner());
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_windows() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let query = AuditLogQuery::decisions()
+            .between(start, start + Duration::from_secs(150 * 60))
+            .with_page_duration(Duration::from_secs(60 * 60));
+        let requests: Vec<_> = query.requests().unwrap().collect();
+        assert_eq!(requests.len(), 3);
+
+        let ranges: Vec<(i64, i64)> = requests
+            .iter()
+            .map(|r| match r.filter.as_ref().unwrap() {
+                Filter::Between(TimeRange {
+                    start: Some(s),
+                    end: Some(e),
+                }) => (s.seconds, e.seconds),
+                f => panic!("unexpected filter {f:?}"),
+            })
+            .collect();
+        let base = 1_700_000_000;
+        assert_eq!(
+            ranges,
+            [
+                (base, base + 3599),
+                (base + 3600, base + 7199),
+                (base + 7200, base + 9000)
+            ]
+        );
+        assert!(requests.iter().all(|r| r.kind() == Kind::Decision));
+
+        assert_eq!(
+            AuditLogQuery::access().tail(1001).validate(),
+            Err(AuditLogQueryError::InvalidTail(1001))
+        );
+        assert_eq!(
+            AuditLogQuery::access().validate(),
+            Err(AuditLogQueryError::MissingFilter)
+        );
+        assert_eq!(
+            AuditLogQuery::access()
+                .between(start, start - Duration::from_secs(1))
+                .validate(),
+            Err(AuditLogQueryError::InvalidTimeRange)
+        );
+        assert_eq!(
+            AuditLogQuery::access()
+                .between(start, start)
+                .with_page_duration(Duration::MAX)
+                .validate(),
+            Err(AuditLogQueryError::PageDurationOverflow)
+        );
+
+        // Windows of a long range are not materialised up front
+        let year = AuditLogQuery::access()
+            .between(start, start + Duration::from_secs(365 * 24 * 60 * 60))
+            .with_page_duration(Duration::from_secs(1));
+        assert_eq!(year.requests().unwrap().take(2).count(), 2);
+    }
+}
@@ -0,0 +1,483 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Static checks of a [`PolicySet`] that catch broken references before the policies reach a PDP.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::genpb::cerbos::policy::v1::{
+    policy::PolicyType, schemas::Schema, Constants, Policy, Variables,
+};
+
+use crate::sdk::cel;
+
+use super::model::{PolicySet, SchemaSet};
+use super::reconcile::policy_id;
+
+const SCHEMA_URL_PREFIX: &str = "cerbos:///";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// The check that produced a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    MissingDerivedRoles,
+    MissingVariables,
+    MissingConstants,
+    DuplicatePolicy,
+    RuleWithoutRoles,
+    MissingSchema,
+    MissingParentScope,
+    InvalidExpression,
+}
+
+/// A problem found in a policy.
+///
+/// `path` is the offending field, for example `resourcePolicy.importDerivedRoles[0]`. `file` is
+/// the name the policy was loaded from, if it was read from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: LintKind,
+    pub severity: Severity,
+    pub file: Option<String>,
+    pub policy: String,
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}: ")?;
+        }
+        write!(
+            f,
+            "{}: {} ({}): {}",
+            self.severity, self.path, self.policy, self.message
+        )
+    }
+}
+
+/// Check the enabled policies of `policies` for:
+///
+/// - imports of derived roles, variables or constants that are not defined in the set;
+/// - policies with the same ID, i.e. the same kind, name, version and scope;
+/// - resource policy rules and derived role definitions without any roles;
+/// - `cerbos:///` schema references that are not in `schemas`;
+/// - scoped resource and principal policies without a policy for each parent scope;
+/// - condition, variable and output expressions that are not valid CEL.
+///
+/// Missing parent scopes are reported as warnings, everything else as errors. Diagnostics are
+/// returned in the order of the policies in the set.
+///
+/// ```rust,no_run
+/// # use cerbos::sdk::admin::{lint::{lint, Severity}, model::PolicySet};
+/// # fn example() -> anyhow::Result<()> {
+/// let (policies, schemas) = PolicySet::from_directory("policies")?;
+/// let diagnostics = lint(&policies, &schemas);
+/// for diagnostic in &diagnostics {
+///     eprintln!("{diagnostic}");
+/// }
+/// if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+///     anyhow::bail!("policies have errors");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn lint(policies: &PolicySet, schemas: &SchemaSet) -> Vec<Diagnostic> {
+    let enabled: Vec<(usize, &Policy)> = policies
+        .get_policies()
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !p.disabled)
+        .collect();
+
+    let mut linter = Linter {
+        derived_roles: HashSet::new(),
+        variables: HashSet::new(),
+        constants: HashSet::new(),
+        scopes: HashSet::new(),
+        schemas: schemas
+            .get_schemas()
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect(),
+        diagnostics: Vec::new(),
+    };
+    for (_, policy) in &enabled {
+        match &policy.policy_type {
+            Some(PolicyType::DerivedRoles(p)) => {
+                linter.derived_roles.insert(&p.name);
+            }
+            Some(PolicyType::ExportVariables(p)) => {
+                linter.variables.insert(&p.name);
+            }
+            Some(PolicyType::ExportConstants(p)) => {
+                linter.constants.insert(&p.name);
+            }
+            Some(PolicyType::ResourcePolicy(p)) => {
+                linter
+                    .scopes
+                    .insert(("resource", &p.resource, version(&p.version), &p.scope));
+            }
+            Some(PolicyType::PrincipalPolicy(p)) => {
+                linter
+                    .scopes
+                    .insert(("principal", &p.principal, version(&p.version), &p.scope));
+            }
+            _ => {}
+        }
+    }
+
+    let mut ids: HashMap<String, Option<&str>> = HashMap::new();
+    for (index, policy) in enabled {
+        let Ok(id) = policy_id(policy) else {
+            continue;
+        };
+        let mut cx = Context {
+            linter: &mut linter,
+            file: policies.file_name(index).or_else(|| {
+                policy
+                    .metadata
+                    .as_ref()
+                    .map(|m| m.source_file.as_str())
+                    .filter(|f| !f.is_empty())
+            }),
+            policy: &id,
+        };
+
+        match ids.get(&id) {
+            Some(first) => {
+                let message = match first {
+                    Some(file) => format!("policy {id} is already defined in {file}"),
+                    None => format!("policy {id} is already defined"),
+                };
+                // The copy is not linted, so that its findings are not reported twice.
+                cx.report(
+                    LintKind::DuplicatePolicy,
+                    Severity::Error,
+                    root(policy).to_string(),
+                    message,
+                );
+            }
+            None => {
+                ids.insert(id.clone(), cx.file);
+                cx.lint_policy(policy);
+            }
+        }
+    }
+    linter.diagnostics
+}
+
+// Policy kind, name, version and scope of the resource and principal policies in the set.
+type ScopeKey<'a> = (&'static str, &'a str, &'a str, &'a str);
+
+struct Linter<'a> {
+    derived_roles: HashSet<&'a str>,
+    variables: HashSet<&'a str>,
+    constants: HashSet<&'a str>,
+    scopes: HashSet<ScopeKey<'a>>,
+    schemas: HashSet<&'a str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// The policy being linted.
+struct Context<'l, 'a> {
+    linter: &'l mut Linter<'a>,
+    file: Option<&'a str>,
+    policy: &'l str,
+}
+
+impl Context<'_, '_> {
+    fn report(&mut self, kind: LintKind, severity: Severity, path: String, message: String) {
+        self.linter.diagnostics.push(Diagnostic {
+            kind,
+            severity,
+            file: self.file.map(str::to_string),
+            policy: self.policy.to_string(),
+            path,
+            message,
+        });
+    }
+
+    fn lint_policy(&mut self, policy: &Policy) {
+        for err in cel::check_policy(policy) {
+            self.report(
+                LintKind::InvalidExpression,
+                Severity::Error,
+                err.path,
+                err.error.to_string(),
+            );
+        }
+        let root = root(policy);
+        match &policy.policy_type {
+            Some(PolicyType::ResourcePolicy(p)) => {
+                for (i, name) in p.import_derived_roles.iter().enumerate() {
+                    if !self.linter.derived_roles.contains(name.as_str()) {
+                        self.report(
+                            LintKind::MissingDerivedRoles,
+                            Severity::Error,
+                            format!("{root}.importDerivedRoles[{i}]"),
+                            format!("derived roles {name:?} are not defined"),
+                        );
+                    }
+                }
+                for (i, rule) in p.rules.iter().enumerate() {
+                    if rule.roles.is_empty() && rule.derived_roles.is_empty() {
+                        self.report(
+                            LintKind::RuleWithoutRoles,
+                            Severity::Error,
+                            format!("{root}.rules[{i}]"),
+                            "rule has no roles or derived roles".to_string(),
+                        );
+                    }
+                }
+                if let Some(schemas) = &p.schemas {
+                    self.lint_schema(root, "principalSchema", schemas.principal_schema.as_ref());
+                    self.lint_schema(root, "resourceSchema", schemas.resource_schema.as_ref());
+                }
+                self.lint_variables(root, p.variables.as_ref());
+                self.lint_constants(root, p.constants.as_ref());
+                self.lint_scope(root, "resource", &p.resource, &p.version, &p.scope);
+            }
+            Some(PolicyType::PrincipalPolicy(p)) => {
+                self.lint_variables(root, p.variables.as_ref());
+                self.lint_constants(root, p.constants.as_ref());
+                self.lint_scope(root, "principal", &p.principal, &p.version, &p.scope);
+            }
+            Some(PolicyType::DerivedRoles(p)) => {
+                for (i, def) in p.definitions.iter().enumerate() {
+                    if def.parent_roles.is_empty() {
+                        self.report(
+                            LintKind::RuleWithoutRoles,
+                            Severity::Error,
+                            format!("{root}.definitions[{i}]"),
+                            format!("derived role {:?} has no parent roles", def.name),
+                        );
+                    }
+                }
+                self.lint_variables(root, p.variables.as_ref());
+                self.lint_constants(root, p.constants.as_ref());
+            }
+            _ => {}
+        }
+    }
+
+    fn lint_variables(&mut self, root: &str, variables: Option<&Variables>) {
+        for (i, name) in variables.into_iter().flat_map(|v| &v.import).enumerate() {
+            if !self.linter.variables.contains(name.as_str()) {
+                self.report(
+                    LintKind::MissingVariables,
+                    Severity::Error,
+                    format!("{root}.variables.import[{i}]"),
+                    format!("exported variables {name:?} are not defined"),
+                );
+            }
+        }
+    }
+
+    fn lint_constants(&mut self, root: &str, constants: Option<&Constants>) {
+        for (i, name) in constants.into_iter().flat_map(|c| &c.import).enumerate() {
+            if !self.linter.constants.contains(name.as_str()) {
+                self.report(
+                    LintKind::MissingConstants,
+                    Severity::Error,
+                    format!("{root}.constants.import[{i}]"),
+                    format!("exported constants {name:?} are not defined"),
+                );
+            }
+        }
+    }
+
+    // Only references to the schemas stored alongside the policies can be checked.
+    fn lint_schema(&mut self, root: &str, field: &str, schema: Option<&Schema>) {
+        let Some(schema) = schema else {
+            return;
+        };
+        let Some(id) = schema.r#ref.strip_prefix(SCHEMA_URL_PREFIX) else {
+            return;
+        };
+        if !self.linter.schemas.contains(id) {
+            self.report(
+                LintKind::MissingSchema,
+                Severity::Error,
+                format!("{root}.schemas.{field}.ref"),
+                format!("schema {:?} is not in the schema set", schema.r#ref),
+            );
+        }
+    }
+
+    fn lint_scope(&mut self, root: &str, kind: &'static str, name: &str, ver: &str, scope: &str) {
+        let mut parent = scope;
+        while !parent.is_empty() {
+            parent = parent.rsplit_once('.').map_or("", |(p, _)| p);
+            if !self
+                .linter
+                .scopes
+                .contains(&(kind, name, version(ver), parent))
+            {
+                let parent_name = if parent.is_empty() { "root" } else { parent };
+                self.report(
+                    LintKind::MissingParentScope,
+                    Severity::Warning,
+                    format!("{root}.scope"),
+                    format!(
+                        "no {kind} policy for {name:?} version {:?} in parent scope {parent_name:?}",
+                        version(ver)
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn version(version: &str) -> &str {
+    if version.is_empty() {
+        "default"
+    } else {
+        version
+    }
+}
+
+fn root(policy: &Policy) -> &'static str {
+    match &policy.policy_type {
+        Some(PolicyType::ResourcePolicy(_)) => "resourcePolicy",
+        Some(PolicyType::PrincipalPolicy(_)) => "principalPolicy",
+        Some(PolicyType::DerivedRoles(_)) => "derivedRoles",
+        Some(PolicyType::ExportVariables(_)) => "exportVariables",
+        Some(PolicyType::ExportConstants(_)) => "exportConstants",
+        Some(PolicyType::RolePolicy(_)) => "rolePolicy",
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdk::deser::read_policies;
+
+    #[test]
+    fn test_lint() -> anyhow::Result<()> {
+        let store = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/store");
+        let (policies, schemas) = PolicySet::from_directory(store)?;
+        assert_eq!(lint(&policies, &schemas), []);
+
+        let yaml = r#"---
+apiVersion: api.cerbos.dev/v1
+derivedRoles:
+  name: common_roles
+  definitions:
+    - name: owner
+      parentRoles: []
+      condition:
+        match:
+          expr: R.attr.owner ==
+---
+apiVersion: api.cerbos.dev/v1
+resourcePolicy:
+  resource: album
+  version: default
+  scope: acme.hr
+  importDerivedRoles: [common_roles, missing_roles]
+  variables:
+    import: [missing_variables]
+  constants:
+    import: [missing_constants]
+  schemas:
+    principalSchema:
+      ref: cerbos:///principal.json
+    resourceSchema:
+      ref: cerbos:///album.json
+  rules:
+    - actions: [view]
+      effect: EFFECT_ALLOW
+      roles: [user]
+    - actions: [delete]
+      effect: EFFECT_ALLOW
+---
+apiVersion: api.cerbos.dev/v1
+resourcePolicy:
+  resource: album
+  version: default
+  scope: acme.hr
+  rules: []
+"#;
+        let mut policies = PolicySet::new();
+        policies.add_policies(read_policies(yaml.as_bytes())?);
+        let diagnostics = lint(&policies, &schemas);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.kind, d.severity, d.path.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    LintKind::InvalidExpression,
+                    Severity::Error,
+                    "derivedRoles.definitions[0].condition.match.expr"
+                ),
+                (
+                    LintKind::RuleWithoutRoles,
+                    Severity::Error,
+                    "derivedRoles.definitions[0]"
+                ),
+                (
+                    LintKind::MissingDerivedRoles,
+                    Severity::Error,
+                    "resourcePolicy.importDerivedRoles[1]"
+                ),
+                (
+                    LintKind::RuleWithoutRoles,
+                    Severity::Error,
+                    "resourcePolicy.rules[1]"
+                ),
+                (
+                    LintKind::MissingSchema,
+                    Severity::Error,
+                    "resourcePolicy.schemas.resourceSchema.ref"
+                ),
+                (
+                    LintKind::MissingVariables,
+                    Severity::Error,
+                    "resourcePolicy.variables.import[0]"
+                ),
+                (
+                    LintKind::MissingConstants,
+                    Severity::Error,
+                    "resourcePolicy.constants.import[0]"
+                ),
+                (
+                    LintKind::MissingParentScope,
+                    Severity::Warning,
+                    "resourcePolicy.scope"
+                ),
+                (
+                    LintKind::MissingParentScope,
+                    Severity::Warning,
+                    "resourcePolicy.scope"
+                ),
+                (LintKind::DuplicatePolicy, Severity::Error, "resourcePolicy"),
+            ]
+        );
+        assert_eq!(
+            diagnostics[7].to_string(),
+            r#"warning: resourcePolicy.scope (resource.album.vdefault/acme.hr): no resource policy for "album" version "default" in parent scope "acme""#
+        );
+        Ok(())
+    }
+}
@@ -2,34 +2,38 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::genpb::cerbos::{
-    policy::v1::Policy,
+    policy::v1::{policy::PolicyType, Policy},
     request::v1::{
         AddOrUpdatePolicyRequest, AddOrUpdateSchemaRequest, DeleteSchemaRequest,
         DisablePolicyRequest, EnablePolicyRequest, GetPolicyRequest, GetSchemaRequest,
         InspectPoliciesRequest, ListAuditLogEntriesRequest, ListPoliciesRequest,
         ListSchemasRequest, ReloadStoreRequest,
     },
-    response::v1::{InspectPoliciesResponse, ListAuditLogEntriesResponse},
+    response::v1::ListAuditLogEntriesResponse,
     schema::v1::Schema,
     svc::v1::cerbos_admin_service_client::CerbosAdminServiceClient,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use base64::prelude::{Engine as _, BASE64_STANDARD};
-use model::{FilterOptions, PolicySet, SchemaSet};
-use std::time::Duration;
+use futures_util::{stream, StreamExt};
+use model::{
+    FilterOptions, InspectedPolicies, PolicySet, SchemaSet, UploadFailure, UploadOptions,
+    UploadReport,
+};
+use std::{future::Future, time::Duration};
 use tonic::{
     metadata::MetadataValue,
     service::{interceptor::InterceptedService, Interceptor},
     transport::Channel,
-    Request, Status,
+    Code, Request, Status,
 };
 
 use super::CerbosClientOptions;
 
-const ADD_POLICY_BATCH_SIZE: usize = 10;
-const ADD_SCHEMA_BATCH_SIZE: usize = 10;
-
+pub mod audit;
+pub mod lint;
 pub mod model;
+pub mod reconcile;
 
 #[derive(Debug, Clone)]
 pub struct BasicAuth {
@@ -43,6 +47,7 @@ impl BasicAuth {
     }
 }
 
+#[derive(Clone)]
 struct CerbosBasicAuthInterceptor {
     request_timeout: Duration,
     auth_header: MetadataValue<tonic::metadata::Ascii>,
@@ -91,23 +96,41 @@ impl CerbosAdminClient {
         MetadataValue::try_from(header_value).with_context(|| "fail to parse metadata value")
     }
     pub async fn add_or_update_policy(&mut self, policies: &PolicySet) -> Result<()> {
+        self.upload_policies(policies, UploadOptions::default())
+            .await?
+            .into_result()
+            .with_context(|| "Failed to add or update policies")
+    }
+    /// Add or update policies in batches, sending up to `options.concurrency` batches at a time.
+    /// Policies are stored in three phases so that they are only sent once the policies they can
+    /// import are stored: exported variables and constants first, then derived roles, then the
+    /// rest. A batch rejected as invalid is retried one policy at a time so that the
+    /// report carries the server's error for each policy that failed.
+    pub async fn upload_policies(
+        &mut self,
+        policies: &PolicySet,
+        options: UploadOptions,
+    ) -> Result<UploadReport> {
         policies.validate()?;
 
-        let all_policies = policies.get_policies();
-
-        // Process policies in batches
-        for chunk in all_policies.chunks(ADD_POLICY_BATCH_SIZE) {
-            let request = AddOrUpdatePolicyRequest {
-                policies: chunk.to_vec(),
-            };
+        let client = &self.client;
+        let send = |policies| {
+            let mut client = client.clone();
+            async move {
+                client
+                    .add_or_update_policy(AddOrUpdatePolicyRequest { policies })
+                    .await
+                    .map(|_| ())
+            }
+        };
 
-            self.client
-                .add_or_update_policy(request)
-                .await
-                .with_context(|| "Failed to add or update policies")?;
+        let mut report = UploadReport::default();
+        for phase in upload_phases(policies.get_policies()) {
+            let phase_report = upload(phase, &options, &send).await;
+            report.succeeded.extend(phase_report.succeeded);
+            report.failed.extend(phase_report.failed);
         }
-
-        Ok(())
+        Ok(report)
     }
     pub async fn list_policies(&mut self, options: Option<FilterOptions>) -> Result<Vec<String>> {
         let options = options.unwrap_or_default();
@@ -131,7 +154,7 @@ impl CerbosAdminClient {
     pub async fn inspect_policies(
         &mut self,
         options: Option<FilterOptions>,
-    ) -> Result<InspectPoliciesResponse> {
+    ) -> Result<InspectedPolicies> {
         let options = options.unwrap_or_default();
 
         let request = InspectPoliciesRequest {
@@ -148,7 +171,7 @@ impl CerbosAdminClient {
             .await
             .with_context(|| "Failed to inspect policies")?;
 
-        Ok(response.into_inner())
+        Ok(response.into_inner().into())
     }
     pub async fn get_policy(&mut self, ids: Vec<String>) -> Result<Vec<Policy>> {
         let request = GetPolicyRequest { id: ids };
@@ -184,21 +207,33 @@ impl CerbosAdminClient {
         Ok(response.into_inner().enabled_policies)
     }
     pub async fn add_or_update_schema(&mut self, schemas: &SchemaSet) -> Result<()> {
-        let all_schemas = schemas.get_schemas();
-
-        // Process schemas in batches
-        for chunk in all_schemas.chunks(ADD_SCHEMA_BATCH_SIZE) {
-            let request = AddOrUpdateSchemaRequest {
-                schemas: chunk.to_vec(),
-            };
-
-            self.client
-                .add_or_update_schema(request)
-                .await
-                .with_context(|| "Failed to add or update schemas")?;
-        }
-
-        Ok(())
+        self.upload_schemas(schemas, UploadOptions::default())
+            .await?
+            .into_result()
+            .with_context(|| "Failed to add or update schemas")
+    }
+    /// Add or update schemas in batches. See [`CerbosAdminClient::upload_policies`].
+    pub async fn upload_schemas(
+        &mut self,
+        schemas: &SchemaSet,
+        options: UploadOptions,
+    ) -> Result<UploadReport> {
+        let items = schemas
+            .get_schemas()
+            .iter()
+            .map(|s| (s.id.clone(), s.clone()))
+            .collect();
+        let client = &self.client;
+        Ok(upload(items, &options, |schemas| {
+            let mut client = client.clone();
+            async move {
+                client
+                    .add_or_update_schema(AddOrUpdateSchemaRequest { schemas })
+                    .await
+                    .map(|_| ())
+            }
+        })
+        .await)
     }
     pub async fn delete_schema(&mut self, ids: Vec<String>) -> Result<u32> {
         let request = DeleteSchemaRequest { id: ids };
@@ -268,4 +303,191 @@ impl CerbosAdminClient {
 
         Ok(response.into_inner())
     }
+
+    /// Stream the audit log entries matching the query. Time ranges are fetched one page at a
+    /// time.
+    ///
+    /// ```rust,no_run
+    /// # use cerbos::sdk::admin::{audit::AuditLogQuery, CerbosAdminClient};
+    /// # use futures_util::TryStreamExt;
+    /// # async fn example(client: &mut CerbosAdminClient) -> anyhow::Result<()> {
+    /// let mut entries = Box::pin(client.audit_log_entries(&AuditLogQuery::decisions().tail(10))?);
+    /// while let Some(entry) = entries.try_next().await? {
+    ///     println!("{}", entry.call_id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn audit_log_entries<K: audit::AuditLogKind>(
+        &mut self,
+        query: &audit::AuditLogQuery<K>,
+    ) -> Result<impl futures_util::Stream<Item = Result<K>> + Send + 'static> {
+        Ok(audit::entries(self.client.clone(), query)?)
+    }
+}
+
+/// Policies with their IDs, grouped so that each group can only import policies of the groups
+/// before it: exported variables and constants, derived roles, and everything else.
+fn upload_phases(policies: &[Policy]) -> [Vec<(String, Policy)>; 3] {
+    let mut phases: [Vec<_>; 3] = Default::default();
+    for (i, policy) in policies.iter().enumerate() {
+        let id = reconcile::policy_id(policy).unwrap_or_else(|_| format!("policy #{i}"));
+        let phase = match policy.policy_type {
+            Some(PolicyType::ExportVariables(_) | PolicyType::ExportConstants(_)) => 0,
+            Some(PolicyType::DerivedRoles(_)) => 1,
+            _ => 2,
+        };
+        phases[phase].push((id, policy.clone()));
+    }
+    phases
+}
+
+async fn upload<T, F, Fut>(
+    items: Vec<(String, T)>,
+    options: &UploadOptions,
+    send: F,
+) -> UploadReport
+where
+    T: Clone,
+    F: Fn(Vec<T>) -> Fut,
+    Fut: Future<Output = std::result::Result<(), Status>>,
+{
+    let batches = items.chunks(options.batch_size.max(1)).map(|batch| {
+        let request = send(batch.iter().map(|(_, item)| item.clone()).collect());
+        async move { (batch, request.await) }
+    });
+    let results: Vec<_> = stream::iter(batches)
+        .buffered(options.concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut report = UploadReport::default();
+    for (batch, result) in results {
+        match result {
+            Ok(()) => report
+                .succeeded
+                .extend(batch.iter().map(|(id, _)| id.clone())),
+            // Only the server's verdict on the contents is worth narrowing down to single items;
+            // other errors would fail the same way for each of them.
+            Err(status)
+                if batch.len() > 1
+                    && matches!(
+                        status.code(),
+                        Code::InvalidArgument | Code::FailedPrecondition
+                    ) =>
+            {
+                for (id, item) in batch {
+                    match send(vec![item.clone()]).await {
+                        Ok(()) => report.succeeded.push(id.clone()),
+                        Err(status) => report.failed.push(UploadFailure {
+                            id: id.clone(),
+                            status,
+                        }),
+                    }
+                }
+            }
+            Err(status) => report
+                .failed
+                .extend(batch.iter().map(|(id, _)| UploadFailure {
+                    id: id.clone(),
+                    status: status.clone(),
+                })),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_upload_isolates_failures() {
+        let items = ["a", "b", "bad", "d", "e"]
+            .map(|id| (id.to_string(), id.to_string()))
+            .to_vec();
+        let requests = Mutex::new(Vec::new());
+        let options = UploadOptions::new().with_batch_size(2).with_concurrency(2);
+
+        let report = upload(items, &options, |batch: Vec<String>| {
+            requests.lock().unwrap().push(batch.len());
+            let failed = batch.iter().any(|id| id == "bad");
+            async move {
+                if failed {
+                    Err(Status::invalid_argument("invalid policy"))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(report.succeeded, ["a", "b", "d", "e"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].id, "bad");
+        assert_eq!(report.failed[0].status.message(), "invalid policy");
+        assert_eq!(*requests.lock().unwrap(), [2, 2, 1, 1, 1]);
+        assert!(report.into_result().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upload_phases() {
+        use crate::sdk::builder::{
+            DerivedRolesBuilder, ExportConstantsBuilder, ExportVariablesBuilder,
+            ResourcePolicyBuilder,
+        };
+
+        let policies = [
+            ResourcePolicyBuilder::new("album", "default").build(),
+            DerivedRolesBuilder::new("common_roles").build(),
+            ExportVariablesBuilder::new("common_vars").build(),
+            ResourcePolicyBuilder::new("photo", "default").build(),
+            ExportConstantsBuilder::new("common_consts").build(),
+        ];
+        let batches = Mutex::new(Vec::new());
+        let options = UploadOptions::new().with_batch_size(2).with_concurrency(2);
+        let send = |batch: Vec<Policy>| {
+            let ids: Vec<_> = batch
+                .iter()
+                .map(|p| reconcile::policy_id(p).unwrap())
+                .collect();
+            batches.lock().unwrap().push(ids);
+            async { Ok(()) }
+        };
+        for phase in upload_phases(&policies) {
+            upload(phase, &options, &send).await;
+        }
+
+        assert_eq!(
+            *batches.lock().unwrap(),
+            [
+                vec![
+                    "export_variables.common_vars",
+                    "export_constants.common_consts"
+                ],
+                vec!["derived_roles.common_roles"],
+                vec!["resource.album.vdefault", "resource.photo.vdefault"],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_does_not_split_on_unavailable() {
+        let items = ["a", "b", "c"]
+            .map(|id| (id.to_string(), id.to_string()))
+            .to_vec();
+        let requests = Mutex::new(0);
+        let options = UploadOptions::new().with_batch_size(3);
+
+        let report = upload(items, &options, |_: Vec<String>| {
+            *requests.lock().unwrap() += 1;
+            async { Err(Status::unavailable("down")) }
+        })
+        .await;
+
+        assert!(report.succeeded.is_empty());
+        assert_eq!(report.failed.len(), 3);
+        assert_eq!(*requests.lock().unwrap(), 1);
+    }
 }
@@ -2,8 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
     fs::File,
     io::{BufReader, Read},
+    path::{Path, PathBuf},
 };
 
 use crate::{
@@ -14,18 +17,40 @@ use crate::{
                 list_audit_log_entries_request::{Filter, Kind, TimeRange},
                 ListAuditLogEntriesRequest,
             },
+            response::v1::{inspect_policies_response, InspectPoliciesResponse},
             schema::v1::Schema,
         },
-        google::protobuf::Timestamp,
+        google::protobuf::{Timestamp, Value},
     },
-    sdk::deser::read_policy,
+    sdk::deser::read_policies,
 };
 use anyhow::{Context, Result};
+use thiserror::Error;
+
+pub use crate::genpb::cerbos::response::v1::inspect_policies_response::{
+    attribute::Kind as AttributeKind, constant::Kind as ConstantKind,
+    derived_role::Kind as DerivedRoleKind, variable::Kind as VariableKind,
+};
+use walkdir::{DirEntry, WalkDir};
+
+const SCHEMAS_DIR: &str = "_schemas";
+const TESTS_DIRS: [&str; 2] = ["tests", "testdata"];
+const POLICY_EXTENSIONS: [&str; 3] = ["yaml", "yml", "json"];
 
 /// Policy set container for managing multiple policies
 #[derive(Debug, Clone, Default)]
 pub struct PolicySet {
     policies: Vec<Policy>,
+    // Documents that policies were read from, keyed by their index in `policies`.
+    sources: HashMap<usize, PolicySource>,
+    // Names of the files that policies were read from, keyed by their index in `policies`.
+    files: HashMap<usize, String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PolicySource {
+    pub(crate) file_name: Option<String>,
+    pub(crate) contents: Vec<u8>,
 }
 
 /// Schema set container for managing multiple schemas
@@ -34,6 +59,25 @@ pub struct SchemaSet {
     schemas: Vec<Schema>,
 }
 
+/// Errors for the files of a directory that could not be loaded, in walk order.
+#[derive(Error, Debug)]
+#[error("{}", DirectoryErrors(.errors))]
+pub struct LoadDirectoryError {
+    pub errors: Vec<(PathBuf, anyhow::Error)>,
+}
+
+struct DirectoryErrors<'a>(&'a [(PathBuf, anyhow::Error)]);
+
+impl fmt::Display for DirectoryErrors<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to load {} file(s)", self.0.len())?;
+        for (path, err) in self.0 {
+            write!(f, "; {}: {err:#}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
 /// Options for filtering policies and schemas
 #[derive(Debug, Clone, Default)]
 pub struct FilterOptions {
@@ -74,20 +118,165 @@ impl PolicySet {
         Ok(())
     }
 
+    /// Add the policies of a JSON or YAML file. YAML files may contain multiple documents.
     pub fn add_policy_from_file(&mut self, policy_path: std::path::PathBuf) -> Result<()> {
-        let file = File::open(&policy_path)
+        let file_name = policy_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned());
+        self.add_policy_from_path(&policy_path, file_name)
+    }
+
+    /// Load a policy repository laid out like a Cerbos disk store.
+    ///
+    /// Policies are read from all YAML and JSON files under `dir`, except hidden files, test
+    /// suites (`*_test.yaml`, `tests/` and `testdata/`) and `_schemas/`. JSON files under
+    /// `_schemas/` are returned as a [`SchemaSet`] with their path relative to `_schemas/` as the
+    /// ID, so that they can be referenced as `cerbos:///<id>` from policies.
+    ///
+    /// All files are attempted and every failure is reported in the returned error.
+    pub fn from_directory(
+        dir: impl AsRef<Path>,
+    ) -> std::result::Result<(PolicySet, SchemaSet), LoadDirectoryError> {
+        let dir = dir.as_ref();
+        let mut policies = PolicySet::new();
+        let mut schemas = SchemaSet::new();
+        let mut errors = Vec::new();
+
+        let walker = WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !is_hidden(e));
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    let path = err.path().unwrap_or(dir).to_path_buf();
+                    errors.push((path, err.into()));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let Ok(rel) = path.strip_prefix(dir) else {
+                continue;
+            };
+
+            let result = if let Ok(id) = rel.strip_prefix(SCHEMAS_DIR) {
+                if !has_extension(path, &["json"]) {
+                    continue;
+                }
+                schemas.add_schema_from_file(path.to_path_buf(), to_slash(id))
+            } else {
+                if is_test_path(rel) || !has_extension(path, &POLICY_EXTENSIONS) {
+                    continue;
+                }
+                policies.add_policy_from_path(path, Some(to_slash(rel)))
+            };
+            if let Err(err) = result {
+                errors.push((path.to_path_buf(), err));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok((policies, schemas))
+        } else {
+            Err(LoadDirectoryError { errors })
+        }
+    }
+
+    fn add_policy_from_path(
+        &mut self,
+        policy_path: &Path,
+        file_name: Option<String>,
+    ) -> Result<()> {
+        let file = File::open(policy_path)
             .with_context(|| format!("filed to open {}", policy_path.display()))?;
-        let policy = read_policy(BufReader::new(file))
+        let mut contents = Vec::new();
+        BufReader::new(file)
+            .read_to_end(&mut contents)
+            .with_context(|| format!("failed to read {}", policy_path.display()))?;
+        let policies = read_policies(contents.as_slice())
             .with_context(|| format!("failed to read policy from {}", policy_path.display()))?;
-        self.add_policy(policy);
+        self.add_policies_with_source(
+            policies,
+            PolicySource {
+                file_name,
+                contents,
+            },
+        );
         Ok(())
     }
 
-    pub fn add_policy_from_reader(&mut self, reader: impl Read) -> Result<()> {
-        let policy = read_policy(reader)?;
-        self.add_policy(policy);
+    /// Add the policies read from a JSON or YAML source. YAML sources may contain multiple
+    /// documents.
+    pub fn add_policy_from_reader(&mut self, mut reader: impl Read) -> Result<()> {
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents)?;
+        let policies = read_policies(contents.as_slice())?;
+        self.add_policies_with_source(
+            policies,
+            PolicySource {
+                file_name: None,
+                contents,
+            },
+        );
         Ok(())
     }
+
+    // The source document is only kept for single-policy documents, so that a document is never
+    // uploaded to the playground more than once.
+    fn add_policies_with_source(&mut self, policies: Vec<Policy>, source: PolicySource) {
+        if let Some(file_name) = &source.file_name {
+            let start = self.policies.len();
+            self.files
+                .extend((start..start + policies.len()).map(|i| (i, file_name.clone())));
+        }
+        if let [_] = policies.as_slice() {
+            self.sources.insert(self.policies.len(), source);
+        }
+        self.policies.extend(policies);
+    }
+
+    /// Original document of the policy at `index`, if it was read from a file or reader.
+    pub(crate) fn source(&self, index: usize) -> Option<&PolicySource> {
+        self.sources.get(&index)
+    }
+
+    /// Name of the file the policy at `index` was read from.
+    pub(crate) fn file_name(&self, index: usize) -> Option<&str> {
+        self.files.get(&index).map(String::as_str)
+    }
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with('.')
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e))
+}
+
+fn is_test_path(rel: &Path) -> bool {
+    let in_tests_dir = rel
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|c| TESTS_DIRS.iter().any(|d| c.as_os_str() == *d));
+    let is_test_file = rel
+        .file_stem()
+        .is_some_and(|s| s.to_string_lossy().ends_with("_test"));
+    in_tests_dir || is_test_file
+}
+
+fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
 }
 
 impl SchemaSet {
@@ -130,6 +319,80 @@ impl SchemaSet {
     }
 }
 
+/// Options for bulk uploads of policies and schemas
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    /// Number of policies or schemas sent in one request.
+    pub batch_size: usize,
+    /// Number of requests in flight at the same time. Policies importing derived roles or exported
+    /// variables and constants are only sent once all of those have been stored.
+    pub concurrency: usize,
+}
+
+/// Outcome of a bulk upload of policies or schemas.
+#[derive(Debug, Clone, Default)]
+pub struct UploadReport {
+    /// IDs of the policies or schemas that were stored.
+    pub succeeded: Vec<String>,
+    pub failed: Vec<UploadFailure>,
+}
+
+/// A policy or schema rejected by the server.
+#[derive(Debug, Clone)]
+pub struct UploadFailure {
+    pub id: String,
+    pub status: tonic::Status,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 10,
+            concurrency: 1,
+        }
+    }
+}
+
+impl UploadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+}
+
+impl UploadReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Fail with the server's error for every rejected policy or schema.
+    pub fn into_result(self) -> Result<()> {
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+        let errors: Vec<String> = self
+            .failed
+            .iter()
+            .map(|f| format!("{}: {}", f.id, f.status.message()))
+            .collect();
+        anyhow::bail!(
+            "{} of {} failed: {}",
+            self.failed.len(),
+            self.failed.len() + self.succeeded.len(),
+            errors.join("; ")
+        )
+    }
+}
+
 impl FilterOptions {
     pub fn new() -> Self {
         Self::default()
@@ -188,3 +451,272 @@ impl ListAuditLogEntriesRequest {
         self
     }
 }
+
+/// Policies returned by [`inspect_policies`](super::CerbosAdminClient::inspect_policies), keyed by
+/// policy ID.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InspectedPolicies {
+    policies: BTreeMap<String, InspectedPolicy>,
+}
+
+/// What a policy defines and refers to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InspectedPolicy {
+    pub policy_id: String,
+    /// Actions the policy has rules for, as written in the rules. Wildcards such as `view:*` are
+    /// not expanded.
+    pub actions: Vec<String>,
+    pub derived_roles: Vec<InspectedDerivedRole>,
+    pub variables: Vec<InspectedVariable>,
+    pub constants: Vec<InspectedConstant>,
+    pub attributes: Vec<InspectedAttribute>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectedDerivedRole {
+    pub name: String,
+    pub kind: DerivedRoleKind,
+    /// Policy that defines the derived role, if it is not defined locally.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectedVariable {
+    pub name: String,
+    /// Expression the variable is bound to.
+    pub expr: String,
+    pub kind: VariableKind,
+    /// Policy that exports the variable, if it is not defined locally.
+    pub source: Option<String>,
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectedConstant {
+    pub name: String,
+    pub value: Option<Value>,
+    pub kind: ConstantKind,
+    /// Policy that exports the constant, if it is not defined locally.
+    pub source: Option<String>,
+    pub used: bool,
+}
+
+/// A principal or resource attribute referenced by a policy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InspectedAttribute {
+    pub kind: AttributeKind,
+    pub name: String,
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+impl From<InspectPoliciesResponse> for InspectedPolicies {
+    fn from(response: InspectPoliciesResponse) -> Self {
+        let policies = response
+            .results
+            .into_iter()
+            .map(|(id, r)| {
+                let policy = InspectedPolicy {
+                    policy_id: id.clone(),
+                    derived_roles: r
+                        .derived_roles
+                        .into_iter()
+                        .map(|d| InspectedDerivedRole {
+                            kind: d.kind(),
+                            name: d.name,
+                            source: non_empty(d.source),
+                        })
+                        .collect(),
+                    variables: r
+                        .variables
+                        .into_iter()
+                        .map(|v| InspectedVariable {
+                            kind: v.kind(),
+                            name: v.name,
+                            expr: v.value,
+                            source: non_empty(v.source),
+                            used: v.used,
+                        })
+                        .collect(),
+                    constants: r
+                        .constants
+                        .into_iter()
+                        .map(|c| InspectedConstant {
+                            kind: c.kind(),
+                            name: c.name,
+                            value: c.value,
+                            source: non_empty(c.source),
+                            used: c.used,
+                        })
+                        .collect(),
+                    attributes: r
+                        .attributes
+                        .into_iter()
+                        .map(
+                            |a: inspect_policies_response::Attribute| InspectedAttribute {
+                                kind: a.kind(),
+                                name: a.name,
+                            },
+                        )
+                        .collect(),
+                    actions: r.actions,
+                };
+                (id, policy)
+            })
+            .collect();
+        Self { policies }
+    }
+}
+
+impl InspectedPolicies {
+    pub fn get(&self, policy_id: &str) -> Option<&InspectedPolicy> {
+        self.policies.get(policy_id)
+    }
+
+    /// Policies ordered by ID.
+    pub fn iter(&self) -> impl Iterator<Item = &InspectedPolicy> {
+        self.policies.values()
+    }
+
+    pub fn policy_ids(&self) -> impl Iterator<Item = &str> {
+        self.policies.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Policies with rules for `action`.
+    pub fn with_action<'a>(&'a self, action: &'a str) -> impl Iterator<Item = &'a InspectedPolicy> {
+        self.iter()
+            .filter(move |p| p.actions.iter().any(|a| a == action))
+    }
+
+    /// Policies that reference the principal or resource attribute `name`.
+    pub fn with_attribute<'a>(
+        &'a self,
+        kind: AttributeKind,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a InspectedPolicy> {
+        self.iter().filter(move |p| {
+            p.attributes
+                .iter()
+                .any(|a| a.kind == kind && a.name == name)
+        })
+    }
+
+    /// Policies that use the derived role `name`.
+    pub fn with_derived_role<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a InspectedPolicy> {
+        self.iter()
+            .filter(move |p| p.derived_roles.iter().any(|d| d.name == name))
+    }
+
+    /// Policies that define or import the variable `name`.
+    pub fn with_variable<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a InspectedPolicy> {
+        self.iter()
+            .filter(move |p| p.variables.iter().any(|v| v.name == name))
+    }
+
+    /// Policies that define or import the constant `name`.
+    pub fn with_constant<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a InspectedPolicy> {
+        self.iter()
+            .filter(move |p| p.constants.iter().any(|c| c.name == name))
+    }
+}
+
+impl<'a> IntoIterator for &'a InspectedPolicies {
+    type Item = &'a InspectedPolicy;
+    type IntoIter = std::collections::btree_map::Values<'a, String, InspectedPolicy>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.policies.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genpb::cerbos::response::v1::inspect_policies_response::{
+        Attribute, Result as InspectResult, Variable,
+    };
+
+    #[test]
+    fn test_inspected_policies() {
+        let response = InspectPoliciesResponse {
+            results: HashMap::from([
+                (
+                    "resource.leave_request.vdefault".to_string(),
+                    InspectResult {
+                        actions: vec!["approve".to_string(), "view:*".to_string()],
+                        attributes: vec![Attribute {
+                            kind: AttributeKind::ResourceAttribute as i32,
+                            name: "owner".to_string(),
+                        }],
+                        variables: vec![Variable {
+                            name: "is_owner".to_string(),
+                            value: "R.attr.owner == P.id".to_string(),
+                            kind: VariableKind::Imported as i32,
+                            source: "export_variables.common".to_string(),
+                            used: true,
+                        }],
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "principal.donald_duck.vdefault".to_string(),
+                    InspectResult {
+                        actions: vec!["approve".to_string()],
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        };
+
+        let policies = InspectedPolicies::from(response);
+        assert_eq!(policies.len(), 2);
+
+        let approve: Vec<_> = policies
+            .with_action("approve")
+            .map(|p| p.policy_id.as_str())
+            .collect();
+        assert_eq!(
+            approve,
+            [
+                "principal.donald_duck.vdefault",
+                "resource.leave_request.vdefault"
+            ]
+        );
+
+        let owner: Vec<_> = policies
+            .with_attribute(AttributeKind::ResourceAttribute, "owner")
+            .map(|p| p.policy_id.as_str())
+            .collect();
+        assert_eq!(owner, ["resource.leave_request.vdefault"]);
+        assert_eq!(
+            policies
+                .with_attribute(AttributeKind::PrincipalAttribute, "owner")
+                .count(),
+            0
+        );
+
+        let variable = &policies
+            .get("resource.leave_request.vdefault")
+            .unwrap()
+            .variables[0];
+        assert_eq!(variable.kind, VariableKind::Imported);
+        assert_eq!(variable.source.as_deref(), Some("export_variables.common"));
+    }
+}
@@ -0,0 +1,329 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use anyhow::{bail, Result};
+
+use crate::genpb::cerbos::{
+    policy::v1::{policy::PolicyType, role_policy, Metadata, Policy},
+    schema::v1::Schema,
+};
+
+use super::model::{FilterOptions, PolicySet, SchemaSet};
+use super::CerbosAdminClient;
+
+/// Change required to bring the PDP in line with the local policies and schemas.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReconcileAction {
+    AddSchema(String),
+    UpdateSchema(String),
+    AddPolicy(String),
+    UpdatePolicy(String),
+    DisablePolicy(String),
+    DeleteSchema(String),
+}
+
+impl fmt::Display for ReconcileAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconcileAction::AddSchema(id) => write!(f, "+ schema {id}"),
+            ReconcileAction::UpdateSchema(id) => write!(f, "~ schema {id}"),
+            ReconcileAction::AddPolicy(id) => write!(f, "+ policy {id}"),
+            ReconcileAction::UpdatePolicy(id) => write!(f, "~ policy {id}"),
+            ReconcileAction::DisablePolicy(id) => write!(f, "- policy {id} (disable)"),
+            ReconcileAction::DeleteSchema(id) => write!(f, "- schema {id}"),
+        }
+    }
+}
+
+/// Changes produced by [`CerbosAdminClient::plan_reconcile`], in the order they are applied.
+///
+/// Schemas are uploaded before the policies that may reference them and deleted only after
+/// obsolete policies have been disabled. The plan prints one line per change.
+#[derive(Debug, Clone, Default)]
+pub struct ReconcilePlan {
+    actions: Vec<ReconcileAction>,
+    policies: BTreeMap<String, Policy>,
+    schemas: BTreeMap<String, Schema>,
+}
+
+impl ReconcilePlan {
+    pub fn actions(&self) -> &[ReconcileAction] {
+        &self.actions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+impl fmt::Display for ReconcilePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.actions.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        for action in &self.actions {
+            writeln!(f, "{action}")?;
+        }
+        Ok(())
+    }
+}
+
+impl CerbosAdminClient {
+    /// Compare the local policies and schemas with the ones stored in the PDP and list the changes
+    /// needed to make the PDP match: new and modified policies and schemas are uploaded, policies
+    /// missing locally are disabled and schemas missing locally are deleted. Disabled policies in
+    /// the PDP are re-enabled when a local copy exists and otherwise left alone.
+    ///
+    /// ```rust,no_run
+    /// # use cerbos::sdk::admin::{model::PolicySet, CerbosAdminClient};
+    /// # async fn example(client: &mut CerbosAdminClient) -> anyhow::Result<()> {
+    /// let (policies, schemas) = PolicySet::from_directory("policies")?;
+    /// let plan = client.plan_reconcile(&policies, &schemas).await?;
+    /// print!("{plan}");
+    /// client.apply_reconcile(&plan).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn plan_reconcile(
+        &mut self,
+        policies: &PolicySet,
+        schemas: &SchemaSet,
+    ) -> Result<ReconcilePlan> {
+        let mut plan = ReconcilePlan::default();
+
+        for policy in policies.get_policies() {
+            let id = policy_id(policy)?;
+            if plan.policies.insert(id.clone(), policy.clone()).is_some() {
+                bail!("duplicate policy {id}");
+            }
+        }
+        for schema in schemas.get_schemas() {
+            if plan
+                .schemas
+                .insert(schema.id.clone(), schema.clone())
+                .is_some()
+            {
+                bail!("duplicate schema {}", schema.id);
+            }
+        }
+
+        let remote_schema_ids: HashSet<String> = self.list_schemas().await?.into_iter().collect();
+        let existing: Vec<String> = plan
+            .schemas
+            .keys()
+            .filter(|id| remote_schema_ids.contains(*id))
+            .cloned()
+            .collect();
+        let remote_schemas = if existing.is_empty() {
+            Vec::new()
+        } else {
+            self.get_schema(existing).await?
+        };
+        let remote_schemas: BTreeMap<_, _> = remote_schemas
+            .into_iter()
+            .map(|s| (s.id, s.definition))
+            .collect();
+
+        for (id, schema) in &plan.schemas {
+            match remote_schemas.get(id) {
+                None => plan.actions.push(ReconcileAction::AddSchema(id.clone())),
+                Some(definition) if *definition != schema.definition => {
+                    plan.actions.push(ReconcileAction::UpdateSchema(id.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+
+        // Disabled policies are still in the store: a local copy re-enables them and they must
+        // not be disabled again when they are missing locally.
+        let remote_policy_ids: HashSet<String> = self
+            .list_policies(Some(FilterOptions::new().with_include_disabled(true)))
+            .await?
+            .into_iter()
+            .collect();
+        let enabled_policy_ids: HashSet<String> =
+            self.list_policies(None).await?.into_iter().collect();
+        let existing: Vec<String> = plan
+            .policies
+            .keys()
+            .filter(|id| remote_policy_ids.contains(*id))
+            .cloned()
+            .collect();
+        let remote_policies = if existing.is_empty() {
+            Vec::new()
+        } else {
+            self.get_policy(existing).await?
+        };
+        let mut remote_policies: BTreeMap<String, Policy> = remote_policies
+            .into_iter()
+            .map(|mut p| {
+                let id = policy_id(&p)?;
+                p.disabled = !enabled_policy_ids.contains(&id);
+                Ok((id, p))
+            })
+            .collect::<Result<_>>()?;
+
+        for (id, policy) in &plan.policies {
+            match remote_policies.remove(id) {
+                None => plan.actions.push(ReconcileAction::AddPolicy(id.clone())),
+                Some(remote) if !same_policy(policy, &remote) => {
+                    plan.actions.push(ReconcileAction::UpdatePolicy(id.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut obsolete: Vec<&String> = remote_policy_ids
+            .iter()
+            .filter(|id| !plan.policies.contains_key(*id) && enabled_policy_ids.contains(*id))
+            .collect();
+        obsolete.sort();
+        plan.actions.extend(
+            obsolete
+                .into_iter()
+                .map(|id| ReconcileAction::DisablePolicy(id.clone())),
+        );
+
+        let mut obsolete: Vec<&String> = remote_schema_ids
+            .iter()
+            .filter(|id| !plan.schemas.contains_key(*id))
+            .collect();
+        obsolete.sort();
+        plan.actions.extend(
+            obsolete
+                .into_iter()
+                .map(|id| ReconcileAction::DeleteSchema(id.clone())),
+        );
+
+        plan.actions.sort();
+        Ok(plan)
+    }
+
+    /// Apply a plan produced by [`CerbosAdminClient::plan_reconcile`].
+    pub async fn apply_reconcile(&mut self, plan: &ReconcilePlan) -> Result<()> {
+        let mut schemas = SchemaSet::new();
+        let mut policies = PolicySet::new();
+        let mut disable = Vec::new();
+        let mut delete = Vec::new();
+        for action in &plan.actions {
+            match action {
+                ReconcileAction::AddSchema(id) | ReconcileAction::UpdateSchema(id) => {
+                    schemas.add_schema(plan.schemas[id].clone());
+                }
+                ReconcileAction::AddPolicy(id) | ReconcileAction::UpdatePolicy(id) => {
+                    policies.add_policy(plan.policies[id].clone());
+                }
+                ReconcileAction::DisablePolicy(id) => disable.push(id.clone()),
+                ReconcileAction::DeleteSchema(id) => delete.push(id.clone()),
+            }
+        }
+
+        if schemas.size() > 0 {
+            self.add_or_update_schema(&schemas).await?;
+        }
+        if policies.size() > 0 {
+            self.add_or_update_policy(&policies).await?;
+        }
+        if !disable.is_empty() {
+            self.disable_policy(disable).await?;
+        }
+        if !delete.is_empty() {
+            self.delete_schema(delete).await?;
+        }
+        Ok(())
+    }
+}
+
+/// ID of the policy as reported by the admin API, for example `resource.leave_request.vdefault/acme`.
+pub(crate) fn policy_id(policy: &Policy) -> Result<String> {
+    fn versioned(kind: &str, name: &str, version: &str, scope: &str) -> String {
+        let version = if version.is_empty() {
+            "default"
+        } else {
+            version
+        };
+        let mut id = format!("{kind}.{name}.v{version}");
+        if !scope.is_empty() {
+            id.push('/');
+            id.push_str(scope);
+        }
+        id
+    }
+
+    Ok(match &policy.policy_type {
+        Some(PolicyType::ResourcePolicy(p)) => {
+            versioned("resource", &p.resource, &p.version, &p.scope)
+        }
+        Some(PolicyType::PrincipalPolicy(p)) => {
+            versioned("principal", &p.principal, &p.version, &p.scope)
+        }
+        Some(PolicyType::RolePolicy(p)) => match &p.policy_type {
+            Some(role_policy::PolicyType::Role(role)) => versioned("role", role, "", &p.scope),
+            None => bail!("role policy has no role"),
+        },
+        Some(PolicyType::DerivedRoles(p)) => format!("derived_roles.{}", p.name),
+        Some(PolicyType::ExportVariables(p)) => format!("export_variables.{}", p.name),
+        Some(PolicyType::ExportConstants(p)) => format!("export_constants.{}", p.name),
+        None => bail!("policy has no type"),
+    })
+}
+
+// Compare ignoring the metadata the store attaches to policies it returns.
+fn same_policy(local: &Policy, remote: &Policy) -> bool {
+    fn normalize(mut policy: Policy) -> Policy {
+        policy.metadata = policy
+            .metadata
+            .filter(|m| !m.annotations.is_empty())
+            .map(|m| Metadata {
+                annotations: m.annotations,
+                ..Default::default()
+            });
+        policy
+    }
+    normalize(local.clone()) == normalize(remote.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genpb::cerbos::policy::v1::{DerivedRoles, ResourcePolicy};
+
+    #[test]
+    fn test_policy_id() {
+        let resource = Policy {
+            policy_type: Some(PolicyType::ResourcePolicy(ResourcePolicy {
+                resource: "leave_request".to_string(),
+                scope: "acme.hr".to_string(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy_id(&resource).unwrap(),
+            "resource.leave_request.vdefault/acme.hr"
+        );
+
+        let derived = Policy {
+            policy_type: Some(PolicyType::DerivedRoles(DerivedRoles {
+                name: "common_roles".to_string(),
+                ..Default::default()
+            })),
+            metadata: Some(Metadata {
+                store_identifier: "derived_roles/common_roles.yaml".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(policy_id(&derived).unwrap(), "derived_roles.common_roles");
+        assert!(same_policy(
+            &Policy {
+                metadata: None,
+                ..derived.clone()
+            },
+            &derived
+        ));
+    }
+}
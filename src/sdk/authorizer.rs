@@ -0,0 +1,130 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::future::Future;
+
+use super::model::{
+    AuxData, CheckResourcesResponse, PlanResourcesResponse, Principal, Resource, ResourceKind,
+    ResourceList,
+};
+use super::{CerbosAsyncClient, Result};
+
+/// Authorization operations provided by a PDP.
+///
+/// Depend on this trait instead of [`CerbosAsyncClient`] to be able to substitute the client in
+/// tests, for example with `FakeAuthorizer` from the `testing` feature.
+pub trait Authorizer: Send {
+    /// Check access to multiple resources
+    fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<CheckResourcesResponse>> + Send;
+
+    /// Check access to a single resource
+    fn is_allowed<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: Resource,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<bool>> + Send
+    where
+        S: Into<String> + Clone + Send,
+    {
+        async move {
+            let resp = self
+                .check_resources(
+                    principal,
+                    ResourceList::new().add(resource, [action.clone()]),
+                    aux_data,
+                )
+                .await?;
+            Ok(resp
+                .iter()
+                .next()
+                .map(|r| r.is_allowed(action.into()))
+                .unwrap_or(false))
+        }
+    }
+
+    /// Produce a query plan for selecting resources that the principal can perform the given
+    /// action on.
+    fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<PlanResourcesResponse>> + Send
+    where
+        S: Into<String> + Clone + Send;
+}
+
+impl Authorizer for CerbosAsyncClient {
+    fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<CheckResourcesResponse>> + Send {
+        CerbosAsyncClient::check_resources(self, principal, resources, aux_data)
+    }
+
+    fn is_allowed<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: Resource,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<bool>> + Send
+    where
+        S: Into<String> + Clone + Send,
+    {
+        CerbosAsyncClient::is_allowed(self, action, principal, resource, aux_data)
+    }
+
+    fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<PlanResourcesResponse>> + Send
+    where
+        S: Into<String> + Clone + Send,
+    {
+        CerbosAsyncClient::plan_resources(self, action, principal, resource, aux_data)
+    }
+}
+
+#[cfg(feature = "local")]
+impl Authorizer for super::local::LocalEngine {
+    fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<CheckResourcesResponse>> + Send {
+        super::local::LocalEngine::check_resources(self, principal, resources, aux_data)
+    }
+
+    /// Query planning is not supported by the local engine and always fails with
+    /// [`Unsupported`](super::local::Unsupported).
+    async fn plan_resources<S>(
+        &mut self,
+        _action: S,
+        _principal: Principal,
+        _resource: ResourceKind,
+        _aux_data: Option<AuxData>,
+    ) -> Result<PlanResourcesResponse>
+    where
+        S: Into<String> + Clone + Send,
+    {
+        Err(super::local::Unsupported {
+            operation: "plan_resources",
+        }
+        .into())
+    }
+}
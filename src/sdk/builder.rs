@@ -0,0 +1,762 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Builders for authoring policies in code.
+//!
+//! ```rust
+//! use cerbos::sdk::builder::{all, expr, DerivedRolesBuilder, ResourcePolicyBuilder, ResourceRuleBuilder, RoleDefBuilder};
+//!
+//! let derived_roles = DerivedRolesBuilder::new("common_roles")
+//!     .add_definition(
+//!         RoleDefBuilder::new("owner", ["user"]).with_condition(expr("R.attr.owner == P.id")),
+//!     )
+//!     .build();
+//!
+//! let policy = ResourcePolicyBuilder::new("album", "default")
+//!     .with_scope("acme")
+//!     .import_derived_roles(["common_roles"])
+//!     .add_rule(ResourceRuleBuilder::allow(["*"]).with_derived_roles(["owner"]))
+//!     .add_rule(
+//!         ResourceRuleBuilder::allow(["view"])
+//!             .with_roles(["user"])
+//!             .with_condition(all([expr("R.attr.public"), expr("!R.attr.flagged")])),
+//!     )
+//!     .build();
+//! ```
+
+use std::collections::HashMap;
+
+use crate::genpb::cerbos::{
+    effect::v1::Effect,
+    policy::v1::{
+        condition, policy::PolicyType, principal_rule, r#match, role_policy, schemas, Condition,
+        Constants, DerivedRoles, ExportConstants, ExportVariables, Match, Policy, PrincipalPolicy,
+        PrincipalRule, ResourcePolicy, ResourceRule, RoleDef, RolePolicy, RoleRule, Schemas,
+        Variables,
+    },
+};
+
+use super::attr::AttrVal;
+
+const API_VERSION: &str = "api.cerbos.dev/v1";
+
+/// A CEL expression condition.
+pub fn expr(expr: impl Into<String>) -> Match {
+    Match {
+        op: Some(r#match::Op::Expr(expr.into())),
+    }
+}
+
+/// A condition that is met if all of `matches` are met.
+pub fn all(matches: impl IntoIterator<Item = Match>) -> Match {
+    Match {
+        op: Some(r#match::Op::All(expr_list(matches))),
+    }
+}
+
+/// A condition that is met if any of `matches` is met.
+pub fn any(matches: impl IntoIterator<Item = Match>) -> Match {
+    Match {
+        op: Some(r#match::Op::Any(expr_list(matches))),
+    }
+}
+
+/// A condition that is met if none of `matches` is met.
+pub fn none(matches: impl IntoIterator<Item = Match>) -> Match {
+    Match {
+        op: Some(r#match::Op::None(expr_list(matches))),
+    }
+}
+
+fn expr_list(matches: impl IntoIterator<Item = Match>) -> r#match::ExprList {
+    r#match::ExprList {
+        of: matches.into_iter().collect(),
+    }
+}
+
+impl From<Match> for Condition {
+    fn from(m: Match) -> Self {
+        Condition {
+            condition: Some(condition::Condition::Match(m)),
+        }
+    }
+}
+
+fn strings<I, T>(items: I) -> impl Iterator<Item = String>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<String>,
+{
+    items.into_iter().map(Into::into)
+}
+
+// Fields common to all kinds of policies.
+#[derive(Debug, Clone, Default)]
+struct Header {
+    description: String,
+    disabled: bool,
+}
+
+impl Header {
+    fn policy(self, policy_type: PolicyType) -> Policy {
+        Policy {
+            api_version: API_VERSION.to_string(),
+            description: self.description,
+            disabled: self.disabled,
+            policy_type: Some(policy_type),
+            ..Default::default()
+        }
+    }
+}
+
+macro_rules! header_methods {
+    () => {
+        pub fn with_description(mut self, description: impl Into<String>) -> Self {
+            self.header.description = description.into();
+            self
+        }
+
+        pub fn with_disabled(mut self, disabled: bool) -> Self {
+            self.header.disabled = disabled;
+            self
+        }
+    };
+}
+
+// Variables and constants sections of a policy.
+macro_rules! variables_methods {
+    () => {
+        /// Import the variables of an [`ExportVariablesBuilder`] policy.
+        pub fn import_variables<I, T>(mut self, names: I) -> Self
+        where
+            I: IntoIterator<Item = T>,
+            T: Into<String>,
+        {
+            self.variables().import.extend(strings(names));
+            self
+        }
+
+        /// Define a variable local to the policy.
+        pub fn add_variable(mut self, name: impl Into<String>, expr: impl Into<String>) -> Self {
+            self.variables().local.insert(name.into(), expr.into());
+            self
+        }
+
+        /// Import the constants of an [`ExportConstantsBuilder`] policy.
+        pub fn import_constants<I, T>(mut self, names: I) -> Self
+        where
+            I: IntoIterator<Item = T>,
+            T: Into<String>,
+        {
+            self.constants().import.extend(strings(names));
+            self
+        }
+
+        /// Define a constant local to the policy.
+        pub fn add_constant(mut self, name: impl Into<String>, value: impl AttrVal) -> Self {
+            self.constants().local.insert(name.into(), value.to_value());
+            self
+        }
+
+        fn variables(&mut self) -> &mut Variables {
+            self.policy.variables.get_or_insert_with(Default::default)
+        }
+
+        fn constants(&mut self) -> &mut Constants {
+            self.policy.constants.get_or_insert_with(Default::default)
+        }
+    };
+}
+
+/// Builder of resource policies.
+#[derive(Debug, Clone)]
+pub struct ResourcePolicyBuilder {
+    header: Header,
+    policy: ResourcePolicy,
+}
+
+impl ResourcePolicyBuilder {
+    pub fn new(resource: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            header: Header::default(),
+            policy: ResourcePolicy {
+                resource: resource.into(),
+                version: version.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    header_methods!();
+    variables_methods!();
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.policy.scope = scope.into();
+        self
+    }
+
+    pub fn import_derived_roles<I, T>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.policy.import_derived_roles.extend(strings(names));
+        self
+    }
+
+    /// Validate principal and resource attributes against schemas, such as
+    /// `cerbos:///principal.json`.
+    pub fn with_schemas(
+        mut self,
+        principal_schema: impl Into<String>,
+        resource_schema: impl Into<String>,
+    ) -> Self {
+        let schema = |r: String| schemas::Schema {
+            r#ref: r,
+            ignore_when: None,
+        };
+        self.policy.schemas = Some(Schemas {
+            principal_schema: Some(schema(principal_schema.into())),
+            resource_schema: Some(schema(resource_schema.into())),
+        });
+        self
+    }
+
+    pub fn add_rule(mut self, rule: ResourceRuleBuilder) -> Self {
+        self.policy.rules.push(rule.rule);
+        self
+    }
+
+    pub fn build(self) -> Policy {
+        self.header.policy(PolicyType::ResourcePolicy(self.policy))
+    }
+}
+
+/// Builder of resource policy rules.
+#[derive(Debug, Clone)]
+pub struct ResourceRuleBuilder {
+    rule: ResourceRule,
+}
+
+impl ResourceRuleBuilder {
+    /// A rule that allows `actions`.
+    pub fn allow<I, T>(actions: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self::new(actions, Effect::Allow)
+    }
+
+    /// A rule that denies `actions`.
+    pub fn deny<I, T>(actions: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self::new(actions, Effect::Deny)
+    }
+
+    fn new<I, T>(actions: I, effect: Effect) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            rule: ResourceRule {
+                actions: strings(actions).collect(),
+                effect: effect as i32,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.rule.name = name.into();
+        self
+    }
+
+    pub fn with_roles<I, T>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.rule.roles.extend(strings(roles));
+        self
+    }
+
+    pub fn with_derived_roles<I, T>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.rule.derived_roles.extend(strings(roles));
+        self
+    }
+
+    pub fn with_condition(mut self, condition: impl Into<Condition>) -> Self {
+        self.rule.condition = Some(condition.into());
+        self
+    }
+}
+
+/// Builder of principal policies.
+#[derive(Debug, Clone)]
+pub struct PrincipalPolicyBuilder {
+    header: Header,
+    policy: PrincipalPolicy,
+}
+
+impl PrincipalPolicyBuilder {
+    pub fn new(principal: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            header: Header::default(),
+            policy: PrincipalPolicy {
+                principal: principal.into(),
+                version: version.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    header_methods!();
+    variables_methods!();
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.policy.scope = scope.into();
+        self
+    }
+
+    pub fn add_rule(mut self, rule: PrincipalRuleBuilder) -> Self {
+        self.policy.rules.push(rule.rule);
+        self
+    }
+
+    pub fn build(self) -> Policy {
+        self.header.policy(PolicyType::PrincipalPolicy(self.policy))
+    }
+}
+
+/// Builder of the rules of a principal policy for one resource kind.
+#[derive(Debug, Clone)]
+pub struct PrincipalRuleBuilder {
+    rule: PrincipalRule,
+}
+
+impl PrincipalRuleBuilder {
+    pub fn new(resource: impl Into<String>) -> Self {
+        Self {
+            rule: PrincipalRule {
+                resource: resource.into(),
+                actions: Vec::new(),
+            },
+        }
+    }
+
+    /// Allow `action`.
+    pub fn allow(self, action: impl Into<String>) -> Self {
+        self.add_action(PrincipalActionBuilder::allow(action))
+    }
+
+    /// Deny `action`.
+    pub fn deny(self, action: impl Into<String>) -> Self {
+        self.add_action(PrincipalActionBuilder::deny(action))
+    }
+
+    pub fn add_action(mut self, action: PrincipalActionBuilder) -> Self {
+        self.rule.actions.push(action.action);
+        self
+    }
+}
+
+/// Builder of a single action of a principal policy rule.
+#[derive(Debug, Clone)]
+pub struct PrincipalActionBuilder {
+    action: principal_rule::Action,
+}
+
+impl PrincipalActionBuilder {
+    pub fn allow(action: impl Into<String>) -> Self {
+        Self::new(action, Effect::Allow)
+    }
+
+    pub fn deny(action: impl Into<String>) -> Self {
+        Self::new(action, Effect::Deny)
+    }
+
+    fn new(action: impl Into<String>, effect: Effect) -> Self {
+        Self {
+            action: principal_rule::Action {
+                action: action.into(),
+                effect: effect as i32,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.action.name = name.into();
+        self
+    }
+
+    pub fn with_condition(mut self, condition: impl Into<Condition>) -> Self {
+        self.action.condition = Some(condition.into());
+        self
+    }
+}
+
+/// Builder of role policies.
+#[derive(Debug, Clone)]
+pub struct RolePolicyBuilder {
+    header: Header,
+    policy: RolePolicy,
+}
+
+impl RolePolicyBuilder {
+    pub fn new(role: impl Into<String>) -> Self {
+        Self {
+            header: Header::default(),
+            policy: RolePolicy {
+                policy_type: Some(role_policy::PolicyType::Role(role.into())),
+                ..Default::default()
+            },
+        }
+    }
+
+    header_methods!();
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.policy.scope = scope.into();
+        self
+    }
+
+    pub fn with_parent_roles<I, T>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.policy.parent_roles.extend(strings(roles));
+        self
+    }
+
+    pub fn add_rule(mut self, rule: RoleRuleBuilder) -> Self {
+        self.policy.rules.push(rule.rule);
+        self
+    }
+
+    pub fn build(self) -> Policy {
+        self.header.policy(PolicyType::RolePolicy(self.policy))
+    }
+}
+
+/// Builder of the actions a role policy allows on a resource kind.
+#[derive(Debug, Clone)]
+pub struct RoleRuleBuilder {
+    rule: RoleRule,
+}
+
+impl RoleRuleBuilder {
+    pub fn new(resource: impl Into<String>) -> Self {
+        Self {
+            rule: RoleRule {
+                resource: resource.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn allow<I, T>(mut self, actions: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.rule.allow_actions.extend(strings(actions));
+        self
+    }
+
+    pub fn with_condition(mut self, condition: impl Into<Condition>) -> Self {
+        self.rule.condition = Some(condition.into());
+        self
+    }
+}
+
+/// Builder of derived roles policies.
+#[derive(Debug, Clone)]
+pub struct DerivedRolesBuilder {
+    header: Header,
+    policy: DerivedRoles,
+}
+
+impl DerivedRolesBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            header: Header::default(),
+            policy: DerivedRoles {
+                name: name.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    header_methods!();
+    variables_methods!();
+
+    pub fn add_definition(mut self, definition: RoleDefBuilder) -> Self {
+        self.policy.definitions.push(definition.definition);
+        self
+    }
+
+    pub fn build(self) -> Policy {
+        self.header.policy(PolicyType::DerivedRoles(self.policy))
+    }
+}
+
+/// Builder of a derived role definition.
+#[derive(Debug, Clone)]
+pub struct RoleDefBuilder {
+    definition: RoleDef,
+}
+
+impl RoleDefBuilder {
+    pub fn new<I, T>(name: impl Into<String>, parent_roles: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            definition: RoleDef {
+                name: name.into(),
+                parent_roles: strings(parent_roles).collect(),
+                condition: None,
+            },
+        }
+    }
+
+    pub fn with_condition(mut self, condition: impl Into<Condition>) -> Self {
+        self.definition.condition = Some(condition.into());
+        self
+    }
+}
+
+/// Builder of policies that export variables for other policies to import.
+#[derive(Debug, Clone)]
+pub struct ExportVariablesBuilder {
+    header: Header,
+    name: String,
+    definitions: HashMap<String, String>,
+}
+
+impl ExportVariablesBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            header: Header::default(),
+            name: name.into(),
+            definitions: HashMap::new(),
+        }
+    }
+
+    header_methods!();
+
+    pub fn add_definition(mut self, name: impl Into<String>, expr: impl Into<String>) -> Self {
+        self.definitions.insert(name.into(), expr.into());
+        self
+    }
+
+    pub fn build(self) -> Policy {
+        self.header
+            .policy(PolicyType::ExportVariables(ExportVariables {
+                name: self.name,
+                definitions: self.definitions,
+            }))
+    }
+}
+
+/// Builder of policies that export constants for other policies to import.
+#[derive(Debug, Clone)]
+pub struct ExportConstantsBuilder {
+    header: Header,
+    policy: ExportConstants,
+}
+
+impl ExportConstantsBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            header: Header::default(),
+            policy: ExportConstants {
+                name: name.into(),
+                definitions: HashMap::new(),
+            },
+        }
+    }
+
+    header_methods!();
+
+    pub fn add_definition(mut self, name: impl Into<String>, value: impl AttrVal) -> Self {
+        self.policy
+            .definitions
+            .insert(name.into(), value.to_value());
+        self
+    }
+
+    pub fn build(self) -> Policy {
+        self.header.policy(PolicyType::ExportConstants(self.policy))
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::sdk::deser::read_policies;
+
+    #[test]
+    fn test_builders() -> anyhow::Result<()> {
+        let yaml = r#"---
+apiVersion: api.cerbos.dev/v1
+derivedRoles:
+  name: common_roles
+  definitions:
+    - name: owner
+      parentRoles: [user]
+      condition:
+        match:
+          expr: R.attr.owner == P.id
+  variables:
+    import: [common_variables]
+---
+apiVersion: api.cerbos.dev/v1
+description: Albums of tenant acme
+resourcePolicy:
+  resource: album
+  version: default
+  scope: acme
+  importDerivedRoles: [common_roles]
+  schemas:
+    principalSchema:
+      ref: cerbos:///principal.json
+    resourceSchema:
+      ref: cerbos:///album.json
+  constants:
+    local:
+      max_size: 100
+  rules:
+    - actions: ["*"]
+      effect: EFFECT_ALLOW
+      derivedRoles: [owner]
+    - name: public-view
+      actions: [view]
+      effect: EFFECT_ALLOW
+      roles: [user]
+      condition:
+        match:
+          all:
+            of:
+              - expr: R.attr.public
+              - none:
+                  of:
+                    - expr: R.attr.flagged
+    - actions: [delete]
+      effect: EFFECT_DENY
+      roles: ["*"]
+      condition:
+        match:
+          any:
+            of:
+              - expr: R.attr.locked
+              - expr: R.attr.size > C.max_size
+---
+apiVersion: api.cerbos.dev/v1
+principalPolicy:
+  principal: alice
+  version: default
+  rules:
+    - resource: album
+      actions:
+        - action: "*"
+          effect: EFFECT_ALLOW
+        - action: delete
+          effect: EFFECT_DENY
+          name: no-delete
+          condition:
+            match:
+              expr: R.attr.locked
+---
+apiVersion: api.cerbos.dev/v1
+disabled: true
+rolePolicy:
+  role: moderator
+  parentRoles: [user]
+  rules:
+    - resource: album
+      allowActions: [view, flag]
+---
+apiVersion: api.cerbos.dev/v1
+exportVariables:
+  name: common_variables
+  definitions:
+    is_owner: R.attr.owner == P.id
+---
+apiVersion: api.cerbos.dev/v1
+exportConstants:
+  name: common_constants
+  definitions:
+    max_size: 100
+"#;
+
+        let built = vec![
+            DerivedRolesBuilder::new("common_roles")
+                .add_definition(
+                    RoleDefBuilder::new("owner", ["user"])
+                        .with_condition(expr("R.attr.owner == P.id")),
+                )
+                .import_variables(["common_variables"])
+                .build(),
+            ResourcePolicyBuilder::new("album", "default")
+                .with_description("Albums of tenant acme")
+                .with_scope("acme")
+                .import_derived_roles(["common_roles"])
+                .with_schemas("cerbos:///principal.json", "cerbos:///album.json")
+                .add_constant("max_size", 100)
+                .add_rule(ResourceRuleBuilder::allow(["*"]).with_derived_roles(["owner"]))
+                .add_rule(
+                    ResourceRuleBuilder::allow(["view"])
+                        .with_name("public-view")
+                        .with_roles(["user"])
+                        .with_condition(all([
+                            expr("R.attr.public"),
+                            none([expr("R.attr.flagged")]),
+                        ])),
+                )
+                .add_rule(
+                    ResourceRuleBuilder::deny(["delete"])
+                        .with_roles(["*"])
+                        .with_condition(any([
+                            expr("R.attr.locked"),
+                            expr("R.attr.size > C.max_size"),
+                        ])),
+                )
+                .build(),
+            PrincipalPolicyBuilder::new("alice", "default")
+                .add_rule(
+                    PrincipalRuleBuilder::new("album").allow("*").add_action(
+                        PrincipalActionBuilder::deny("delete")
+                            .with_name("no-delete")
+                            .with_condition(expr("R.attr.locked")),
+                    ),
+                )
+                .build(),
+            RolePolicyBuilder::new("moderator")
+                .with_disabled(true)
+                .with_parent_roles(["user"])
+                .add_rule(RoleRuleBuilder::new("album").allow(["view", "flag"]))
+                .build(),
+            ExportVariablesBuilder::new("common_variables")
+                .add_definition("is_owner", "R.attr.owner == P.id")
+                .build(),
+            ExportConstantsBuilder::new("common_constants")
+                .add_definition("max_size", 100)
+                .build(),
+        ];
+        assert_eq!(built, read_policies(yaml.as_bytes())?);
+        Ok(())
+    }
+}
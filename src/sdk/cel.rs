@@ -0,0 +1,1184 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parser for the Common Expression Language (CEL) used in policy conditions.
+//!
+//! Expressions are parsed into the `google.api.expr.v1alpha1` AST. Macros (`has`, `all`, `exists`,
+//! `exists_one`, `map` and `filter`) are expanded into comprehensions the same way the reference
+//! implementation does it.
+//!
+//! [`check_policy`] parses every expression of a policy so that syntax errors can be reported
+//! without a PDP.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::genpb::cerbos::policy::v1::{
+    condition, policy::PolicyType, r#match::Op, Condition, Match, Output, Policy, Variables,
+};
+use crate::genpb::google::api::expr::v1alpha1::{
+    constant::ConstantKind,
+    expr::{
+        create_struct::{entry::KeyKind, Entry},
+        Call, Comprehension, CreateList, CreateStruct, ExprKind, Ident, Select,
+    },
+    Constant, Expr, ParsedExpr, SourceInfo,
+};
+
+pub(crate) const ACCUMULATOR_VAR: &str = "__result__";
+
+/// Deepest nesting of grammar rules accepted by [`parse`], as in cel-go. Every operator
+/// precedence level and unary operator counts, so a parenthesised expression uses seven levels.
+/// Parsing is recursive, so this bounds the stack it uses.
+const MAX_NESTING: usize = 250;
+
+/// Syntax error with the byte offset in the source where it was detected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "syntax error at offset {}: {}",
+            self.offset, self.message
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+type ParseResult<T> = std::result::Result<T, SyntaxError>;
+
+/// Parse a CEL expression.
+pub fn parse(src: &str) -> ParseResult<ParsedExpr> {
+    let tokens = Lexer::new(src).tokenize()?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+        next_id: 0,
+        positions: HashMap::new(),
+    };
+    let expr = parser.expr()?;
+    let tok = parser.peek();
+    if tok.tok != Tok::Eof {
+        return Err(SyntaxError {
+            offset: tok.offset,
+            message: format!("unexpected {}", tok.tok),
+        });
+    }
+
+    let line_offsets = src
+        .char_indices()
+        .filter(|(_, c)| *c == '\n')
+        .map(|(i, _)| i as i32 + 1)
+        .collect();
+    Ok(ParsedExpr {
+        expr: Some(expr),
+        source_info: Some(SourceInfo {
+            line_offsets,
+            positions: parser.positions,
+            ..Default::default()
+        }),
+    })
+}
+
+/// An expression in a policy and the path of the field that holds it, for example
+/// `resourcePolicy.rules[0].condition.match.all.of[1].expr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyExpression<'a> {
+    pub path: String,
+    pub source: &'a str,
+}
+
+/// Syntax error in an expression of a policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionError {
+    pub path: String,
+    pub source: String,
+    pub error: SyntaxError,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+/// Collect the expressions of conditions, variables and outputs in `policy`. Variables are
+/// returned in name order.
+pub fn policy_expressions(policy: &Policy) -> Vec<PolicyExpression<'_>> {
+    let mut out = Vec::new();
+    add_map(&mut out, "variables", &policy.variables);
+    match &policy.policy_type {
+        Some(PolicyType::ResourcePolicy(p)) => {
+            let root = "resourcePolicy";
+            add_variables(&mut out, root, p.variables.as_ref());
+            for (i, rule) in p.rules.iter().enumerate() {
+                let path = format!("{root}.rules[{i}]");
+                add_condition(&mut out, &path, rule.condition.as_ref());
+                add_output(&mut out, &path, rule.output.as_ref());
+            }
+        }
+        Some(PolicyType::PrincipalPolicy(p)) => {
+            let root = "principalPolicy";
+            add_variables(&mut out, root, p.variables.as_ref());
+            for (i, rule) in p.rules.iter().enumerate() {
+                for (j, action) in rule.actions.iter().enumerate() {
+                    let path = format!("{root}.rules[{i}].actions[{j}]");
+                    add_condition(&mut out, &path, action.condition.as_ref());
+                    add_output(&mut out, &path, action.output.as_ref());
+                }
+            }
+        }
+        Some(PolicyType::DerivedRoles(p)) => {
+            let root = "derivedRoles";
+            add_variables(&mut out, root, p.variables.as_ref());
+            for (i, def) in p.definitions.iter().enumerate() {
+                let path = format!("{root}.definitions[{i}]");
+                add_condition(&mut out, &path, def.condition.as_ref());
+            }
+        }
+        Some(PolicyType::RolePolicy(p)) => {
+            for (i, rule) in p.rules.iter().enumerate() {
+                let path = format!("rolePolicy.rules[{i}]");
+                add_condition(&mut out, &path, rule.condition.as_ref());
+            }
+        }
+        Some(PolicyType::ExportVariables(p)) => {
+            add_map(&mut out, "exportVariables.definitions", &p.definitions);
+        }
+        Some(PolicyType::ExportConstants(_)) | None => {}
+    }
+    out
+}
+
+/// Parse every expression of `policy` and return the syntax errors.
+///
+/// ```rust
+/// # use cerbos::sdk::{builder::{expr, ResourcePolicyBuilder, ResourceRuleBuilder}, cel};
+/// let policy = ResourcePolicyBuilder::new("album", "default")
+///     .add_rule(
+///         ResourceRuleBuilder::allow(["view"])
+///             .with_roles(["user"])
+///             .with_condition(expr("R.attr.public ==")),
+///     )
+///     .build();
+/// let errors = cel::check_policy(&policy);
+/// assert_eq!(errors[0].path, "resourcePolicy.rules[0].condition.match.expr");
+/// assert_eq!(errors[0].error.offset, 16);
+/// ```
+pub fn check_policy(policy: &Policy) -> Vec<ExpressionError> {
+    policy_expressions(policy)
+        .into_iter()
+        .filter_map(|e| {
+            parse(e.source).err().map(|error| ExpressionError {
+                path: e.path,
+                source: e.source.to_string(),
+                error,
+            })
+        })
+        .collect()
+}
+
+fn add<'a>(out: &mut Vec<PolicyExpression<'a>>, path: String, source: &'a str) {
+    if !source.is_empty() {
+        out.push(PolicyExpression { path, source });
+    }
+}
+
+fn add_map<'a>(out: &mut Vec<PolicyExpression<'a>>, root: &str, map: &'a HashMap<String, String>) {
+    let mut names: Vec<_> = map.keys().collect();
+    names.sort();
+    for name in names {
+        add(out, format!("{root}.{name}"), &map[name]);
+    }
+}
+
+fn add_variables<'a>(out: &mut Vec<PolicyExpression<'a>>, root: &str, v: Option<&'a Variables>) {
+    if let Some(v) = v {
+        add_map(out, &format!("{root}.variables.local"), &v.local);
+    }
+}
+
+fn add_condition<'a>(out: &mut Vec<PolicyExpression<'a>>, root: &str, c: Option<&'a Condition>) {
+    match c.and_then(|c| c.condition.as_ref()) {
+        Some(condition::Condition::Match(m)) => {
+            add_match(out, format!("{root}.condition.match"), m)
+        }
+        Some(condition::Condition::Script(s)) => add(out, format!("{root}.condition.script"), s),
+        None => {}
+    }
+}
+
+fn add_match<'a>(out: &mut Vec<PolicyExpression<'a>>, path: String, m: &'a Match) {
+    let (op, list) = match &m.op {
+        Some(Op::Expr(e)) => return add(out, format!("{path}.expr"), e),
+        Some(Op::All(l)) => ("all", l),
+        Some(Op::Any(l)) => ("any", l),
+        Some(Op::None(l)) => ("none", l),
+        None => return,
+    };
+    for (i, m) in list.of.iter().enumerate() {
+        add_match(out, format!("{path}.{op}.of[{i}]"), m);
+    }
+}
+
+fn add_output<'a>(out: &mut Vec<PolicyExpression<'a>>, root: &str, o: Option<&'a Output>) {
+    let Some(o) = o else { return };
+    #[allow(deprecated)]
+    add(out, format!("{root}.output.expr"), &o.expr);
+    if let Some(when) = &o.when {
+        add(
+            out,
+            format!("{root}.output.when.ruleActivated"),
+            &when.rule_activated,
+        );
+        add(
+            out,
+            format!("{root}.output.when.conditionNotMet"),
+            &when.condition_not_met,
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    /// Magnitude of a signed integer literal. It is range checked by the parser, which knows
+    /// whether a minus sign applies to it.
+    Int(u64),
+    Uint(u64),
+    Double(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Ident(String),
+    Punct(&'static str),
+    Eof,
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tok::Int(i) => write!(f, "integer {i}"),
+            Tok::Uint(u) => write!(f, "integer {u}u"),
+            Tok::Double(d) => write!(f, "number {d}"),
+            Tok::Str(s) => write!(f, "string {s:?}"),
+            Tok::Bytes(_) => f.write_str("bytes literal"),
+            Tok::Ident(i) => write!(f, "identifier '{i}'"),
+            Tok::Punct(p) => write!(f, "'{p}'"),
+            Tok::Eof => f.write_str("end of input"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    offset: usize,
+}
+
+// Longest operators first so that `==` is not lexed as two `=`.
+const PUNCTUATION: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%", "?", ":", ".", ",",
+    "[", "]", "(", ")", "{", "}",
+];
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn err<T>(&self, offset: usize, message: impl Into<String>) -> ParseResult<T> {
+        Err(SyntaxError {
+            offset,
+            message: message.into(),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn tokenize(mut self) -> ParseResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let offset = self.pos;
+            let Some(c) = self.rest().chars().next() else {
+                tokens.push(Token {
+                    tok: Tok::Eof,
+                    offset,
+                });
+                return Ok(tokens);
+            };
+
+            let tok = if c.is_ascii_digit() || (c == '.' && self.peek_digit_after_dot()) {
+                self.number()?
+            } else if let Some(tok) = self.string_literal()? {
+                tok
+            } else if c == '_' || c.is_ascii_alphabetic() {
+                let len = self
+                    .rest()
+                    .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+                    .unwrap_or(self.rest().len());
+                let ident = &self.rest()[..len];
+                self.pos += len;
+                Tok::Ident(ident.to_string())
+            } else if let Some(p) = PUNCTUATION.iter().find(|p| self.rest().starts_with(**p)) {
+                self.pos += p.len();
+                Tok::Punct(p)
+            } else {
+                return self.err(offset, format!("unexpected character '{c}'"));
+            };
+            tokens.push(Token { tok, offset });
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn peek_digit_after_dot(&self) -> bool {
+        self.rest()[1..].starts_with(|c: char| c.is_ascii_digit())
+    }
+
+    fn number(&mut self) -> ParseResult<Tok> {
+        let start = self.pos;
+        let rest = self.rest();
+        if rest.starts_with("0x") || rest.starts_with("0X") {
+            let len = rest[2..]
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(rest.len() - 2);
+            let digits = &rest[2..2 + len];
+            self.pos += 2 + len;
+            if self.rest().starts_with(['u', 'U']) {
+                self.pos += 1;
+                return u64::from_str_radix(digits, 16)
+                    .map(Tok::Uint)
+                    .or_else(|e| self.err(start, format!("invalid integer: {e}")));
+            }
+            return u64::from_str_radix(digits, 16)
+                .map(Tok::Int)
+                .or_else(|e| self.err(start, format!("invalid integer: {e}")));
+        }
+
+        let bytes = rest.as_bytes();
+        let mut len = 0;
+        let mut is_double = false;
+        while len < bytes.len() && bytes[len].is_ascii_digit() {
+            len += 1;
+        }
+        if len + 1 < bytes.len() && bytes[len] == b'.' && bytes[len + 1].is_ascii_digit() {
+            is_double = true;
+            len += 1;
+            while len < bytes.len() && bytes[len].is_ascii_digit() {
+                len += 1;
+            }
+        }
+        if len < bytes.len() && (bytes[len] == b'e' || bytes[len] == b'E') {
+            let mut exp = len + 1;
+            if exp < bytes.len() && (bytes[exp] == b'+' || bytes[exp] == b'-') {
+                exp += 1;
+            }
+            if exp < bytes.len() && bytes[exp].is_ascii_digit() {
+                is_double = true;
+                len = exp;
+                while len < bytes.len() && bytes[len].is_ascii_digit() {
+                    len += 1;
+                }
+            }
+        }
+
+        let text = &rest[..len];
+        self.pos += len;
+        if is_double {
+            return text
+                .parse()
+                .map(Tok::Double)
+                .or_else(|e| self.err(start, format!("invalid number: {e}")));
+        }
+        if self.rest().starts_with(['u', 'U']) {
+            self.pos += 1;
+            return text
+                .parse()
+                .map(Tok::Uint)
+                .or_else(|e| self.err(start, format!("invalid integer: {e}")));
+        }
+        text.parse()
+            .map(Tok::Int)
+            .or_else(|e| self.err(start, format!("invalid integer: {e}")))
+    }
+
+    fn string_literal(&mut self) -> ParseResult<Option<Tok>> {
+        let start = self.pos;
+        let rest = self.rest();
+        let prefix_len = rest
+            .find(|c: char| !matches!(c, 'r' | 'R' | 'b' | 'B'))
+            .unwrap_or(rest.len());
+        if prefix_len > 2 || !rest[prefix_len..].starts_with(['"', '\'']) {
+            return Ok(None);
+        }
+        let prefix = rest[..prefix_len].to_ascii_lowercase();
+        let (raw, bytes) = match prefix.as_str() {
+            "" => (false, false),
+            "r" => (true, false),
+            "b" => (false, true),
+            "rb" | "br" => (true, true),
+            _ => return Ok(None),
+        };
+
+        let body = &rest[prefix_len..];
+        let quote = &body[..1];
+        let triple = quote.repeat(3);
+        let delim = if body.starts_with(&triple) {
+            triple.as_str()
+        } else {
+            quote
+        };
+        self.pos += prefix_len + delim.len();
+
+        let mut out: Vec<u8> = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.starts_with(delim) {
+                self.pos += delim.len();
+                break;
+            }
+            let Some(c) = rest.chars().next() else {
+                return self.err(start, "unterminated string literal");
+            };
+            if c == '\n' && delim.len() == 1 {
+                return self.err(start, "unterminated string literal");
+            }
+            if c == '\\' && !raw {
+                self.escape(&mut out, bytes)?;
+                continue;
+            }
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            self.pos += c.len_utf8();
+        }
+
+        if bytes {
+            return Ok(Some(Tok::Bytes(out)));
+        }
+        String::from_utf8(out)
+            .map(|s| Some(Tok::Str(s)))
+            .or_else(|_| self.err(start, "invalid UTF-8 in string literal"))
+    }
+
+    fn escape(&mut self, out: &mut Vec<u8>, bytes: bool) -> ParseResult<()> {
+        let start = self.pos;
+        let rest = &self.rest()[1..];
+        let Some(c) = rest.chars().next() else {
+            return self.err(start, "unterminated escape sequence");
+        };
+
+        let simple = match c {
+            'a' => Some(0x07),
+            'b' => Some(0x08),
+            'f' => Some(0x0c),
+            'n' => Some(b'\n'),
+            'r' => Some(b'\r'),
+            't' => Some(b'\t'),
+            'v' => Some(0x0b),
+            '\\' | '\'' | '"' | '`' | '?' => Some(c as u8),
+            _ => None,
+        };
+        if let Some(b) = simple {
+            out.push(b);
+            self.pos += 2;
+            return Ok(());
+        }
+
+        let (radix, len) = match c {
+            'x' | 'X' => (16, 2),
+            'u' if !bytes => (16, 4),
+            'U' if !bytes => (16, 8),
+            '0'..='3' => (8, 3),
+            _ => return self.err(start, format!("invalid escape sequence '\\{c}'")),
+        };
+        let skip = if radix == 8 { 0 } else { 1 };
+        let digits = rest.get(skip..skip + len).unwrap_or_default();
+        let Ok(code) = u32::from_str_radix(digits, radix) else {
+            return self.err(start, "invalid escape sequence");
+        };
+        self.pos += 1 + skip + len;
+
+        if bytes {
+            out.push(code as u8);
+            return Ok(());
+        }
+        match char::from_u32(code) {
+            Some(ch) => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                Ok(())
+            }
+            None => self.err(start, "invalid unicode code point in escape sequence"),
+        }
+    }
+}
+
+fn out_of_range(offset: usize, literal: String) -> SyntaxError {
+    SyntaxError {
+        offset,
+        message: format!("invalid integer: {literal} is out of range"),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+    next_id: i64,
+    positions: HashMap<i64, i32>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> Token {
+        let t = self.tokens[self.pos].clone();
+        if t.tok != Tok::Eof {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn at(&self, p: &str) -> bool {
+        matches!(self.peek().tok, Tok::Punct(q) if q == p)
+    }
+
+    fn eat(&mut self, p: &str) -> bool {
+        if self.at(p) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, p: &str) -> ParseResult<usize> {
+        let t = self.peek();
+        if matches!(t.tok, Tok::Punct(q) if q == p) {
+            let offset = t.offset;
+            self.pos += 1;
+            return Ok(offset);
+        }
+        Err(SyntaxError {
+            offset: t.offset,
+            message: format!("expected '{p}' but found {}", t.tok),
+        })
+    }
+
+    fn new_expr(&mut self, offset: usize, kind: ExprKind) -> Expr {
+        self.next_id += 1;
+        self.positions.insert(self.next_id, offset as i32);
+        Expr {
+            id: self.next_id,
+            expr_kind: Some(kind),
+        }
+    }
+
+    fn call(&mut self, offset: usize, function: &str, args: Vec<Expr>) -> Expr {
+        self.new_expr(
+            offset,
+            ExprKind::CallExpr(Box::new(Call {
+                target: None,
+                function: function.to_string(),
+                args,
+            })),
+        )
+    }
+
+    fn ident(&mut self, offset: usize, name: &str) -> Expr {
+        self.new_expr(
+            offset,
+            ExprKind::IdentExpr(Ident {
+                name: name.to_string(),
+            }),
+        )
+    }
+
+    fn constant(&mut self, offset: usize, kind: ConstantKind) -> Expr {
+        self.new_expr(
+            offset,
+            ExprKind::ConstExpr(Constant {
+                constant_kind: Some(kind),
+            }),
+        )
+    }
+
+    fn nested(&mut self, f: fn(&mut Self) -> ParseResult<Expr>) -> ParseResult<Expr> {
+        if self.depth == MAX_NESTING {
+            return Err(SyntaxError {
+                offset: self.peek().offset,
+                message: format!("expression nested more than {MAX_NESTING} levels deep"),
+            });
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn expr(&mut self) -> ParseResult<Expr> {
+        self.nested(Self::conditional)
+    }
+
+    fn conditional(&mut self) -> ParseResult<Expr> {
+        let cond = self.nested(Self::or)?;
+        if self.at("?") {
+            let offset = self.advance().offset;
+            let then = self.nested(Self::or)?;
+            self.expect(":")?;
+            let otherwise = self.expr()?;
+            return Ok(self.call(offset, "_?_:_", vec![cond, then, otherwise]));
+        }
+        Ok(cond)
+    }
+
+    fn or(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.nested(Self::and)?;
+        while self.at("||") {
+            let offset = self.advance().offset;
+            let rhs = self.nested(Self::and)?;
+            lhs = self.call(offset, "_||_", vec![lhs, rhs]);
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.nested(Self::relation)?;
+        while self.at("&&") {
+            let offset = self.advance().offset;
+            let rhs = self.nested(Self::relation)?;
+            lhs = self.call(offset, "_&&_", vec![lhs, rhs]);
+        }
+        Ok(lhs)
+    }
+
+    fn relation(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.nested(Self::addition)?;
+        loop {
+            let function = match &self.peek().tok {
+                Tok::Punct("==") => "_==_",
+                Tok::Punct("!=") => "_!=_",
+                Tok::Punct("<") => "_<_",
+                Tok::Punct("<=") => "_<=_",
+                Tok::Punct(">") => "_>_",
+                Tok::Punct(">=") => "_>=_",
+                Tok::Ident(i) if i == "in" => "@in",
+                _ => return Ok(lhs),
+            };
+            let offset = self.advance().offset;
+            let rhs = self.nested(Self::addition)?;
+            lhs = self.call(offset, function, vec![lhs, rhs]);
+        }
+    }
+
+    fn addition(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.nested(Self::multiplication)?;
+        loop {
+            let function = match self.peek().tok {
+                Tok::Punct("+") => "_+_",
+                Tok::Punct("-") => "_-_",
+                _ => return Ok(lhs),
+            };
+            let offset = self.advance().offset;
+            let rhs = self.nested(Self::multiplication)?;
+            lhs = self.call(offset, function, vec![lhs, rhs]);
+        }
+    }
+
+    fn multiplication(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let function = match self.peek().tok {
+                Tok::Punct("*") => "_*_",
+                Tok::Punct("/") => "_/_",
+                Tok::Punct("%") => "_%_",
+                _ => return Ok(lhs),
+            };
+            let offset = self.advance().offset;
+            let rhs = self.unary()?;
+            lhs = self.call(offset, function, vec![lhs, rhs]);
+        }
+    }
+
+    fn unary(&mut self) -> ParseResult<Expr> {
+        self.nested(Self::prefixed)
+    }
+
+    fn prefixed(&mut self) -> ParseResult<Expr> {
+        if self.at("!") {
+            let offset = self.advance().offset;
+            let operand = self.unary()?;
+            return Ok(self.call(offset, "!_", vec![operand]));
+        }
+        if self.at("-") {
+            let offset = self.advance().offset;
+            // Fold negative numeric literals into constants.
+            match self.peek().tok {
+                Tok::Int(i) if !self.followed_by_member_access() => {
+                    let literal = self.advance().offset;
+                    let Some(value) = 0i64.checked_sub_unsigned(i) else {
+                        return Err(out_of_range(literal, format!("-{i}")));
+                    };
+                    return Ok(self.constant(offset, ConstantKind::Int64Value(value)));
+                }
+                Tok::Double(d) if !self.followed_by_member_access() => {
+                    self.advance();
+                    return Ok(self.constant(offset, ConstantKind::DoubleValue(-d)));
+                }
+                _ => {}
+            }
+            let operand = self.unary()?;
+            return Ok(self.call(offset, "-_", vec![operand]));
+        }
+        self.member()
+    }
+
+    fn followed_by_member_access(&self) -> bool {
+        matches!(
+            self.tokens.get(self.pos + 1).map(|t| &t.tok),
+            Some(Tok::Punct(".")) | Some(Tok::Punct("["))
+        )
+    }
+
+    fn member(&mut self) -> ParseResult<Expr> {
+        let mut operand = self.primary()?;
+        loop {
+            if self.at(".") {
+                let offset = self.advance().offset;
+                let field = self.identifier()?;
+                if self.at("(") {
+                    self.advance();
+                    let args = self.expr_list(")")?;
+                    operand = self.method_call(offset, operand, &field, args)?;
+                } else {
+                    operand = self.new_expr(
+                        offset,
+                        ExprKind::SelectExpr(Box::new(Select {
+                            operand: Some(Box::new(operand)),
+                            field,
+                            test_only: false,
+                        })),
+                    );
+                }
+            } else if self.at("[") {
+                let offset = self.advance().offset;
+                let index = self.expr()?;
+                self.expect("]")?;
+                operand = self.call(offset, "_[_]", vec![operand, index]);
+            } else {
+                return Ok(operand);
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> ParseResult<String> {
+        let t = self.advance();
+        match t.tok {
+            Tok::Ident(name) => Ok(name),
+            tok => Err(SyntaxError {
+                offset: t.offset,
+                message: format!("expected identifier but found {tok}"),
+            }),
+        }
+    }
+
+    fn expr_list(&mut self, close: &str) -> ParseResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        while !self.at(close) {
+            args.push(self.expr()?);
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect(close)?;
+        Ok(args)
+    }
+
+    fn primary(&mut self) -> ParseResult<Expr> {
+        let t = self.advance();
+        let offset = t.offset;
+        match t.tok {
+            Tok::Int(i) => match i64::try_from(i) {
+                Ok(value) => Ok(self.constant(offset, ConstantKind::Int64Value(value))),
+                Err(_) => Err(out_of_range(offset, i.to_string())),
+            },
+            Tok::Uint(u) => Ok(self.constant(offset, ConstantKind::Uint64Value(u))),
+            Tok::Double(d) => Ok(self.constant(offset, ConstantKind::DoubleValue(d))),
+            Tok::Str(s) => Ok(self.constant(offset, ConstantKind::StringValue(s))),
+            Tok::Bytes(b) => Ok(self.constant(offset, ConstantKind::BytesValue(b))),
+            Tok::Ident(name) => match name.as_str() {
+                "true" => Ok(self.constant(offset, ConstantKind::BoolValue(true))),
+                "false" => Ok(self.constant(offset, ConstantKind::BoolValue(false))),
+                "null" => Ok(self.constant(offset, ConstantKind::NullValue(0))),
+                "in" => Err(SyntaxError {
+                    offset,
+                    message: "unexpected 'in'".to_string(),
+                }),
+                _ => self.ident_or_call(offset, name),
+            },
+            Tok::Punct(".") => {
+                let name = self.identifier()?;
+                self.ident_or_call(offset, format!(".{name}"))
+            }
+            Tok::Punct("(") => {
+                let e = self.expr()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Tok::Punct("[") => {
+                let elements = self.expr_list("]")?;
+                Ok(self.new_expr(
+                    offset,
+                    ExprKind::ListExpr(CreateList {
+                        elements,
+                        optional_indices: vec![],
+                    }),
+                ))
+            }
+            Tok::Punct("{") => {
+                let mut entries = Vec::new();
+                while !self.at("}") {
+                    let key = self.expr()?;
+                    let colon = self.expect(":")?;
+                    let value = self.expr()?;
+                    self.next_id += 1;
+                    self.positions.insert(self.next_id, colon as i32);
+                    entries.push(Entry {
+                        id: self.next_id,
+                        value: Some(value),
+                        optional_entry: false,
+                        key_kind: Some(KeyKind::MapKey(key)),
+                    });
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect("}")?;
+                Ok(self.new_expr(
+                    offset,
+                    ExprKind::StructExpr(CreateStruct {
+                        message_name: String::new(),
+                        entries,
+                    }),
+                ))
+            }
+            tok => Err(SyntaxError {
+                offset,
+                message: format!("unexpected {tok}"),
+            }),
+        }
+    }
+
+    fn ident_or_call(&mut self, offset: usize, name: String) -> ParseResult<Expr> {
+        if !self.at("(") {
+            return Ok(self.ident(offset, &name));
+        }
+        self.advance();
+        let mut args = self.expr_list(")")?;
+        if name == "has" {
+            let arg = match (args.pop(), args.is_empty()) {
+                (Some(arg), true) => arg,
+                _ => {
+                    return Err(SyntaxError {
+                        offset,
+                        message: "has() expects exactly one argument".to_string(),
+                    })
+                }
+            };
+            return match arg.expr_kind {
+                Some(ExprKind::SelectExpr(mut select)) => {
+                    select.test_only = true;
+                    Ok(self.new_expr(offset, ExprKind::SelectExpr(select)))
+                }
+                _ => Err(SyntaxError {
+                    offset,
+                    message: "invalid argument to has() macro".to_string(),
+                }),
+            };
+        }
+        Ok(self.call(offset, &name, args))
+    }
+
+    fn method_call(
+        &mut self,
+        offset: usize,
+        target: Expr,
+        function: &str,
+        mut args: Vec<Expr>,
+    ) -> ParseResult<Expr> {
+        let is_macro = matches!(
+            (function, args.len()),
+            ("all" | "exists" | "exists_one" | "filter", 2) | ("map", 2 | 3)
+        );
+        if !is_macro {
+            return Ok(self.new_expr(
+                offset,
+                ExprKind::CallExpr(Box::new(Call {
+                    target: Some(Box::new(target)),
+                    function: function.to_string(),
+                    args,
+                })),
+            ));
+        }
+
+        let iter_var = match args.remove(0).expr_kind {
+            Some(ExprKind::IdentExpr(Ident { name })) => name,
+            _ => {
+                return Err(SyntaxError {
+                    offset,
+                    message: format!("argument to {function}() must be a simple name"),
+                })
+            }
+        };
+        let accu = |p: &mut Self| p.ident(offset, ACCUMULATOR_VAR);
+
+        let (init, condition, step, result) = match function {
+            "all" => {
+                let init = self.constant(offset, ConstantKind::BoolValue(true));
+                let a = accu(self);
+                let cond = self.call(offset, "@not_strictly_false", vec![a]);
+                let a = accu(self);
+                let step = self.call(offset, "_&&_", vec![a, args.remove(0)]);
+                (init, cond, step, accu(self))
+            }
+            "exists" => {
+                let init = self.constant(offset, ConstantKind::BoolValue(false));
+                let a = accu(self);
+                let not = self.call(offset, "!_", vec![a]);
+                let cond = self.call(offset, "@not_strictly_false", vec![not]);
+                let a = accu(self);
+                let step = self.call(offset, "_||_", vec![a, args.remove(0)]);
+                (init, cond, step, accu(self))
+            }
+            "exists_one" => {
+                let init = self.constant(offset, ConstantKind::Int64Value(0));
+                let cond = self.constant(offset, ConstantKind::BoolValue(true));
+                let a = accu(self);
+                let one = self.constant(offset, ConstantKind::Int64Value(1));
+                let inc = self.call(offset, "_+_", vec![a, one]);
+                let a = accu(self);
+                let step = self.call(offset, "_?_:_", vec![args.remove(0), inc, a]);
+                let a = accu(self);
+                let one = self.constant(offset, ConstantKind::Int64Value(1));
+                let result = self.call(offset, "_==_", vec![a, one]);
+                (init, cond, step, result)
+            }
+            _ => {
+                let init = self.new_expr(offset, ExprKind::ListExpr(CreateList::default()));
+                let cond = self.constant(offset, ConstantKind::BoolValue(true));
+                let (filter, element) = match (function, args.len()) {
+                    ("filter", _) => (Some(args.remove(0)), self.ident(offset, &iter_var)),
+                    (_, 2) => (Some(args.remove(0)), args.remove(0)),
+                    _ => (None, args.remove(0)),
+                };
+                let list = self.new_expr(
+                    offset,
+                    ExprKind::ListExpr(CreateList {
+                        elements: vec![element],
+                        optional_indices: vec![],
+                    }),
+                );
+                let a = accu(self);
+                let mut step = self.call(offset, "_+_", vec![a, list]);
+                if let Some(filter) = filter {
+                    let a = accu(self);
+                    step = self.call(offset, "_?_:_", vec![filter, step, a]);
+                }
+                (init, cond, step, accu(self))
+            }
+        };
+
+        Ok(self.new_expr(
+            offset,
+            ExprKind::ComprehensionExpr(Box::new(Comprehension {
+                iter_var,
+                iter_var2: String::new(),
+                iter_range: Some(Box::new(target)),
+                accu_var: ACCUMULATOR_VAR.to_string(),
+                accu_init: Some(Box::new(init)),
+                loop_condition: Some(Box::new(condition)),
+                loop_step: Some(Box::new(step)),
+                result: Some(Box::new(result)),
+            })),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_expr(src: &str) -> Expr {
+        parse(src).unwrap().expr.unwrap()
+    }
+
+    fn call_name(e: &Expr) -> &str {
+        match e.expr_kind.as_ref() {
+            Some(ExprKind::CallExpr(c)) => &c.function,
+            other => panic!("expected call, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let e = parse_expr(r#"R.attr.owner == P.id || "a" in R.attr.tags && !R.attr.x"#);
+        assert_eq!(call_name(&e), "_||_");
+        let Some(ExprKind::CallExpr(or)) = e.expr_kind else {
+            unreachable!()
+        };
+        assert_eq!(call_name(&or.args[0]), "_==_");
+        assert_eq!(call_name(&or.args[1]), "_&&_");
+    }
+
+    #[test]
+    fn test_literals() {
+        let e = parse_expr(
+            r#"[1, -2.5, 3u, 'x\n', r"\d", b"\x01", true, null, -9223372036854775808, {"a": 1,}]"#,
+        );
+        let Some(ExprKind::ListExpr(list)) = e.expr_kind else {
+            panic!("expected list")
+        };
+        assert_eq!(list.elements.len(), 10);
+        let constants: Vec<_> = list.elements[..9]
+            .iter()
+            .map(|e| match e.expr_kind.as_ref() {
+                Some(ExprKind::ConstExpr(c)) => c.constant_kind.clone().unwrap(),
+                other => panic!("expected constant, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            constants,
+            vec![
+                ConstantKind::Int64Value(1),
+                ConstantKind::DoubleValue(-2.5),
+                ConstantKind::Uint64Value(3),
+                ConstantKind::StringValue("x\n".to_string()),
+                ConstantKind::StringValue("\\d".to_string()),
+                ConstantKind::BytesValue(vec![1]),
+                ConstantKind::BoolValue(true),
+                ConstantKind::NullValue(0),
+                ConstantKind::Int64Value(i64::MIN),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_macros() {
+        let e = parse_expr("R.attr.tags.exists(t, t.startsWith('a'))");
+        assert!(matches!(
+            e.expr_kind,
+            Some(ExprKind::ComprehensionExpr(ref c)) if c.iter_var == "t"
+        ));
+
+        let e = parse_expr("has(R.attr.owner)");
+        assert!(matches!(
+            e.expr_kind,
+            Some(ExprKind::SelectExpr(ref s)) if s.test_only && s.field == "owner"
+        ));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let err = parse("R.attr.owner == ").unwrap_err();
+        assert_eq!(err.offset, 16);
+
+        let err = parse("a && (b || c").unwrap_err();
+        assert_eq!(err.offset, 12);
+
+        let err = parse("'unterminated").unwrap_err();
+        assert_eq!(err.offset, 0);
+
+        assert!(parse("a # b").is_err());
+
+        let err = parse("9223372036854775808").unwrap_err();
+        assert_eq!(
+            err.message,
+            "invalid integer: 9223372036854775808 is out of range"
+        );
+        assert!(parse("-9223372036854775809").is_err());
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(30)).is_ok());
+        let err = parse(&nested(40)).unwrap_err();
+        assert!(err.message.contains("nested"), "{err}");
+        assert!(parse(&nested(100_000)).is_err());
+        assert!(parse(&format!("{}true", "!".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn test_check_policy() {
+        use crate::sdk::builder::{
+            all, any, expr, DerivedRolesBuilder, PrincipalActionBuilder, PrincipalPolicyBuilder,
+            PrincipalRuleBuilder, RoleDefBuilder,
+        };
+
+        let policy = PrincipalPolicyBuilder::new("donald_duck", "default")
+            .add_variable("ok", "P.attr.dept == 'marketing'")
+            .add_variable("bad", "P.attr.dept ==")
+            .add_rule(
+                PrincipalRuleBuilder::new("leave_request").add_action(
+                    PrincipalActionBuilder::allow("view")
+                        .with_condition(all([expr("V.ok"), any([expr("true"), expr("(")])])),
+                ),
+            )
+            .build();
+        let found: Vec<_> = check_policy(&policy)
+            .into_iter()
+            .map(|e| (e.path, e.error.offset))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("principalPolicy.variables.local.bad".to_string(), 14),
+                (
+                    "principalPolicy.rules[0].actions[0].condition.match.all.of[1].any.of[1].expr"
+                        .to_string(),
+                    1
+                ),
+            ]
+        );
+
+        let policy = DerivedRolesBuilder::new("common_roles")
+            .add_definition(
+                RoleDefBuilder::new("owner", ["user"]).with_condition(expr("R.attr.owner == P.id")),
+            )
+            .build();
+        let paths: Vec<_> = policy_expressions(&policy)
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(paths, ["derivedRoles.definitions[0].condition.match.expr"]);
+        assert!(check_policy(&policy).is_empty());
+    }
+}
@@ -2,173 +2,668 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::{bail, Context};
-use serde::{Deserialize, Deserializer};
+use serde::{de::Visitor, Deserialize, Deserializer, Serializer};
 
-use crate::genpb::cerbos::{
-    effect::v1::Effect,
-    policy::v1::{
-        policy::PolicyType, DerivedRoles, ExportConstants, ExportVariables, Policy,
-        PrincipalPolicy, ResourcePolicy, RolePolicy,
+use crate::genpb::{
+    cerbos::{
+        effect::v1::Effect,
+        policy::v1::{
+            policy::PolicyType, DerivedRoles, ExportConstants, ExportVariables, Metadata, Policy,
+            PrincipalPolicy, ResourcePolicy, RolePolicy, ScopePermissions,
+        },
     },
+    google::protobuf::Timestamp,
 };
-use std::io::{BufRead, BufReader, Read};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{Read, Write};
 
 pub mod value;
 
+/// Parses a protobuf enum from its name inside the deserializer, so that errors point at the
+/// offending value rather than at the enclosing mapping.
+struct EnumName {
+    kind: &'static str,
+    from_str_name: fn(&str) -> Option<i32>,
+}
+
+impl<'de> Visitor<'de> for EnumName {
+    type Value = i32;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} name", self.kind)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<i32, E> {
+        (self.from_str_name)(s).ok_or_else(|| E::custom(format!("Unknown {}: {s}", self.kind)))
+    }
+}
+
+const EFFECT: EnumName = EnumName {
+    kind: "effect",
+    from_str_name: |s| Effect::from_str_name(s).map(|e| e as i32),
+};
+
+/// An effect value of a map, parsed with [`EFFECT`].
+struct EffectName(i32);
+
+impl<'de> Deserialize<'de> for EffectName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(EFFECT).map(EffectName)
+    }
+}
+
 pub(crate) fn deserialize_effect<'de, D>(deserializer: D) -> Result<i32, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
-    Effect::from_str_name(&s)
-        .map(|e| e as i32)
-        .ok_or_else(|| serde::de::Error::custom(format!("Unknown effect: {s}")))
+    deserializer.deserialize_str(EFFECT)
+}
+
+pub(crate) fn serialize_effect<S>(effect: &i32, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let e = Effect::try_from(*effect)
+        .map_err(|_| serde::ser::Error::custom(format!("Unknown effect: {effect}")))?;
+    serializer.serialize_str(e.as_str_name())
+}
+
+pub(crate) fn deserialize_effect_map<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, i32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(HashMap::<String, EffectName>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(action, EffectName(effect))| (action, effect))
+        .collect())
+}
+
+pub(crate) fn serialize_effect_map<S>(
+    effects: &HashMap<String, i32>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut names = BTreeMap::new();
+    for (action, effect) in effects {
+        let e = Effect::try_from(*effect)
+            .map_err(|_| serde::ser::Error::custom(format!("Unknown effect: {effect}")))?;
+        names.insert(action, e.as_str_name());
+    }
+    serializer.collect_map(names)
+}
+
+/// Timestamps are written as RFC 3339 strings, as in `now: "2022-08-02T15:00:00Z"`.
+pub(crate) fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<Timestamp>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct Rfc3339(Timestamp);
+
+    impl<'de> Deserialize<'de> for Rfc3339 {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_str(Rfc3339Visitor)
+        }
+    }
+
+    struct Rfc3339Visitor;
+
+    impl Visitor<'_> for Rfc3339Visitor {
+        type Value = Rfc3339;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an RFC 3339 timestamp")
+        }
+
+        fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Rfc3339, E> {
+            parse_rfc3339(s)
+                .map(Rfc3339)
+                .ok_or_else(|| E::custom(format!("Invalid timestamp: {s}")))
+        }
+    }
+
+    Ok(Option::<Rfc3339>::deserialize(deserializer)?.map(|Rfc3339(ts)| ts))
+}
+
+pub(crate) fn serialize_timestamp<S>(
+    timestamp: &Option<Timestamp>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match timestamp {
+        Some(ts) => serializer.serialize_str(&format_rfc3339(ts)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn parse_rfc3339(s: &str) -> Option<Timestamp> {
+    fn num(s: &str) -> Option<i64> {
+        (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| s.parse().ok())
+            .flatten()
+    }
+
+    let (date, time) = s.split_once(['T', 't', ' '])?;
+    let mut date = date.splitn(3, '-');
+    let (year, month, day) = (num(date.next()?)?, num(date.next()?)?, num(date.next()?)?);
+
+    let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let i = time.rfind(['+', '-'])?;
+            let (hh, mm) = time[i + 1..].split_once(':')?;
+            let offset = num(hh)? * 3600 + num(mm)? * 60;
+            (
+                &time[..i],
+                if &time[i..=i] == "-" { -offset } else { offset },
+            )
+        }
+    };
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':');
+    let (hour, minute, second) = (num(time.next()?)?, num(time.next()?)?, num(time.next()?)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        let digits = &fraction[..fraction.len().min(9)];
+        num(fraction)?;
+        num(digits)? * 10_i64.pow(9 - digits.len() as u32)
+    };
+
+    let days = days_from_civil(year, month, day);
+    Some(Timestamp {
+        seconds: days * 86400 + hour * 3600 + minute * 60 + second - offset,
+        nanos: nanos as i32,
+    })
+}
+
+fn format_rfc3339(ts: &Timestamp) -> String {
+    let (days, secs) = (ts.seconds.div_euclid(86400), ts.seconds.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    let mut s = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    );
+    if ts.nanos != 0 {
+        s.push_str(format!(".{:09}", ts.nanos).trim_end_matches('0'));
+    }
+    s.push('Z');
+    s
+}
+
+// Conversions between proleptic Gregorian dates and days since the Unix epoch, from
+// http://howardhinnant.github.io/date_algorithms.html.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+pub(crate) fn deserialize_scope_permissions<'de, D>(deserializer: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(EnumName {
+        kind: "scope permissions",
+        from_str_name: |s| ScopePermissions::from_str_name(s).map(|p| p as i32),
+    })
+}
+
+pub(crate) fn serialize_scope_permissions<S>(
+    permissions: &i32,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let p = ScopePermissions::try_from(*permissions).map_err(|_| {
+        serde::ser::Error::custom(format!("Unknown scope permissions: {permissions}"))
+    })?;
+    serializer.serialize_str(p.as_str_name())
+}
+
+pub(crate) fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
 
-const BUF_SIZE: usize = 1024 * 4; // 4KiB
 const MAX_FILE_SIZE: usize = 1024 * 1024 * 4; // 4MiB
 static JSON_START: &str = "{";
 
-trait PolicyDeser {
-    type Value: Clone;
-    fn from_value<T: serde::de::DeserializeOwned>(value: Self::Value) -> anyhow::Result<T>;
-    fn into<T: serde::de::DeserializeOwned>(self) -> anyhow::Result<T>;
-    fn get(&self, name: &str) -> Option<&Self::Value>;
+/// A policy document that could not be parsed.
+///
+/// `path` is the field that failed, for example `resourcePolicy.rules[3].condition`. Line and
+/// column are 1-based and refer to the whole source, so they stay accurate for the later documents
+/// of a YAML stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyParseError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub path: Option<String>,
+    pub message: String,
 }
 
-struct JsonPolicyDeser(serde_json::Value);
-impl JsonPolicyDeser {
-    fn new(buf: Vec<u8>) -> anyhow::Result<Self> {
-        Ok(Self(
-            serde_json::from_slice(&buf).with_context(|| "fail to deser from slice")?,
-        ))
+impl fmt::Display for PolicyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "line {line}, column {column}: ")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "{path}: ")?;
+        }
+        f.write_str(&self.message)
     }
 }
-impl PolicyDeser for JsonPolicyDeser {
-    type Value = serde_json::Value;
-    fn from_value<T: serde::de::DeserializeOwned>(value: Self::Value) -> anyhow::Result<T> {
-        serde_json::from_value(value).with_context(|| "fail to deserialize")
+
+impl std::error::Error for PolicyParseError {}
+
+impl PolicyParseError {
+    fn new(
+        path: &serde_path_to_error::Path,
+        message: String,
+        location: Option<(usize, usize)>,
+    ) -> Self {
+        let message = match location {
+            Some((line, column)) => strip_location(message, line, column),
+            None => message,
+        };
+        Self {
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            path: path.iter().next().map(|_| path.to_string()),
+            message,
+        }
     }
 
-    fn get(&self, name: &str) -> Option<&Self::Value> {
-        self.0.get(name)
+    fn from_json(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = err.path().clone();
+        Self::json(&path, err.into_inner())
     }
 
-    fn into<T: serde::de::DeserializeOwned>(self) -> anyhow::Result<T> {
-        serde_json::from_value(self.0).with_context(|| "fail to deserialize")
+    fn json(path: &serde_path_to_error::Path, err: serde_json::Error) -> Self {
+        let location = (err.line() > 0).then(|| (err.line(), err.column()));
+        Self::new(path, err.to_string(), location)
     }
-}
 
-struct YamlPolicyDeser(serde_yml::Value);
-impl YamlPolicyDeser {
-    fn new(buf: Vec<u8>) -> anyhow::Result<Self> {
-        Ok(Self(
-            serde_yml::from_slice(&buf).with_context(|| "fail to deser from slice")?,
-        ))
+    fn from_yaml(err: serde_path_to_error::Error<serde_yml::Error>) -> Self {
+        let path = err.path().clone();
+        let err = err.into_inner();
+        let location = err.location().map(|l| (l.line(), l.column()));
+        let mut message = err.to_string();
+        // serde_yml prefixes its own rendering of the path, as in `rules.\[1\]: unknown field`.
+        if let Some((prefix, rest)) = message.split_once(": ") {
+            if !prefix.contains(char::is_whitespace) {
+                message = rest.to_string();
+            }
+        }
+        Self::new(&path, message, location)
     }
 }
-impl PolicyDeser for YamlPolicyDeser {
-    type Value = serde_yml::Value;
 
-    fn from_value<T: serde::de::DeserializeOwned>(value: Self::Value) -> anyhow::Result<T> {
-        serde_yml::from_value(value).with_context(|| "fail to deserialize")
+// serde_json and serde_yml include the position in their messages; it is reported separately.
+fn strip_location(message: String, line: usize, column: usize) -> String {
+    message.replacen(&format!(" at line {line} column {column}"), "", 1)
+}
+
+/// A policy document. The policy type is a oneof keyed by the name of the document's top level
+/// field, which the generated `Policy` does not deserialize, so documents are read into this
+/// mirror of `Policy` instead.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct PolicyDoc {
+    api_version: String,
+    disabled: bool,
+    description: String,
+    metadata: Option<Metadata>,
+    variables: HashMap<String, String>,
+    json_schema: String,
+    resource_policy: Option<ResourcePolicy>,
+    principal_policy: Option<PrincipalPolicy>,
+    derived_roles: Option<DerivedRoles>,
+    export_variables: Option<ExportVariables>,
+    export_constants: Option<ExportConstants>,
+    role_policy: Option<RolePolicy>,
+}
+
+impl From<PolicyDoc> for Policy {
+    fn from(doc: PolicyDoc) -> Self {
+        let policy_type = doc
+            .resource_policy
+            .map(PolicyType::ResourcePolicy)
+            .or(doc.principal_policy.map(PolicyType::PrincipalPolicy))
+            .or(doc.derived_roles.map(PolicyType::DerivedRoles))
+            .or(doc.export_variables.map(PolicyType::ExportVariables))
+            .or(doc.export_constants.map(PolicyType::ExportConstants))
+            .or(doc.role_policy.map(PolicyType::RolePolicy));
+        Policy {
+            api_version: doc.api_version,
+            disabled: doc.disabled,
+            description: doc.description,
+            metadata: doc.metadata,
+            variables: doc.variables,
+            json_schema: doc.json_schema,
+            policy_type,
+        }
     }
+}
 
-    fn get(&self, name: &str) -> Option<&Self::Value> {
-        self.0.get(name)
+fn read_source(src: impl Read) -> anyhow::Result<String> {
+    let mut data = String::new();
+    src.take(MAX_FILE_SIZE as u64 + 1)
+        .read_to_string(&mut data)
+        .with_context(|| "failed to read from source")?;
+    if data.len() > MAX_FILE_SIZE {
+        bail!("file too large: policy files must not exceed {MAX_FILE_SIZE} bytes");
     }
+    Ok(data)
+}
+
+fn json_policy(src: &str) -> Result<Policy, PolicyParseError> {
+    let mut de = serde_json::Deserializer::from_str(src);
+    let doc: PolicyDoc =
+        serde_path_to_error::deserialize(&mut de).map_err(PolicyParseError::from_json)?;
+    // Trailing characters after the document.
+    de.end()
+        .map_err(|e| PolicyParseError::json(&serde_path_to_error::Track::new().path(), e))?;
+    Ok(doc.into())
+}
 
-    fn into<T: serde::de::DeserializeOwned>(self) -> anyhow::Result<T> {
-        serde_yml::from_value(self.0).with_context(|| "fail to deserialize")
+// Each document is deserialized straight from the source rather than through a `Value` so that
+// errors keep their position. Empty documents deserialize to `None`.
+fn yaml_policies(src: &str) -> anyhow::Result<Vec<Policy>> {
+    let mut policies = Vec::new();
+    for (i, doc) in serde_yml::Deserializer::from_str(src).enumerate() {
+        let doc: Option<PolicyDoc> = serde_path_to_error::deserialize(doc)
+            .map_err(PolicyParseError::from_yaml)
+            .with_context(|| format!("document {}", i + 1))?;
+        policies.extend(doc.map(Policy::from));
     }
+    Ok(policies)
 }
-enum Decoder {
-    Json(JsonPolicyDeser),
-    Yaml(YamlPolicyDeser),
+
+/// Read a single policy from a JSON or YAML document. Fails if the source contains more than one
+/// YAML document; use [`read_policies`] for those.
+pub fn read_policy(src: impl Read) -> anyhow::Result<Policy> {
+    let mut policies = read_policies(src)?;
+    match policies.len() {
+        0 => bail!("no policy found"),
+        1 => Ok(policies.remove(0)),
+        _ => bail!("multiple YAML docs"),
+    }
 }
-fn make_decoder(src: impl Read) -> anyhow::Result<Decoder> {
-    let mut buf = BufReader::with_capacity(BUF_SIZE, src);
 
-    // Peek at the buffer to determine format
-    let prelude = buf.fill_buf().map(String::from_utf8_lossy)?;
-    let trimmed = prelude.trim_start();
-    let result = if trimmed.starts_with(JSON_START) {
-        let mut h = buf.take(MAX_FILE_SIZE as u64);
-        let mut data = Vec::new();
-        let _ = h.read(&mut data)?;
-        Decoder::Json(JsonPolicyDeser::new(data)?)
+/// Read all policies from a JSON document or a YAML stream of any number of `---` separated
+/// documents. Empty documents are skipped.
+pub fn read_policies(src: impl Read) -> anyhow::Result<Vec<Policy>> {
+    let src = read_source(src)?;
+    if src.trim_start().starts_with(JSON_START) {
+        Ok(vec![json_policy(&src)?])
     } else {
-        let h = buf.take(MAX_FILE_SIZE as u64);
-        let data: Vec<u8> = parse_yaml(h)?;
-        Decoder::Yaml(YamlPolicyDeser::new(data)?)
-    };
-    Ok(result)
-}
-fn make_policy<T: PolicyDeser>(s: T) -> anyhow::Result<Policy> {
-    let pt = if let Some(v) = s.get("resourcePolicy") {
-        let p: ResourcePolicy = T::from_value(v.clone())?;
-        Some(PolicyType::ResourcePolicy(p))
-    } else if let Some(v) = s.get("principalPolicy") {
-        let p: PrincipalPolicy = T::from_value(v.clone())?;
-        Some(PolicyType::PrincipalPolicy(p))
-    } else if let Some(v) = s.get("derivedRoles") {
-        let p: DerivedRoles = T::from_value(v.clone())?;
-        Some(PolicyType::DerivedRoles(p))
-    } else if let Some(v) = s.get("exportVariables") {
-        let p: ExportVariables = T::from_value(v.clone())?;
-        Some(PolicyType::ExportVariables(p))
-    } else if let Some(v) = s.get("exportConstants") {
-        let p: ExportConstants = T::from_value(v.clone())?;
-        Some(PolicyType::ExportConstants(p))
-    } else if let Some(v) = s.get("rolePolicy") {
-        let p: RolePolicy = T::from_value(v.clone())?;
-        Some(PolicyType::RolePolicy(p))
+        yaml_policies(&src)
+    }
+}
+
+/// Read a single JSON or YAML document other than a policy, such as a test suite.
+pub(crate) fn read_document<T: serde::de::DeserializeOwned>(src: impl Read) -> anyhow::Result<T> {
+    let src = read_source(src)?;
+    if src.trim_start().starts_with(JSON_START) {
+        let mut de = serde_json::Deserializer::from_str(&src);
+        let doc = serde_path_to_error::deserialize(&mut de).map_err(PolicyParseError::from_json)?;
+        de.end()
+            .map_err(|e| PolicyParseError::json(&serde_path_to_error::Track::new().path(), e))?;
+        Ok(doc)
     } else {
-        None
-    };
-    let mut policy: Policy = s.into()?;
-    policy.policy_type = pt;
-    Ok(policy)
+        Ok(
+            serde_path_to_error::deserialize(serde_yml::Deserializer::from_str(&src))
+                .map_err(PolicyParseError::from_yaml)?,
+        )
+    }
 }
 
-pub fn read_policy(src: impl Read) -> anyhow::Result<Policy> {
-    match make_decoder(src)? {
-        Decoder::Json(j) => make_policy(j),
-        Decoder::Yaml(y) => make_policy(y),
+/// Output format of [`write_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyFormat {
+    Yaml,
+    Json,
+}
+
+/// Write a policy in canonical form: keys are sorted and fields holding their default value are
+/// omitted, so writing the same policy always produces the same document regardless of where it
+/// came from. The metadata a store attaches to the policies it returns (hash, store identifier
+/// and source attributes) is left out. The output can be read back with [`read_policy`].
+pub fn write_policy(
+    policy: &Policy,
+    format: PolicyFormat,
+    mut dst: impl Write,
+) -> anyhow::Result<()> {
+    let policy = without_store_metadata(policy);
+    let value = serde_json::to_value(&policy).with_context(|| "fail to serialize")?;
+    match format {
+        PolicyFormat::Json => {
+            serde_json::to_writer_pretty(&mut dst, &value).with_context(|| "fail to serialize")?;
+            dst.write_all(b"\n")?;
+        }
+        PolicyFormat::Yaml => {
+            serde_yml::to_writer(&mut dst, &value).with_context(|| "fail to serialize")?
+        }
     }
+    Ok(())
 }
-fn parse_yaml(reader: impl BufRead) -> anyhow::Result<Vec<u8>> {
-    const YAML_SEP: &str = "---";
-    const YAML_COMMENT: &str = "#";
-    const NEWLINE: u8 = b'\n';
 
-    let mut buf = Vec::new();
-    let mut num_docs = 0;
-    let mut seen_content = false;
+fn without_store_metadata(policy: &Policy) -> Policy {
+    let mut policy = policy.clone();
+    if let Some(metadata) = policy.metadata.as_mut() {
+        metadata.hash = None;
+        metadata.store_identifier.clear();
+        #[allow(deprecated)]
+        metadata.store_identifer.clear();
+        metadata.source_attributes = None;
+    }
+    policy.metadata = policy.metadata.filter(|m| !is_default(m));
+    policy
+}
 
-    for line_result in reader.lines() {
-        let line = line_result.with_context(|| "failed to read from source".to_string())?;
-        let trimmed_line = line.trim_start();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if trimmed_line.starts_with(YAML_COMMENT) {
-            continue;
-        }
+    #[test]
+    fn test_write_policy_round_trip() -> anyhow::Result<()> {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let dirs = [
+            "resources/store/derived_roles",
+            "resources/store/principal_policies",
+            "resources/store/resource_policies",
+            "tests/testdata/replace_files/success/resource_policies",
+        ];
+        for dir in dirs {
+            for entry in std::fs::read_dir(root.join(dir))? {
+                let path = entry?.path();
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if name.starts_with('.') || !name.ends_with(".yaml") || name.ends_with("_test.yaml")
+                {
+                    continue;
+                }
+                let policy = read_policy(std::fs::File::open(&path)?)?;
 
-        if !seen_content && trimmed_line.is_empty() {
-            continue;
-        }
-        seen_content = true;
+                let mut yaml = Vec::new();
+                write_policy(&policy, PolicyFormat::Yaml, &mut yaml)?;
+                let read_back = read_policy(yaml.as_slice())?;
+                assert_eq!(policy, read_back, "{}", path.display());
 
-        if line.starts_with(YAML_SEP) {
-            num_docs += 1;
-            if num_docs > 1 || (num_docs == 1 && !buf.is_empty()) {
-                bail!("multiple YAML docs")
+                let mut again = Vec::new();
+                write_policy(&read_back, PolicyFormat::Yaml, &mut again)?;
+                assert_eq!(yaml, again, "{}", path.display());
+
+                let mut json = Vec::new();
+                write_policy(&policy, PolicyFormat::Json, &mut json)?;
+                assert_eq!(policy, read_policy(json.as_slice())?, "{}", path.display());
             }
         }
+        Ok(())
+    }
 
-        buf.extend_from_slice(line.as_bytes());
-        buf.push(NEWLINE);
+    #[test]
+    fn test_write_policy_strips_store_metadata() -> anyhow::Result<()> {
+        use crate::genpb::google::protobuf::UInt64Value;
+
+        let mut policy = Policy {
+            api_version: "api.cerbos.dev/v1".to_string(),
+            policy_type: Some(PolicyType::DerivedRoles(DerivedRoles {
+                name: "common_roles".to_string(),
+                ..Default::default()
+            })),
+            metadata: Some(Metadata {
+                hash: Some(UInt64Value { value: 42 }),
+                store_identifier: "derived_roles/common_roles.yaml".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut yaml = Vec::new();
+        write_policy(&policy, PolicyFormat::Yaml, &mut yaml)?;
+        let yaml = String::from_utf8(yaml)?;
+        assert!(!yaml.contains("metadata"), "{yaml}");
+
+        policy.metadata.as_mut().unwrap().annotations =
+            HashMap::from([("owner".to_string(), "hr".to_string())]);
+        let mut json = Vec::new();
+        write_policy(&policy, PolicyFormat::Json, &mut json)?;
+        let read_back = read_policy(json.as_slice())?;
+        let metadata = read_back.metadata.unwrap();
+        assert_eq!(metadata.annotations["owner"], "hr");
+        assert_eq!(metadata.hash, None);
+        assert!(metadata.store_identifier.is_empty());
+        Ok(())
     }
 
-    Ok(buf)
+    #[test]
+    fn test_read_policies_multi_doc() -> anyhow::Result<()> {
+        let src = r#"# yaml-language-server: $schema=../policy.schema.json
+---
+apiVersion: api.cerbos.dev/v1
+derivedRoles:
+  name: common_roles
+  definitions:
+    - name: owner
+      parentRoles: ["user"]
+      condition:
+        match:
+          expr: R.attr.owner == P.id
+---
+---
+apiVersion: api.cerbos.dev/v1
+resourcePolicy:
+  resource: album
+  version: default
+  importDerivedRoles: [common_roles]
+  rules:
+    - actions: ["*"]
+      effect: EFFECT_ALLOW
+      derivedRoles: [owner]
+"#;
+        let policies = read_policies(src.as_bytes())?;
+        assert_eq!(policies.len(), 2);
+        assert!(matches!(
+            policies[0].policy_type,
+            Some(PolicyType::DerivedRoles(_))
+        ));
+        assert!(matches!(
+            policies[1].policy_type,
+            Some(PolicyType::ResourcePolicy(_))
+        ));
+
+        let err = read_policy(src.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "multiple YAML docs");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let yaml = r#"---
+apiVersion: api.cerbos.dev/v1
+derivedRoles:
+  name: common_roles
+  definitions: []
+---
+apiVersion: api.cerbos.dev/v1
+resourcePolicy:
+  resource: album
+  version: default
+  rules:
+    - actions: ["view"]
+      effect: EFFECT_ALLOW
+      roles: ["user"]
+    - actions: ["delete"]
+      effect: EFFECT_MAYBE
+      roles: ["user"]
+"#;
+        let err = read_policies(yaml.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PolicyParseError>(),
+            Some(&PolicyParseError {
+                line: Some(16),
+                column: Some(15),
+                path: Some("resourcePolicy.rules[1].effect".to_string()),
+                message: "Unknown effect: EFFECT_MAYBE".to_string(),
+            })
+        );
+
+        let json = r#"{
+  "apiVersion": "api.cerbos.dev/v1",
+  "resourcePolicy": {
+    "resource": "album",
+    "version": "default",
+    "rules": [{"actions": ["view"], "effect": "EFFECT_ALLOW", "roles": "user"}]
+  }
+}"#;
+        let err = read_policy(json.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 6, column 77: resourcePolicy.rules[0].roles: invalid type: string \"user\", expected a sequence"
+        );
+
+        let large = vec![b' '; MAX_FILE_SIZE + 1];
+        let err = read_policy(large.as_slice()).unwrap_err();
+        assert!(err.to_string().starts_with("file too large"));
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let ts = parse_rfc3339("2022-08-02T17:30:00.25+02:30").unwrap();
+        assert_eq!((ts.seconds, ts.nanos), (1659452400, 250_000_000));
+        assert_eq!(format_rfc3339(&ts), "2022-08-02T15:00:00.25Z");
+        assert_eq!(
+            parse_rfc3339("1969-12-31T23:59:59Z").map(|ts| ts.seconds),
+            Some(-1)
+        );
+        assert_eq!(parse_rfc3339("2022-13-02T15:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2022-08-02"), None);
+    }
 }
@@ -1,6 +1,7 @@
 use serde::{
     de::{self, MapAccess, SeqAccess, Visitor},
-    Deserialize, Deserializer,
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 use serde_json::Value as JsonValue;
 use serde_yml::Value as YamlValue;
@@ -201,6 +202,57 @@ impl<'de> Visitor<'de> for ListValueVisitor {
     }
 }
 
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.kind {
+            None | Some(Kind::NullValue(_)) => serializer.serialize_unit(),
+            Some(Kind::BoolValue(b)) => serializer.serialize_bool(*b),
+            // Whole numbers are written without a fractional part so that they round-trip as
+            // written in policy files.
+            Some(Kind::NumberValue(n))
+                if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 =>
+            {
+                serializer.serialize_i64(*n as i64)
+            }
+            Some(Kind::NumberValue(n)) => serializer.serialize_f64(*n),
+            Some(Kind::StringValue(s)) => serializer.serialize_str(s),
+            Some(Kind::ListValue(l)) => l.serialize(serializer),
+            Some(Kind::StructValue(s)) => s.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Struct {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        let mut map = serializer.serialize_map(Some(fields.len()))?;
+        for (k, v) in fields {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for ListValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.values.len()))?;
+        for v in &self.values {
+            seq.serialize_element(v)?;
+        }
+        seq.end()
+    }
+}
+
 // Helper functions for deserializing from JSON and YAML
 pub fn from_json_str(json_str: &str) -> Result<Value, serde_json::Error> {
     serde_json::from_str(json_str)
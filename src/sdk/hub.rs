@@ -36,6 +36,21 @@ where
     }
 }
 
+impl HubClient<AuthMiddleware> {
+    /// Replace the client credentials, for example after they have been rotated. Requests that
+    /// failed because the previous credentials were rejected can be retried afterwards.
+    pub async fn set_credentials(
+        &self,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) {
+        self.channel
+            .auth_client()
+            .set_credentials(Credentials::new(client_id.into(), client_secret.into()))
+            .await;
+    }
+}
+
 #[derive(Debug)]
 pub struct Credentials {
     pub client_id: String,
@@ -56,6 +71,7 @@ pub struct HubClientBuilder {
     credentials: Option<Credentials>,
     connect_timeout: Duration,
     request_timeout: Duration,
+    background_refresh: bool,
 }
 
 impl Default for HubClientBuilder {
@@ -70,6 +86,7 @@ impl HubClientBuilder {
             endpoint: "https://api.cerbos.cloud".to_string(),
             connect_timeout: Duration::from_secs(30),
             request_timeout: Duration::from_secs(60),
+            background_refresh: true,
             credentials: if let (Ok(id), Ok(secret)) = (
                 env::var("CERBOS_HUB_CLIENT_ID"),
                 env::var("CERBOS_HUB_CLIENT_SECRET"),
@@ -104,6 +121,12 @@ impl HubClientBuilder {
         self
     }
 
+    /// Refresh the access token in the background before it expires. Enabled by default.
+    pub fn with_background_refresh(mut self, enabled: bool) -> Self {
+        self.background_refresh = enabled;
+        self
+    }
+
     pub async fn build(self) -> Result<HubClient<AuthMiddleware>> {
         let endpoint = Endpoint::from_shared(self.endpoint.clone())
             .with_context(|| format!("Failed to create endpoint for {}", self.endpoint))?
@@ -126,6 +149,9 @@ impl HubClientBuilder {
         let credentials = Arc::new(self.credentials.with_context(|| "invalid credentials!")?);
 
         let auth_client = Arc::new(AuthClient::new(channel.clone(), credentials));
+        if self.background_refresh {
+            auth_client.spawn_background_refresh();
+        }
 
         let authenticated_channel = ServiceBuilder::new()
             .layer(tower::layer::layer_fn(move |inner| {
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::genpb::cerbos::cloud::apikey::v1::{
-    api_key_service_client::ApiKeyServiceClient, IssueAccessTokenRequest,
+    api_key_service_client::ApiKeyServiceClient, IssueAccessTokenRequest, IssueAccessTokenResponse,
 };
 use crate::sdk::hub::Credentials;
 use anyhow::Result;
@@ -11,8 +11,10 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use tonic::body::Body;
 use tonic::transport::Channel;
 use tonic::Request;
@@ -29,6 +31,10 @@ impl AuthMiddleware {
     pub fn new(inner: Channel, auth_client: Arc<AuthClient>) -> Self {
         AuthMiddleware { inner, auth_client }
     }
+
+    pub fn auth_client(&self) -> &Arc<AuthClient> {
+        &self.auth_client
+    }
 }
 
 impl Service<http::Request<Body>> for AuthMiddleware {
@@ -63,6 +69,36 @@ impl Service<http::Request<Body>> for AuthMiddleware {
 }
 
 const EARLY_EXPIRY: Duration = Duration::from_secs(300); // 5 minutes
+const REFRESH_AHEAD: Duration = Duration::from_secs(60);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+type StdError = Box<dyn std::error::Error + Send + Sync>;
+
+type IssueFuture =
+    Pin<Box<dyn Future<Output = Result<IssueAccessTokenResponse, tonic::Status>> + Send>>;
+
+/// Source of access tokens. The Hub API key service in production; tests substitute their own to
+/// drive the refresh schedule.
+trait TokenIssuer: Send + Sync {
+    fn issue(&self, credentials: &Credentials) -> IssueFuture;
+}
+
+impl TokenIssuer for ApiKeyServiceClient<Channel> {
+    fn issue(&self, credentials: &Credentials) -> IssueFuture {
+        let mut client = self.clone();
+        let request = Request::new(IssueAccessTokenRequest {
+            client_id: credentials.client_id.clone(),
+            client_secret: credentials.client_secret.clone(),
+        });
+        Box::pin(async move {
+            client
+                .issue_access_token(request)
+                .await
+                .map(|r| r.into_inner())
+        })
+    }
+}
 
 struct TokenInfo {
     token: String,
@@ -76,76 +112,154 @@ enum AuthState {
 }
 
 pub struct AuthClient {
-    api_key_client: ApiKeyServiceClient<Channel>,
-    credentials: Arc<Credentials>,
+    issuer: Box<dyn TokenIssuer>,
+    credentials: std::sync::RwLock<Arc<Credentials>>,
     auth_state: Arc<RwLock<AuthState>>,
+    // Held while a token is being issued, so that concurrent callers wait for one token instead
+    // of each issuing their own. `auth_state` is only locked to read or swap the token.
+    issue_lock: Mutex<()>,
+    credentials_changed: Arc<Notify>,
 }
 
 impl AuthClient {
     pub fn new(channel: Channel, credentials: Arc<Credentials>) -> Self {
-        let api_key_client = ApiKeyServiceClient::new(channel);
+        Self::with_issuer(ApiKeyServiceClient::new(channel), credentials)
+    }
 
+    fn with_issuer(issuer: impl TokenIssuer + 'static, credentials: Arc<Credentials>) -> Self {
         Self {
-            api_key_client,
-            credentials,
+            issuer: Box::new(issuer),
+            credentials: std::sync::RwLock::new(credentials),
             auth_state: Arc::new(RwLock::new(AuthState::None)),
+            issue_lock: Mutex::new(()),
+            credentials_changed: Arc::new(Notify::new()),
         }
     }
 
-    pub async fn authenticate(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        // Try to use existing token first
-        {
-            let auth_state_guard = self.auth_state.read().await;
-            if let AuthState::BadCredentials = *auth_state_guard {
-                return Err(Box::new(tonic::Status::new(
-                    tonic::Code::Unauthenticated,
-                    "short-circuiting auth because credentials are invalid",
-                )));
-            }
+    /// Replace the credentials used to issue access tokens. The current token is discarded and
+    /// authentication is attempted again even if the previous credentials were rejected.
+    pub async fn set_credentials(&self, credentials: Credentials) {
+        let mut auth_state_guard = self.auth_state.write().await;
+        *self.credentials.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(credentials);
+        *auth_state_guard = AuthState::None;
+        drop(auth_state_guard);
+        self.credentials_changed.notify_one();
+    }
 
-            if let AuthState::Authenticated(ref token_info) = *auth_state_guard {
-                if token_info.expires_at > Instant::now() {
-                    return Ok(token_info.token.clone());
+    /// Spawn a task that issues a new access token shortly before the current one expires, so
+    /// that requests never wait for a token to be issued. Failures are retried with exponential
+    /// backoff. When the credentials are rejected, the task waits for
+    /// [`AuthClient::set_credentials`]. The task stops when the client is dropped.
+    pub fn spawn_background_refresh(self: &Arc<Self>) -> JoinHandle<()> {
+        let client = Arc::downgrade(self);
+        let credentials_changed = self.credentials_changed.clone();
+        tokio::spawn(async move {
+            let mut backoff = MIN_BACKOFF;
+            loop {
+                let wait = match client.upgrade() {
+                    Some(client) => client.next_refresh().await,
+                    None => return,
+                };
+                match wait {
+                    Some(wait) => {
+                        tokio::select! {
+                            _ = tokio::time::sleep(wait) => {}
+                            _ = credentials_changed.notified() => {}
+                        }
+                    }
+                    None => credentials_changed.notified().await,
+                }
+
+                let Some(client) = client.upgrade() else {
+                    return;
+                };
+                if client.refresh().await.is_ok() {
+                    backoff = MIN_BACKOFF;
+                    continue;
+                }
+                drop(client);
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = credentials_changed.notified() => {}
                 }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
             }
+        })
+    }
+
+    /// Time until the token should be refreshed, or `None` if the credentials are bad.
+    async fn next_refresh(&self) -> Option<Duration> {
+        match *self.auth_state.read().await {
+            AuthState::Authenticated(ref token_info) => Some(
+                token_info
+                    .expires_at
+                    .saturating_duration_since(Instant::now())
+                    .saturating_sub(REFRESH_AHEAD),
+            ),
+            AuthState::BadCredentials => None,
+            AuthState::None => Some(Duration::ZERO),
         }
+    }
 
-        // Need to get a new token - acquire write lock
-        let mut auth_state_guard = self.auth_state.write().await;
+    /// Issue a new token even if the current one is still valid. Requests keep using the
+    /// current token until the new one is stored.
+    async fn refresh(&self) -> Result<String, StdError> {
+        let _issuing = self.issue_lock.lock().await;
+        if let AuthState::BadCredentials = *self.auth_state.read().await {
+            return Err(bad_credentials());
+        }
+        self.issue_token().await
+    }
+
+    pub async fn authenticate(&self) -> Result<String, StdError> {
+        // Try to use existing token first
+        if let Some(token) = self.current_token().await? {
+            return Ok(token);
+        }
+
+        // Need to get a new token - wait for any token being issued
+        let _issuing = self.issue_lock.lock().await;
 
-        // Double-check after acquiring write lock (another thread might have refreshed)
-        if let AuthState::BadCredentials = *auth_state_guard {
-            return Err(Box::new(tonic::Status::new(
-                tonic::Code::Unauthenticated,
-                "short-circuiting auth because credentials are invalid",
-            )));
+        // Double-check after acquiring the lock (another task might have refreshed)
+        if let Some(token) = self.current_token().await? {
+            return Ok(token);
         }
-        if let AuthState::Authenticated(ref token_info) = *auth_state_guard {
-            if token_info.expires_at > Instant::now() {
-                return Ok(token_info.token.clone());
+
+        self.issue_token().await
+    }
+
+    async fn current_token(&self) -> Result<Option<String>, StdError> {
+        match *self.auth_state.read().await {
+            AuthState::BadCredentials => Err(bad_credentials()),
+            AuthState::Authenticated(ref token_info) if token_info.expires_at > Instant::now() => {
+                Ok(Some(token_info.token.clone()))
             }
+            _ => Ok(None),
         }
+    }
 
-        let request = Request::new(IssueAccessTokenRequest {
-            client_id: self.credentials.client_id.clone(),
-            client_secret: self.credentials.client_secret.clone(),
-        });
-        let response = self
-            .api_key_client
+    fn credentials(&self) -> Arc<Credentials> {
+        self.credentials
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
             .clone()
-            .issue_access_token(request)
-            .await;
+    }
+
+    async fn issue_token(&self) -> Result<String, StdError> {
+        let credentials = self.credentials();
+        let response = self.issuer.issue(&credentials).await;
 
+        let mut auth_state_guard = self.auth_state.write().await;
+        // Credentials replaced while the token was being issued: don't record the outcome
+        let current = Arc::ptr_eq(&credentials, &self.credentials());
         match response {
             Err(e) => {
-                if e.code() == tonic::Code::Unauthenticated {
+                if current && e.code() == tonic::Code::Unauthenticated {
                     *auth_state_guard = AuthState::BadCredentials;
                 }
                 Err(Box::new(e))
             }
-            Ok(r) => {
-                let token_response = r.into_inner();
-
+            Ok(token_response) => {
                 let expires_in_duration = token_response
                     .expires_in
                     .as_ref()
@@ -158,14 +272,191 @@ impl AuthClient {
                 }
 
                 // Store the new token
-                let token_info = TokenInfo {
-                    token: token_response.access_token.clone(),
-                    expires_at: Instant::now() + effective_duration,
-                };
-
-                *auth_state_guard = AuthState::Authenticated(token_info);
+                if current {
+                    *auth_state_guard = AuthState::Authenticated(TokenInfo {
+                        token: token_response.access_token.clone(),
+                        expires_at: Instant::now() + effective_duration,
+                    });
+                }
                 Ok(token_response.access_token)
             }
         }
     }
 }
+
+impl Drop for AuthClient {
+    fn drop(&mut self) {
+        // Wake the background refresh task so that it notices the client is gone.
+        self.credentials_changed.notify_one();
+    }
+}
+
+fn bad_credentials() -> StdError {
+    Box::new(tonic::Status::new(
+        tonic::Code::Unauthenticated,
+        "short-circuiting auth because credentials are invalid",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use tokio::sync::Semaphore;
+
+    const EXPIRES_IN: Duration = Duration::from_secs(600);
+
+    // Issues `<client_id>-<n>` tokens valid for `EXPIRES_IN` unless a failure is queued, and
+    // records who asked and when. Every call waits for a permit from `gate`.
+    struct FakeIssuer {
+        start: Instant,
+        calls: std::sync::Mutex<Vec<(String, Duration)>>,
+        failures: std::sync::Mutex<VecDeque<tonic::Code>>,
+        gate: Arc<Semaphore>,
+    }
+
+    impl FakeIssuer {
+        fn new(failures: impl IntoIterator<Item = tonic::Code>) -> Arc<Self> {
+            Arc::new(Self {
+                start: Instant::now(),
+                calls: Default::default(),
+                failures: std::sync::Mutex::new(failures.into_iter().collect()),
+                gate: Arc::new(Semaphore::new(Semaphore::MAX_PERMITS)),
+            })
+        }
+
+        fn calls(&self) -> Vec<(String, Duration)> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl TokenIssuer for Arc<FakeIssuer> {
+        fn issue(&self, credentials: &Credentials) -> IssueFuture {
+            let fake = self.clone();
+            let client_id = credentials.client_id.clone();
+            Box::pin(async move {
+                fake.gate.acquire().await.unwrap().forget();
+                let mut calls = fake.calls.lock().unwrap();
+                calls.push((client_id.clone(), fake.start.elapsed()));
+                if let Some(code) = fake.failures.lock().unwrap().pop_front() {
+                    return Err(tonic::Status::new(code, "issue failed"));
+                }
+                Ok(IssueAccessTokenResponse {
+                    access_token: format!("{client_id}-{}", calls.len()),
+                    expires_in: Some(crate::genpb::google::protobuf::Duration {
+                        seconds: EXPIRES_IN.as_secs() as i64,
+                        nanos: 0,
+                    }),
+                })
+            })
+        }
+    }
+
+    fn credentials(client_id: &str) -> Credentials {
+        Credentials::new(client_id.to_string(), "secret".to_string())
+    }
+
+    fn client(issuer: &Arc<FakeIssuer>) -> Arc<AuthClient> {
+        Arc::new(AuthClient::with_issuer(
+            issuer.clone(),
+            Arc::new(credentials("first")),
+        ))
+    }
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_ahead_of_expiry() {
+        let issuer = FakeIssuer::new([]);
+        let client = client(&issuer);
+        let _task = client.spawn_background_refresh();
+
+        // Tokens are valid for EXPIRES_IN - EARLY_EXPIRY and renewed REFRESH_AHEAD before that.
+        tokio::time::sleep(secs(500)).await;
+        assert_eq!(
+            issuer.calls(),
+            [
+                ("first".to_string(), secs(0)),
+                ("first".to_string(), secs(240)),
+                ("first".to_string(), secs(480))
+            ]
+        );
+        assert_eq!(client.authenticate().await.unwrap(), "first-3");
+        assert_eq!(issuer.calls().len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_backoff() {
+        let issuer = FakeIssuer::new([tonic::Code::Unavailable; 3]);
+        let client = client(&issuer);
+        let _task = client.spawn_background_refresh();
+
+        tokio::time::sleep(secs(10)).await;
+        let times: Vec<Duration> = issuer.calls().into_iter().map(|(_, t)| t).collect();
+        assert_eq!(times, [secs(0), secs(1), secs(3), secs(7)]);
+        assert_eq!(client.authenticate().await.unwrap(), "first-4");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_waits_for_new_credentials() {
+        let issuer = FakeIssuer::new([tonic::Code::Unauthenticated]);
+        let client = client(&issuer);
+        let _task = client.spawn_background_refresh();
+
+        tokio::time::sleep(secs(3600)).await;
+        assert_eq!(issuer.calls(), [("first".to_string(), secs(0))]);
+        let err = client.authenticate().await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<tonic::Status>().map(|s| s.code()),
+            Some(tonic::Code::Unauthenticated)
+        );
+
+        client.set_credentials(credentials("second")).await;
+        tokio::time::sleep(secs(1)).await;
+        assert_eq!(
+            issuer.calls(),
+            [
+                ("first".to_string(), secs(0)),
+                ("second".to_string(), secs(3600))
+            ]
+        );
+        assert_eq!(client.authenticate().await.unwrap(), "second-2");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_for_replaced_credentials_is_dropped() {
+        let issuer = FakeIssuer::new([]);
+        let client = client(&issuer);
+        issuer.gate.forget_permits(Semaphore::MAX_PERMITS);
+
+        let pending = tokio::spawn({
+            let client = client.clone();
+            async move { client.authenticate().await.unwrap() }
+        });
+        tokio::task::yield_now().await;
+        client.set_credentials(credentials("second")).await;
+        issuer.gate.add_permits(Semaphore::MAX_PERMITS);
+
+        // The caller still gets the token it asked for, but it is not kept.
+        assert_eq!(pending.await.unwrap(), "first-1");
+        assert_eq!(client.authenticate().await.unwrap(), "second-2");
+        assert_eq!(client.authenticate().await.unwrap(), "second-2");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_stops_on_drop() {
+        let issuer = FakeIssuer::new([]);
+        let client = client(&issuer);
+        let task = client.spawn_background_refresh();
+
+        tokio::time::sleep(secs(1)).await;
+        drop(client);
+        tokio::time::timeout(secs(1), task)
+            .await
+            .expect("refresh task still running")
+            .unwrap();
+        assert_eq!(issuer.calls().len(), 1);
+    }
+}
@@ -8,12 +8,16 @@ use tonic::Status;
 
 use crate::genpb::{
     cerbos::cloud::store::v1::{
+        ErrDetailCannotModifyGitConnectedStore, ErrDetailConditionUnsatisfied,
         ErrDetailNoUsableFiles, ErrDetailOperationDiscarded, ErrDetailValidationFailure, FileError,
     },
     google::{protobuf::Any, rpc::Status as GoogleStatus},
 };
 
+/// Errors returned by the Hub store client. New variants may be added as the Hub reports new
+/// kinds of failures.
 #[derive(Error, Debug, Clone)]
+#[non_exhaustive]
 pub enum RPCError {
     #[error("{message:?}")]
     AuthenticationFailed {
@@ -21,9 +25,21 @@ pub enum RPCError {
         underlying: tonic::Status,
     },
     #[error("{message:?}")]
+    CannotModifyGitConnectedStore {
+        message: String,
+        underlying: tonic::Status,
+    },
+    #[error("{message:?}")]
     ConditionUnsatisfied {
         message: String,
         underlying: tonic::Status,
+        /// Version of the store, if the Hub reported it.
+        current_store_version: Option<i64>,
+    },
+    #[error("{message:?}")]
+    DeadlineExceeded {
+        message: String,
+        underlying: tonic::Status,
     },
     #[error("{message:?}")]
     InvalidRequest {
@@ -37,10 +53,17 @@ pub enum RPCError {
         ignored_files: Vec<String>,
     },
     #[error("{message:?}")]
+    NotFound {
+        message: String,
+        underlying: tonic::Status,
+    },
+    #[error("{message:?}")]
     OperationDiscarded {
         message: String,
         underlying: tonic::Status,
-        current_store_version: i64,
+        /// Version of the store, if the Hub reported it.
+        current_store_version: Option<i64>,
+        ignored_files: Vec<String>,
     },
     #[error("{message:?}")]
     PermissionDenied {
@@ -48,11 +71,21 @@ pub enum RPCError {
         underlying: tonic::Status,
     },
     #[error("{message:?}")]
+    ResourceExhausted {
+        message: String,
+        underlying: tonic::Status,
+    },
+    #[error("{message:?}")]
     StoreNotFound {
         message: String,
         underlying: tonic::Status,
     },
     #[error("{message:?}")]
+    Unavailable {
+        message: String,
+        underlying: tonic::Status,
+    },
+    #[error("{message:?}")]
     Unknown {
         message: String,
         underlying: tonic::Status,
@@ -66,6 +99,21 @@ pub enum RPCError {
     #[error("{message:?}")]
     ClientSideValidationError { message: String },
 }
+
+impl RPCError {
+    /// Whether the failure is transient, so that sending the same request again may succeed.
+    /// Requests rejected with [`RPCError::ConditionUnsatisfied`] must be rebuilt from the current
+    /// state of the store instead.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            RPCError::DeadlineExceeded { .. }
+                | RPCError::ResourceExhausted { .. }
+                | RPCError::Unavailable { .. }
+        )
+    }
+}
+
 trait TypeUrl {
     fn type_url() -> &'static str;
 }
@@ -79,83 +127,196 @@ impl TypeUrl for ErrDetailNoUsableFiles {
         "type.googleapis.com/cerbos.cloud.store.v1.ErrDetailNoUsableFiles"
     }
 }
+impl TypeUrl for ErrDetailConditionUnsatisfied {
+    fn type_url() -> &'static str {
+        "type.googleapis.com/cerbos.cloud.store.v1.ErrDetailConditionUnsatisfied"
+    }
+}
 impl TypeUrl for ErrDetailOperationDiscarded {
     fn type_url() -> &'static str {
         "type.googleapis.com/cerbos.cloud.store.v1.ErrDetailOperationDiscarded"
     }
 }
+impl TypeUrl for ErrDetailCannotModifyGitConnectedStore {
+    fn type_url() -> &'static str {
+        "type.googleapis.com/cerbos.cloud.store.v1.ErrDetailCannotModifyGitConnectedStore"
+    }
+}
+
+fn detail<T: Message + Default + TypeUrl>(details: &[Any]) -> Option<T> {
+    details
+        .iter()
+        .find(|d| d.type_url == T::type_url())
+        .and_then(|d| T::decode(d.value.as_slice()).ok())
+}
+
 impl From<tonic::Status> for RPCError {
     fn from(status: Status) -> Self {
+        let message = status.message().to_string();
+        let details = GoogleStatus::decode(status.details())
+            .map(|s| s.details)
+            .unwrap_or_default();
+
+        if let Some(inner) = detail::<ErrDetailValidationFailure>(&details) {
+            return RPCError::ValidationFailure {
+                message,
+                underlying: status,
+                validation_errors: inner.errors,
+            };
+        }
+        if let Some(inner) = detail::<ErrDetailNoUsableFiles>(&details) {
+            return RPCError::NoUsableFiles {
+                message,
+                underlying: status,
+                ignored_files: inner.ignored_files,
+            };
+        }
+        if let Some(inner) = detail::<ErrDetailConditionUnsatisfied>(&details) {
+            return RPCError::ConditionUnsatisfied {
+                message,
+                underlying: status,
+                current_store_version: Some(inner.current_store_version),
+            };
+        }
+        if let Some(inner) = detail::<ErrDetailOperationDiscarded>(&details) {
+            return RPCError::OperationDiscarded {
+                message,
+                underlying: status,
+                current_store_version: Some(inner.current_store_version),
+                ignored_files: inner.ignored_files,
+            };
+        }
+        if detail::<ErrDetailCannotModifyGitConnectedStore>(&details).is_some() {
+            return RPCError::CannotModifyGitConnectedStore {
+                message,
+                underlying: status,
+            };
+        }
+
         match status.code() {
             tonic::Code::PermissionDenied => RPCError::PermissionDenied {
-                message: status.message().to_string(),
+                message,
                 underlying: status,
             },
-            tonic::Code::NotFound => RPCError::StoreNotFound {
-                message: status.message().to_string(),
+            tonic::Code::NotFound => RPCError::NotFound {
+                message,
                 underlying: status,
             },
             tonic::Code::FailedPrecondition => RPCError::ConditionUnsatisfied {
-                message: status.message().to_string(),
+                message,
                 underlying: status,
+                current_store_version: None,
+            },
+            tonic::Code::InvalidArgument => RPCError::InvalidRequest {
+                message,
+                underlying: status,
+            },
+            tonic::Code::AlreadyExists => RPCError::OperationDiscarded {
+                message,
+                underlying: status,
+                current_store_version: None,
+                ignored_files: vec![],
             },
-            tonic::Code::InvalidArgument => {
-                if let Ok(google_status) = GoogleStatus::decode(status.details()) {
-                    for Any { type_url, value } in google_status.details {
-                        if type_url == ErrDetailValidationFailure::type_url() {
-                            if let Ok(inner) = ErrDetailValidationFailure::decode(value.as_slice())
-                            {
-                                return RPCError::ValidationFailure {
-                                    message: status.message().to_string(),
-                                    underlying: status,
-                                    validation_errors: inner.errors,
-                                };
-                            }
-                        } else if type_url == ErrDetailNoUsableFiles::type_url() {
-                            if let Ok(inner) = ErrDetailNoUsableFiles::decode(value.as_slice()) {
-                                return RPCError::NoUsableFiles {
-                                    message: status.message().to_string(),
-                                    underlying: status,
-                                    ignored_files: inner.ignored_files,
-                                };
-                            }
-                        }
-                    }
-                }
-                RPCError::InvalidRequest {
-                    message: status.message().to_string(),
-                    underlying: status,
-                }
-            }
-            tonic::Code::AlreadyExists => {
-                if let Ok(google_status) = GoogleStatus::decode(status.details()) {
-                    for Any { type_url, value } in google_status.details {
-                        if type_url == ErrDetailOperationDiscarded::type_url() {
-                            if let Ok(inner) = ErrDetailOperationDiscarded::decode(value.as_slice())
-                            {
-                                return RPCError::OperationDiscarded {
-                                    message: status.message().to_string(),
-                                    underlying: status,
-                                    current_store_version: inner.current_store_version,
-                                };
-                            }
-                        }
-                    }
-                }
-                RPCError::OperationDiscarded {
-                    message: status.message().to_string(),
-                    underlying: status,
-                    current_store_version: 0,
-                }
-            }
             tonic::Code::Unauthenticated => RPCError::AuthenticationFailed {
-                message: status.message().to_string(),
+                message,
+                underlying: status,
+            },
+            tonic::Code::Unavailable => RPCError::Unavailable {
+                message,
+                underlying: status,
+            },
+            tonic::Code::DeadlineExceeded => RPCError::DeadlineExceeded {
+                message,
+                underlying: status,
+            },
+            tonic::Code::ResourceExhausted => RPCError::ResourceExhausted {
+                message,
                 underlying: status,
             },
             _ => RPCError::Unknown {
-                message: status.message().to_string(),
+                message,
                 underlying: status,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_with_detail<T: Message + TypeUrl>(code: tonic::Code, detail: T) -> Status {
+        let details = GoogleStatus {
+            code: code as i32,
+            message: "failed".to_string(),
+            details: vec![Any {
+                type_url: T::type_url().to_string(),
+                value: detail.encode_to_vec(),
+            }],
+        };
+        Status::with_details(code, "failed", details.encode_to_vec().into())
+    }
+
+    #[test]
+    fn test_from_status() {
+        let err = RPCError::from(status_with_detail(
+            tonic::Code::FailedPrecondition,
+            ErrDetailConditionUnsatisfied {
+                current_store_version: 7,
+            },
+        ));
+        assert!(matches!(
+            err,
+            RPCError::ConditionUnsatisfied {
+                current_store_version: Some(7),
+                ..
+            }
+        ));
+        assert!(!err.is_retryable());
+
+        let err = RPCError::from(status_with_detail(
+            tonic::Code::FailedPrecondition,
+            ErrDetailCannotModifyGitConnectedStore {},
+        ));
+        assert!(matches!(
+            err,
+            RPCError::CannotModifyGitConnectedStore { .. }
+        ));
+
+        let err = RPCError::from(status_with_detail(
+            tonic::Code::AlreadyExists,
+            ErrDetailOperationDiscarded {
+                current_store_version: 3,
+                ignored_files: vec!["README.md".to_string()],
+            },
+        ));
+        assert!(matches!(
+            err,
+            RPCError::OperationDiscarded {
+                current_store_version: Some(3),
+                ref ignored_files,
+                ..
+            } if ignored_files == &["README.md"]
+        ));
+
+        let err = RPCError::from(Status::failed_precondition("failed"));
+        assert!(matches!(
+            err,
+            RPCError::ConditionUnsatisfied {
+                current_store_version: None,
+                ..
+            }
+        ));
+        let err = RPCError::from(Status::not_found("store not found"));
+        assert!(matches!(err, RPCError::NotFound { .. }));
+
+        for status in [
+            Status::unavailable("down"),
+            Status::deadline_exceeded("slow"),
+            Status::resource_exhausted("too many requests"),
+        ] {
+            assert!(RPCError::from(status).is_retryable());
+        }
+        assert!(!RPCError::from(Status::internal("oops")).is_retryable());
+    }
+}
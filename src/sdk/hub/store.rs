@@ -1,14 +1,20 @@
 // Copyright 2021-2025 Zenauth Ltd.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+use std::path::{Component, Path};
+
+use anyhow::Context;
+use sha2::{Digest, Sha256};
 
 use crate::genpb::cerbos::cloud::store::v1::{
     cerbos_store_service_client::CerbosStoreServiceClient,
     change_details::{Git, Internal, Origin, Uploader},
     file_op::Op,
     modify_files_request::Condition as ModifyCondition,
-    replace_files_request::{Condition as ReplaceCondition, Contents},
+    replace_files_request::{Condition as ReplaceCondition, Contents, Files},
     string_match::{InList, Match},
     ChangeDetails, File, FileFilter, FileOp, GetFilesRequest, GetFilesResponse, ListFilesRequest,
     ListFilesResponse, ModifyFilesRequest, ModifyFilesResponse, ReplaceFilesRequest,
@@ -16,9 +22,79 @@ use crate::genpb::cerbos::cloud::store::v1::{
 };
 
 use super::rpc_error::RPCError;
+use super::utils::{read_directory, STORE_MANIFEST};
+
+const MAX_GET_FILES: usize = 10;
+const MAX_MODIFY_OPS: usize = 25;
+const DEFAULT_MAX_UPDATE_ATTEMPTS: usize = 5;
+
+/// Files changed by [`StoreClient::sync_files`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncResult {
+    /// Store version after the sync. It is the version that was read if nothing changed.
+    pub store_version: i64,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+impl SyncResult {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// Store ID and version of a store downloaded by [`StoreClient::download_store`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreManifest {
+    pub store_id: String,
+    pub store_version: i64,
+}
+
+impl StoreManifest {
+    /// Read the manifest of a store downloaded to `dir`.
+    pub fn read(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = dir.as_ref().join(STORE_MANIFEST);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut store_id = None;
+        let mut store_version = None;
+        for line in contents.lines() {
+            match line.split_once('=') {
+                Some(("storeId", v)) => store_id = Some(v.trim().to_string()),
+                Some(("storeVersion", v)) => {
+                    store_version = Some(v.trim().parse().with_context(|| {
+                        format!("invalid storeVersion {v:?} in {}", path.display())
+                    })?)
+                }
+                _ => {}
+            }
+        }
+        match (store_id, store_version) {
+            (Some(store_id), Some(store_version)) => Ok(Self {
+                store_id,
+                store_version,
+            }),
+            _ => anyhow::bail!("{} is not a store manifest", path.display()),
+        }
+    }
+
+    /// Write the manifest to `dir`.
+    pub fn write(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::create_dir_all(dir.as_ref())?;
+        fs::write(
+            dir.as_ref().join(STORE_MANIFEST),
+            format!(
+                "storeId={}\nstoreVersion={}\n",
+                self.store_id, self.store_version
+            ),
+        )
+    }
+}
 
 pub struct StoreClient<T> {
     client: CerbosStoreServiceClient<T>,
+    max_update_attempts: usize,
 }
 
 type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
@@ -32,9 +108,17 @@ where
     pub fn new(channel: T) -> Self {
         Self {
             client: CerbosStoreServiceClient::new(channel),
+            max_update_attempts: DEFAULT_MAX_UPDATE_ATTEMPTS,
         }
     }
 
+    /// Number of times [`StoreClient::update_with`] tries to apply changes before giving up on
+    /// concurrent modifications. Defaults to 5.
+    pub fn with_max_update_attempts(mut self, attempts: usize) -> Self {
+        self.max_update_attempts = attempts.max(1);
+        self
+    }
+
     fn validation_error(msg: &str) -> RPCError {
         RPCError::ClientSideValidationError {
             message: msg.to_string(),
@@ -45,16 +129,19 @@ where
         &mut self,
         request: ReplaceFilesRequest,
     ) -> Result<ReplaceFilesResponse, RPCError> {
+        let store_id = request.store_id.clone();
         let result = self.replace_files(request).await;
         match result {
             Ok(response) => Ok(response),
             Err(RPCError::OperationDiscarded {
-                message: _,
-                underlying: _,
                 current_store_version,
+                ignored_files,
+                ..
             }) => Ok(ReplaceFilesResponse {
-                new_store_version: current_store_version,
-                ignored_files: vec![],
+                new_store_version: self
+                    .current_store_version(&store_id, current_store_version)
+                    .await?,
+                ignored_files,
             }),
             Err(e) => Err(e),
         }
@@ -110,7 +197,11 @@ where
                 });
             }
         };
-        let response = self.client.replace_files(request).await?;
+        let response = self
+            .client
+            .replace_files(request)
+            .await
+            .map_err(store_error)?;
 
         Ok(response.into_inner())
     }
@@ -123,7 +214,11 @@ where
         if request.store_id.is_empty() {
             return Err(Self::validation_error("store_id is required"));
         }
-        let response = self.client.modify_files(request).await?;
+        let response = self
+            .client
+            .modify_files(request)
+            .await
+            .map_err(store_error)?;
 
         Ok(response.into_inner())
     }
@@ -136,7 +231,7 @@ where
         if request.store_id.is_empty() {
             return Err(Self::validation_error("store_id is required"));
         }
-        let response = self.client.list_files(request).await?;
+        let response = self.client.list_files(request).await.map_err(store_error)?;
 
         Ok(response.into_inner())
     }
@@ -149,10 +244,373 @@ where
         if request.store_id.is_empty() {
             return Err(Self::validation_error("store_id is required"));
         }
-        let response = self.client.get_files(request).await?;
+        let response = self.client.get_files(request).await.map_err(store_error)?;
 
         Ok(response.into_inner())
     }
+
+    /// Make the store contain exactly `files`, sending only the files that were added, changed
+    /// or deleted.
+    ///
+    /// Files that the Hub does not store (hidden files, `testdata` directories outside `tests`
+    /// and files that are neither YAML nor JSON) are ignored. Remote files are fetched to compare
+    /// their SHA-256 digests with the local ones. The changes are conditioned on the store
+    /// version that was read, so the sync fails with [`RPCError::ConditionUnsatisfied`] if the
+    /// store is modified concurrently. The changes are always applied in a single request so
+    /// that the store never holds a partial sync: when there are more than a
+    /// `ModifyFilesRequest` allows, all of `files` are sent in a `ReplaceFilesRequest` instead.
+    /// Changes that the Hub discards as leaving the store unchanged are reported as an empty
+    /// result with the current store version.
+    pub async fn sync_files(
+        &mut self,
+        store_id: impl Into<String>,
+        mut files: Vec<File>,
+        change_details: ChangeDetails,
+    ) -> Result<SyncResult, RPCError> {
+        let store_id = store_id.into();
+        let listing = self
+            .list_files(ListFilesRequestBuilder::new(&store_id).build())
+            .await?;
+
+        let mut remote = HashMap::with_capacity(listing.files.len());
+        for batch in listing.files.chunks(MAX_GET_FILES) {
+            let response = self
+                .get_files(GetFilesRequestBuilder::new(&store_id, batch).build())
+                .await?;
+            remote.extend(
+                response
+                    .files
+                    .into_iter()
+                    .map(|f| (f.path, Sha256::digest(&f.contents))),
+            );
+        }
+
+        files.retain(|f| is_store_file(&f.path));
+        let (ops, mut result) = diff_files(&files, &remote);
+        result.store_version = listing.store_version;
+        if ops.is_empty() {
+            return Ok(result);
+        }
+
+        let applied = if ops.len() <= MAX_MODIFY_OPS {
+            let request = ModifyFilesRequestBuilder::new(&store_id, "")
+                .with_change_details(change_details)
+                .add_operations(ops)
+                .only_if_version_equals(listing.store_version)
+                .build();
+            self.modify_files(request)
+                .await
+                .map(|response| response.new_store_version)
+        } else {
+            let request = ReplaceFilesRequest {
+                store_id: store_id.clone(),
+                condition: Some(ReplaceCondition {
+                    store_version_must_equal: listing.store_version,
+                }),
+                change_details: Some(change_details),
+                contents: Some(Contents::Files(Files { files })),
+            };
+            self.replace_files(request)
+                .await
+                .map(|response| response.new_store_version)
+        };
+
+        match applied {
+            Ok(store_version) => {
+                result.store_version = store_version;
+                Ok(result)
+            }
+            Err(RPCError::OperationDiscarded {
+                current_store_version,
+                ..
+            }) => Ok(SyncResult {
+                store_version: self
+                    .current_store_version(&store_id, current_store_version)
+                    .await?,
+                ..Default::default()
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Make the store contain exactly the files in `dir`. See [`StoreClient::sync_files`].
+    pub async fn sync_directory(
+        &mut self,
+        store_id: impl Into<String>,
+        dir: impl AsRef<Path>,
+        change_details: ChangeDetails,
+    ) -> Result<SyncResult, RPCError> {
+        let files = read_directory(dir.as_ref()).map_err(|e| file_error(dir.as_ref(), e))?;
+        self.sync_files(store_id, files, change_details).await
+    }
+
+    /// Write all files of the store to `dir` with their relative paths, and record the store ID
+    /// and version in a [`STORE_MANIFEST`] file.
+    ///
+    /// Files are fetched in batches. The download starts over if the store is modified while it
+    /// is in progress. Other files already in `dir` are left alone.
+    pub async fn download_store(
+        &mut self,
+        store_id: impl Into<String>,
+        dir: impl AsRef<Path>,
+    ) -> Result<StoreManifest, RPCError> {
+        const MAX_ATTEMPTS: usize = 3;
+
+        let store_id = store_id.into();
+        let dir = dir.as_ref();
+        for _ in 0..MAX_ATTEMPTS {
+            let downloaded = self
+                .visit_store(&store_id, |files| {
+                    files.iter().try_for_each(|file| write_file(dir, file))
+                })
+                .await?;
+            if let Some(store_version) = downloaded {
+                let manifest = StoreManifest {
+                    store_id,
+                    store_version,
+                };
+                manifest.write(dir).map_err(|e| file_error(dir, e))?;
+                return Ok(manifest);
+            }
+        }
+        Err(Self::validation_error(
+            "store was modified while it was being downloaded",
+        ))
+    }
+
+    /// Apply the changes produced by `f` from the current files of the store, retrying if the
+    /// store is modified concurrently.
+    ///
+    /// `f` receives all files of the store and returns the operations to apply, conditioned on
+    /// the store version that was read. If another change lands first, the store is read again
+    /// and `f` is re-run, up to the configured number of attempts (see
+    /// [`StoreClient::with_max_update_attempts`]). Changes that leave the store as it was are
+    /// discarded by the Hub and reported as success with the current store version, as are
+    /// empty operation lists.
+    ///
+    /// ```rust,no_run
+    /// # use cerbos::genpb::cerbos::cloud::store::v1::{file_op::Op, FileOp};
+    /// # use cerbos::sdk::hub::{store::ChangeDetailsBuilder, HubClientBuilder};
+    /// # async fn example() -> anyhow::Result<()> {
+    /// let mut store = HubClientBuilder::new().build().await?.store_client();
+    /// let response = store
+    ///     .update_with(
+    ///         "MWPKEMFX3CK1",
+    ///         ChangeDetailsBuilder::new("Remove drafts").build(),
+    ///         |files| {
+    ///             files
+    ///                 .iter()
+    ///                 .filter(|f| f.path.starts_with("drafts/"))
+    ///                 .map(|f| FileOp {
+    ///                     op: Some(Op::Delete(f.path.clone())),
+    ///                 })
+    ///                 .collect()
+    ///         },
+    ///     )
+    ///     .await?;
+    /// println!("store version is {}", response.new_store_version);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update_with<F>(
+        &mut self,
+        store_id: impl Into<String>,
+        change_details: ChangeDetails,
+        mut f: F,
+    ) -> Result<ModifyFilesResponse, RPCError>
+    where
+        F: FnMut(&[File]) -> Vec<FileOp>,
+    {
+        let store_id = store_id.into();
+        let mut last_error = None;
+        for _ in 0..self.max_update_attempts {
+            let Some((store_version, files)) = self.read_store(&store_id).await? else {
+                continue;
+            };
+            let ops = f(&files);
+            if ops.is_empty() {
+                return Ok(ModifyFilesResponse {
+                    new_store_version: store_version,
+                });
+            }
+            if ops.len() > MAX_MODIFY_OPS {
+                return Err(RPCError::ClientSideValidationError {
+                    message: format!(
+                        "{} operations exceed the limit of {MAX_MODIFY_OPS} per change",
+                        ops.len()
+                    ),
+                });
+            }
+
+            let request = ModifyFilesRequestBuilder::new(&store_id, "")
+                .with_change_details(change_details.clone())
+                .add_operations(ops)
+                .only_if_version_equals(store_version)
+                .build();
+            match self.modify_files(request).await {
+                Ok(response) => return Ok(response),
+                Err(RPCError::OperationDiscarded {
+                    current_store_version,
+                    ..
+                }) => {
+                    return Ok(ModifyFilesResponse {
+                        new_store_version: self
+                            .current_store_version(&store_id, current_store_version)
+                            .await?,
+                    })
+                }
+                Err(e @ RPCError::ConditionUnsatisfied { .. }) => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            Self::validation_error("store was modified while it was being read")
+        }))
+    }
+
+    /// The store version reported in an error, or the version read from the store if the Hub
+    /// did not report one.
+    async fn current_store_version(
+        &mut self,
+        store_id: &str,
+        reported: Option<i64>,
+    ) -> Result<i64, RPCError> {
+        match reported {
+            Some(version) => Ok(version),
+            None => Ok(self
+                .list_files(ListFilesRequestBuilder::new(store_id).build())
+                .await?
+                .store_version),
+        }
+    }
+
+    /// Read all files of the store. Returns `None` if the store was modified in between the
+    /// requests.
+    async fn read_store(&mut self, store_id: &str) -> Result<Option<(i64, Vec<File>)>, RPCError> {
+        let mut files = Vec::new();
+        let store_version = self
+            .visit_store(store_id, |batch| {
+                files.extend(batch);
+                Ok(())
+            })
+            .await?;
+
+        Ok(store_version.map(|version| (version, files)))
+    }
+
+    /// Pass the files of the store to `f` one batch at a time. Returns the store version, or
+    /// `None` if the store was modified in between the requests.
+    async fn visit_store<F>(&mut self, store_id: &str, mut f: F) -> Result<Option<i64>, RPCError>
+    where
+        F: FnMut(Vec<File>) -> Result<(), RPCError>,
+    {
+        let listing = self
+            .list_files(ListFilesRequestBuilder::new(store_id).build())
+            .await?;
+        for batch in listing.files.chunks(MAX_GET_FILES) {
+            let response = self
+                .get_files(GetFilesRequestBuilder::new(store_id, batch).build())
+                .await?;
+            if response.store_version != listing.store_version {
+                return Ok(None);
+            }
+            f(response.files)?;
+        }
+
+        Ok(Some(listing.store_version))
+    }
+}
+
+/// Every store RPC addresses a single store, so `NotFound` means that the store does not exist.
+fn store_error(status: tonic::Status) -> RPCError {
+    match RPCError::from(status) {
+        RPCError::NotFound {
+            message,
+            underlying,
+        } => RPCError::StoreNotFound {
+            message,
+            underlying,
+        },
+        err => err,
+    }
+}
+
+fn file_error(path: &Path, err: impl Display) -> RPCError {
+    RPCError::ClientSideValidationError {
+        message: format!("{}: {err}", path.display()),
+    }
+}
+
+fn write_file(dir: &Path, file: &File) -> Result<(), RPCError> {
+    let relative = Path::new(&file.path);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(RPCError::ClientSideValidationError {
+            message: format!("invalid file path {:?} in store", file.path),
+        });
+    }
+    let path = dir.join(relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| file_error(parent, e))?;
+    }
+    fs::write(&path, &file.contents).map_err(|e| file_error(&path, e))
+}
+
+/// Whether the Hub keeps a file with the given path, mirroring the filter it applies to uploads.
+fn is_store_file(path: &str) -> bool {
+    let components: Vec<_> = path.split('/').collect();
+    if components.iter().any(|c| c.starts_with('.')) {
+        return false;
+    }
+    if let Some(i) = components.iter().position(|c| *c == "testdata") {
+        if !components[..i].contains(&"tests") {
+            return false;
+        }
+    }
+    match components.first() {
+        Some(&"_schemas") => path.ends_with(".json"),
+        _ => [".yaml", ".yml", ".json"]
+            .iter()
+            .any(|ext| path.ends_with(ext)),
+    }
+}
+
+fn diff_files<D: AsRef<[u8]>>(
+    local: &[File],
+    remote: &HashMap<String, D>,
+) -> (Vec<FileOp>, SyncResult) {
+    let mut local: Vec<_> = local.iter().collect();
+    local.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut result = SyncResult::default();
+    let mut ops = Vec::new();
+    let mut seen = HashSet::with_capacity(local.len());
+    for file in local {
+        seen.insert(file.path.clone());
+        match remote.get(&file.path) {
+            Some(digest) if digest.as_ref() == Sha256::digest(&file.contents).as_slice() => {
+                continue
+            }
+            Some(_) => result.updated.push(file.path.clone()),
+            None => result.added.push(file.path.clone()),
+        }
+        ops.push(FileOp {
+            op: Some(Op::AddOrUpdate(file.clone())),
+        });
+    }
+
+    result.deleted = remote
+        .keys()
+        .filter(|path| !seen.contains(*path))
+        .cloned()
+        .collect();
+    result.deleted.sort();
+    ops.extend(result.deleted.iter().map(|path| FileOp {
+        op: Some(Op::Delete(path.clone())),
+    }));
+
+    (ops, result)
 }
 
 #[derive(Debug, Clone)]
@@ -431,3 +889,112 @@ impl FileFilterBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, contents: &str) -> File {
+        File {
+            path: path.to_string(),
+            contents: contents.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let remote: HashMap<_, _> = [
+            ("same.yaml", "a"),
+            ("changed.yaml", "b"),
+            ("gone.yaml", "c"),
+        ]
+        .into_iter()
+        .map(|(path, contents)| (path.to_string(), Sha256::digest(contents)))
+        .collect();
+        let local = vec![
+            file("new.yaml", "d"),
+            file("changed.yaml", "B"),
+            file("same.yaml", "a"),
+        ];
+
+        let (ops, result) = diff_files(&local, &remote);
+        assert_eq!(result.added, ["new.yaml"]);
+        assert_eq!(result.updated, ["changed.yaml"]);
+        assert_eq!(result.deleted, ["gone.yaml"]);
+        assert_eq!(
+            ops,
+            [
+                FileOp {
+                    op: Some(Op::AddOrUpdate(file("changed.yaml", "B"))),
+                },
+                FileOp {
+                    op: Some(Op::AddOrUpdate(file("new.yaml", "d"))),
+                },
+                FileOp {
+                    op: Some(Op::Delete("gone.yaml".to_string())),
+                },
+            ]
+        );
+
+        let (ops, result) = diff_files(&[file("same.yaml", "a")], &remote);
+        assert!(ops.iter().all(|op| matches!(op.op, Some(Op::Delete(_)))));
+        assert_eq!(result.deleted, ["changed.yaml", "gone.yaml"]);
+    }
+
+    #[test]
+    fn test_is_store_file() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/testdata/replace_files/success");
+        let mut ignored: Vec<_> = read_directory(&root)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.path)
+            .filter(|path| !is_store_file(path))
+            .collect();
+        ignored.sort();
+        assert_eq!(
+            ignored,
+            [
+                "_schemas/.hidden_directory/ignored.json",
+                "_schemas/.hidden_file.json",
+                "_schemas/ignored.yaml",
+                "resource_policies/.hidden_directory/ignored.yaml",
+                "resource_policies/.hidden_file.yaml",
+                "resource_policies/testdata/ignored.yaml",
+            ]
+        );
+
+        assert!(is_store_file("tests/testdata/principals.yml"));
+        assert!(!is_store_file("README.md"));
+    }
+
+    #[test]
+    fn test_store_error() {
+        let err = store_error(tonic::Status::not_found("no such thing"));
+        assert!(matches!(err, RPCError::StoreNotFound { .. }));
+        let err = store_error(tonic::Status::invalid_argument("bad request"));
+        assert!(matches!(err, RPCError::InvalidRequest { .. }));
+    }
+
+    #[test]
+    fn test_write_store_files() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cerbos-store-{}", uuid::Uuid::new_v4()));
+
+        write_file(&dir, &file("derived_roles/common.yaml", "a"))?;
+        assert!(write_file(&dir, &file("../escape.yaml", "b")).is_err());
+        assert!(write_file(&dir, &file("/etc/escape.yaml", "b")).is_err());
+
+        let manifest = StoreManifest {
+            store_id: "MWPKEMFX3CK1".to_string(),
+            store_version: 42,
+        };
+        manifest.write(&dir)?;
+        assert_eq!(StoreManifest::read(&dir)?, manifest);
+
+        let files = read_directory(&dir)?;
+        assert_eq!(files, [file("derived_roles/common.yaml", "a")]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -8,6 +8,8 @@ use std::{
 use walkdir::WalkDir;
 use zip::write::SimpleFileOptions;
 
+use crate::genpb::cerbos::cloud::store::v1::File;
+
 /// Utility function to create zipped data from a directory
 pub fn zip_directory(dir_path: &std::path::Path) -> anyhow::Result<Vec<u8>> {
     let mut buffer = Vec::new();
@@ -40,3 +42,32 @@ pub fn zip_directory(dir_path: &std::path::Path) -> anyhow::Result<Vec<u8>> {
 
     Ok(buffer)
 }
+
+/// Name of the manifest file written by [`super::store::StoreClient::download_store`]
+pub const STORE_MANIFEST: &str = ".cerbos-hub-store";
+
+/// Utility function to read all files in a directory, with paths relative to the directory.
+/// The store manifest is skipped.
+pub fn read_directory(dir_path: &std::path::Path) -> anyhow::Result<Vec<File>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir_path).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file()
+            || (entry.depth() == 1 && entry.file_name() == STORE_MANIFEST)
+        {
+            continue;
+        }
+        let name = entry.path().strip_prefix(dir_path)?;
+        let path = name
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push(File {
+            path,
+            contents: std::fs::read(entry.path())?,
+        });
+    }
+
+    Ok(files)
+}
@@ -0,0 +1,709 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::genpb::google::api::expr::v1alpha1::{
+    constant::ConstantKind,
+    expr::{create_struct::entry::KeyKind, ExprKind},
+    Expr,
+};
+use crate::genpb::google::protobuf::{value::Kind, ListValue, Struct, Value};
+
+/// Runtime value of a CEL expression.
+#[derive(Debug, Clone)]
+pub(crate) enum Val {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Double(f64),
+    String(Arc<str>),
+    Bytes(Arc<[u8]>),
+    List(Arc<Vec<Val>>),
+    Map(Arc<BTreeMap<String, Val>>),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EvalError(String);
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub(crate) type EvalResult = Result<Val, EvalError>;
+
+fn err<T>(msg: impl Into<String>) -> Result<T, EvalError> {
+    Err(EvalError(msg.into()))
+}
+
+impl Val {
+    pub(crate) fn string(s: impl AsRef<str>) -> Self {
+        Val::String(Arc::from(s.as_ref()))
+    }
+
+    pub(crate) fn list(values: impl IntoIterator<Item = Val>) -> Self {
+        Val::List(Arc::new(values.into_iter().collect()))
+    }
+
+    pub(crate) fn map<K: Into<String>>(entries: impl IntoIterator<Item = (K, Val)>) -> Self {
+        Val::Map(Arc::new(
+            entries.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        ))
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Val::Null => "null",
+            Val::Bool(_) => "bool",
+            Val::Int(_) => "int",
+            Val::Uint(_) => "uint",
+            Val::Double(_) => "double",
+            Val::String(_) => "string",
+            Val::Bytes(_) => "bytes",
+            Val::List(_) => "list",
+            Val::Map(_) => "map",
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Val::Int(i) => Some(*i as f64),
+            Val::Uint(u) => Some(*u as f64),
+            Val::Double(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    fn as_index(&self) -> Option<usize> {
+        match self {
+            Val::Int(i) => usize::try_from(*i).ok(),
+            Val::Uint(u) => usize::try_from(*u).ok(),
+            Val::Double(d) if d.fract() == 0.0 && *d >= 0.0 => Some(*d as usize),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Result<&str, EvalError> {
+        match self {
+            Val::String(s) => Ok(s),
+            v => err(format!("expected string, got {}", v.type_name())),
+        }
+    }
+
+    fn to_display_string(&self) -> String {
+        match self {
+            Val::Null => "null".to_string(),
+            Val::Bool(b) => b.to_string(),
+            Val::Int(i) => i.to_string(),
+            Val::Uint(u) => u.to_string(),
+            Val::Double(d) => d.to_string(),
+            Val::String(s) => s.to_string(),
+            Val::Bytes(b) => String::from_utf8_lossy(b).into_owned(),
+            Val::List(l) => format!(
+                "[{}]",
+                l.iter()
+                    .map(Val::to_display_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Val::Map(m) => format!(
+                "{{{}}}",
+                m.iter()
+                    .map(|(k, v)| format!("{k}: {}", v.to_display_string()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl PartialEq for Val {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Val::Null, Val::Null) => true,
+            (Val::Bool(a), Val::Bool(b)) => a == b,
+            (Val::String(a), Val::String(b)) => a == b,
+            (Val::Bytes(a), Val::Bytes(b)) => a == b,
+            (Val::List(a), Val::List(b)) => a == b,
+            (Val::Map(a), Val::Map(b)) => a == b,
+            (Val::Int(a), Val::Int(b)) => a == b,
+            (Val::Uint(a), Val::Uint(b)) => a == b,
+            (a, b) => matches!((a.as_f64(), b.as_f64()), (Some(x), Some(y)) if x == y),
+        }
+    }
+}
+
+impl From<&Value> for Val {
+    fn from(v: &Value) -> Self {
+        match v.kind.as_ref() {
+            None | Some(Kind::NullValue(_)) => Val::Null,
+            Some(Kind::BoolValue(b)) => Val::Bool(*b),
+            Some(Kind::NumberValue(n)) => Val::Double(*n),
+            Some(Kind::StringValue(s)) => Val::string(s),
+            Some(Kind::ListValue(l)) => Val::list(l.values.iter().map(Val::from)),
+            Some(Kind::StructValue(s)) => Val::map(s.fields.iter().map(|(k, v)| (k, v.into()))),
+        }
+    }
+}
+
+impl From<&Val> for Value {
+    fn from(v: &Val) -> Self {
+        let kind = match v {
+            Val::Null => Kind::NullValue(0),
+            Val::Bool(b) => Kind::BoolValue(*b),
+            Val::Int(i) => Kind::NumberValue(*i as f64),
+            Val::Uint(u) => Kind::NumberValue(*u as f64),
+            Val::Double(d) => Kind::NumberValue(*d),
+            Val::String(s) => Kind::StringValue(s.to_string()),
+            Val::Bytes(b) => Kind::StringValue(String::from_utf8_lossy(b).into_owned()),
+            Val::List(l) => Kind::ListValue(ListValue {
+                values: l.iter().map(Value::from).collect(),
+            }),
+            Val::Map(m) => Kind::StructValue(Struct {
+                fields: m.iter().map(|(k, v)| (k.clone(), v.into())).collect(),
+            }),
+        };
+        Value { kind: Some(kind) }
+    }
+}
+
+/// Values and lazily evaluated variables visible to an expression.
+pub(crate) struct Activation<'a> {
+    pub(crate) request: Val,
+    pub(crate) runtime: Val,
+    pub(crate) constants: Val,
+    pub(crate) variables: HashMap<&'a str, &'a Expr>,
+    var_cache: RefCell<HashMap<String, EvalResult>>,
+    in_progress: RefCell<HashSet<String>>,
+}
+
+type Locals = Vec<(String, EvalResult)>;
+
+impl<'a> Activation<'a> {
+    pub(crate) fn new(
+        request: Val,
+        runtime: Val,
+        constants: Val,
+        variables: HashMap<&'a str, &'a Expr>,
+    ) -> Self {
+        Self {
+            request,
+            runtime,
+            constants,
+            variables,
+            var_cache: RefCell::new(HashMap::new()),
+            in_progress: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Evaluate a condition. Evaluation errors and non-boolean results count as `false`, which is
+    /// how the PDP treats them.
+    pub(crate) fn eval_condition(&self, expr: &Expr) -> bool {
+        matches!(self.eval(expr), Ok(Val::Bool(true)))
+    }
+
+    pub(crate) fn eval(&self, expr: &Expr) -> EvalResult {
+        self.eval_in(expr, &mut Vec::new())
+    }
+
+    fn variable(&self, name: &str) -> EvalResult {
+        if let Some(v) = self.var_cache.borrow().get(name) {
+            return v.clone();
+        }
+        let Some(expr) = self.variables.get(name) else {
+            return err(format!("undefined variable {name}"));
+        };
+        if !self.in_progress.borrow_mut().insert(name.to_string()) {
+            return err(format!("variable {name} refers to itself"));
+        }
+        let result = self.eval(expr);
+        self.in_progress.borrow_mut().remove(name);
+        self.var_cache
+            .borrow_mut()
+            .insert(name.to_string(), result.clone());
+        result
+    }
+
+    fn ident(&self, name: &str, locals: &Locals) -> EvalResult {
+        if let Some((_, v)) = locals.iter().rev().find(|(n, _)| n == name) {
+            return v.clone();
+        }
+        match name {
+            "request" => Ok(self.request.clone()),
+            "P" => select(&self.request, "principal"),
+            "R" => select(&self.request, "resource"),
+            "C" | "constants" => Ok(self.constants.clone()),
+            "runtime" => Ok(self.runtime.clone()),
+            "globals" | "G" => Ok(Val::map::<String>([])),
+            "V" | "variables" => {
+                let names: Vec<&str> = self.variables.keys().copied().collect();
+                let mut entries = Vec::with_capacity(names.len());
+                for n in names {
+                    entries.push((n, self.variable(n)?));
+                }
+                Ok(Val::map(entries))
+            }
+            _ => err(format!("undeclared reference to '{name}'")),
+        }
+    }
+
+    fn eval_in(&self, expr: &Expr, locals: &mut Locals) -> EvalResult {
+        let Some(kind) = expr.expr_kind.as_ref() else {
+            return err("empty expression");
+        };
+        match kind {
+            ExprKind::ConstExpr(c) => match c.constant_kind.as_ref() {
+                Some(ConstantKind::NullValue(_)) => Ok(Val::Null),
+                Some(ConstantKind::BoolValue(b)) => Ok(Val::Bool(*b)),
+                Some(ConstantKind::Int64Value(i)) => Ok(Val::Int(*i)),
+                Some(ConstantKind::Uint64Value(u)) => Ok(Val::Uint(*u)),
+                Some(ConstantKind::DoubleValue(d)) => Ok(Val::Double(*d)),
+                Some(ConstantKind::StringValue(s)) => Ok(Val::string(s)),
+                Some(ConstantKind::BytesValue(b)) => Ok(Val::Bytes(Arc::from(b.as_slice()))),
+                _ => err("unsupported constant"),
+            },
+            ExprKind::IdentExpr(ident) => self.ident(&ident.name, locals),
+            ExprKind::SelectExpr(sel) => {
+                let Some(operand) = sel.operand.as_deref() else {
+                    return err("select without operand");
+                };
+                // Variables are evaluated on demand so that unused ones with unsupported
+                // functions do not break unrelated conditions.
+                if let Some(ExprKind::IdentExpr(ident)) = operand.expr_kind.as_ref() {
+                    let shadowed = locals.iter().any(|(n, _)| *n == ident.name);
+                    if !shadowed && (ident.name == "V" || ident.name == "variables") {
+                        if sel.test_only {
+                            return Ok(Val::Bool(self.variables.contains_key(sel.field.as_str())));
+                        }
+                        return self.variable(&sel.field);
+                    }
+                }
+                let v = self.eval_in(operand, locals)?;
+                if sel.test_only {
+                    return match v {
+                        Val::Map(m) => Ok(Val::Bool(m.contains_key(&sel.field))),
+                        v => err(format!("has() not supported on {}", v.type_name())),
+                    };
+                }
+                select(&v, &sel.field)
+            }
+            ExprKind::ListExpr(list) => {
+                let mut values = Vec::with_capacity(list.elements.len());
+                for e in &list.elements {
+                    values.push(self.eval_in(e, locals)?);
+                }
+                Ok(Val::list(values))
+            }
+            ExprKind::StructExpr(st) => {
+                if !st.message_name.is_empty() {
+                    return err(format!("message {} is not supported", st.message_name));
+                }
+                let mut map = BTreeMap::new();
+                for entry in &st.entries {
+                    let key = match entry.key_kind.as_ref() {
+                        Some(KeyKind::MapKey(k)) => self.eval_in(k, locals)?,
+                        Some(KeyKind::FieldKey(f)) => Val::string(f),
+                        None => return err("map entry without key"),
+                    };
+                    let key = match key {
+                        Val::String(s) => s.to_string(),
+                        k @ (Val::Int(_) | Val::Uint(_) | Val::Bool(_)) => k.to_display_string(),
+                        k => return err(format!("unsupported map key type {}", k.type_name())),
+                    };
+                    let Some(value) = entry.value.as_ref() else {
+                        return err("map entry without value");
+                    };
+                    map.insert(key, self.eval_in(value, locals)?);
+                }
+                Ok(Val::Map(Arc::new(map)))
+            }
+            ExprKind::ComprehensionExpr(c) => {
+                let (Some(range), Some(init), Some(cond), Some(step), Some(result)) = (
+                    c.iter_range.as_deref(),
+                    c.accu_init.as_deref(),
+                    c.loop_condition.as_deref(),
+                    c.loop_step.as_deref(),
+                    c.result.as_deref(),
+                ) else {
+                    return err("incomplete comprehension");
+                };
+                let items: Vec<Val> = match self.eval_in(range, locals)? {
+                    Val::List(l) => l.iter().cloned().collect(),
+                    Val::Map(m) => m.keys().map(Val::string).collect(),
+                    v => return err(format!("cannot iterate over {}", v.type_name())),
+                };
+                let mut accu = self.eval_in(init, locals);
+                for item in items {
+                    locals.push((c.accu_var.clone(), accu.clone()));
+                    let proceed = self.eval_in(cond, locals);
+                    if !matches!(proceed, Ok(Val::Bool(true))) {
+                        locals.pop();
+                        break;
+                    }
+                    locals.push((c.iter_var.clone(), Ok(item)));
+                    let next = self.eval_in(step, locals);
+                    locals.truncate(locals.len() - 2);
+                    accu = next;
+                }
+                locals.push((c.accu_var.clone(), accu));
+                let r = self.eval_in(result, locals);
+                locals.pop();
+                r
+            }
+            ExprKind::CallExpr(call) => {
+                let function = call.function.as_str();
+                match function {
+                    "_&&_" | "_||_" => {
+                        let short = function == "_||_";
+                        let mut error = None;
+                        for arg in &call.args {
+                            match self.eval_in(arg, locals) {
+                                Ok(Val::Bool(b)) if b == short => return Ok(Val::Bool(short)),
+                                Ok(Val::Bool(_)) => {}
+                                Ok(v) => {
+                                    error = Some(EvalError(format!(
+                                        "no such overload for {function} on {}",
+                                        v.type_name()
+                                    )))
+                                }
+                                Err(e) => error = Some(e),
+                            }
+                        }
+                        return match error {
+                            Some(e) => Err(e),
+                            None => Ok(Val::Bool(!short)),
+                        };
+                    }
+                    "_?_:_" => {
+                        let [c, t, f] = call.args.as_slice() else {
+                            return err("conditional expects 3 arguments");
+                        };
+                        return match self.eval_in(c, locals)? {
+                            Val::Bool(true) => self.eval_in(t, locals),
+                            Val::Bool(false) => self.eval_in(f, locals),
+                            v => err(format!("conditional on {}", v.type_name())),
+                        };
+                    }
+                    "@not_strictly_false" => {
+                        let r = call.args.first().map(|a| self.eval_in(a, locals));
+                        return Ok(Val::Bool(!matches!(r, Some(Ok(Val::Bool(false))))));
+                    }
+                    _ => {}
+                }
+
+                let target = match call.target.as_deref() {
+                    Some(t) => Some(self.eval_in(t, locals)?),
+                    None => None,
+                };
+                let mut args = Vec::with_capacity(call.args.len());
+                for a in &call.args {
+                    args.push(self.eval_in(a, locals)?);
+                }
+                call_function(function, target, args)
+            }
+        }
+    }
+}
+
+fn select(v: &Val, field: &str) -> EvalResult {
+    match v {
+        Val::Map(m) => match m.get(field) {
+            Some(v) => Ok(v.clone()),
+            None => err(format!("no such key: {field}")),
+        },
+        v => err(format!(
+            "cannot select field {field} from {}",
+            v.type_name()
+        )),
+    }
+}
+
+fn compare(a: &Val, b: &Val) -> Result<Ordering, EvalError> {
+    let ord = match (a, b) {
+        (Val::String(x), Val::String(y)) => Some(x.cmp(y)),
+        (Val::Bool(x), Val::Bool(y)) => Some(x.cmp(y)),
+        (Val::Bytes(x), Val::Bytes(y)) => Some(x.cmp(y)),
+        (Val::Int(x), Val::Int(y)) => Some(x.cmp(y)),
+        (Val::Uint(x), Val::Uint(y)) => Some(x.cmp(y)),
+        (x, y) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => x.partial_cmp(&y),
+            _ => None,
+        },
+    };
+    ord.ok_or_else(|| {
+        EvalError(format!(
+            "cannot compare {} and {}",
+            a.type_name(),
+            b.type_name()
+        ))
+    })
+}
+
+fn arithmetic(op: &str, a: Val, b: Val) -> EvalResult {
+    let checked = |r: Option<i64>| r.map(Val::Int).ok_or(EvalError("integer overflow".into()));
+    match (op, a, b) {
+        ("_+_", Val::String(x), Val::String(y)) => Ok(Val::string(format!("{x}{y}"))),
+        ("_+_", Val::List(x), Val::List(y)) => Ok(Val::list(x.iter().chain(y.iter()).cloned())),
+        ("_+_", Val::Bytes(x), Val::Bytes(y)) => {
+            Ok(Val::Bytes(x.iter().chain(y.iter()).copied().collect()))
+        }
+        (_, Val::Int(x), Val::Int(y)) => match op {
+            "_+_" => checked(x.checked_add(y)),
+            "_-_" => checked(x.checked_sub(y)),
+            "_*_" => checked(x.checked_mul(y)),
+            "_/_" if y == 0 => err("division by zero"),
+            "_/_" => checked(x.checked_div(y)),
+            "_%_" if y == 0 => err("modulus by zero"),
+            _ => checked(x.checked_rem(y)),
+        },
+        (_, x, y) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => Ok(Val::Double(match op {
+                "_+_" => x + y,
+                "_-_" => x - y,
+                "_*_" => x * y,
+                "_/_" => x / y,
+                _ => x % y,
+            })),
+            _ => err(format!(
+                "no such overload for {op} on {} and {}",
+                x.type_name(),
+                y.type_name()
+            )),
+        },
+    }
+}
+
+fn contains(haystack: &Val, needle: &Val) -> Result<bool, EvalError> {
+    match haystack {
+        Val::List(l) => Ok(l.contains(needle)),
+        Val::Map(m) => Ok(m.contains_key(needle.as_str()?)),
+        v => err(format!("'in' not supported on {}", v.type_name())),
+    }
+}
+
+fn format_string(template: &str, args: &[Val]) -> EvalResult {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => out.push('%'),
+            Some('s' | 'd' | 'v' | 'f') => match args.next() {
+                Some(v) => out.push_str(&v.to_display_string()),
+                None => return err("too few arguments for format"),
+            },
+            Some(c) => return err(format!("unsupported format verb %{c}")),
+            None => return err("format string ends with %"),
+        }
+    }
+    Ok(Val::string(out))
+}
+
+fn call_function(function: &str, target: Option<Val>, args: Vec<Val>) -> EvalResult {
+    let overload = |n: usize| -> EvalResult {
+        err(format!(
+            "no such overload for {function} with {n} argument(s)"
+        ))
+    };
+
+    match (function, target.as_ref(), args.as_slice()) {
+        ("!_", None, [Val::Bool(b)]) => Ok(Val::Bool(!b)),
+        ("-_", None, [Val::Int(i)]) => i
+            .checked_neg()
+            .map(Val::Int)
+            .ok_or(EvalError("integer overflow".into())),
+        ("-_", None, [Val::Double(d)]) => Ok(Val::Double(-d)),
+        ("_==_", None, [a, b]) => Ok(Val::Bool(a == b)),
+        ("_!=_", None, [a, b]) => Ok(Val::Bool(a != b)),
+        ("_<_", None, [a, b]) => compare(a, b).map(|o| Val::Bool(o.is_lt())),
+        ("_<=_", None, [a, b]) => compare(a, b).map(|o| Val::Bool(o.is_le())),
+        ("_>_", None, [a, b]) => compare(a, b).map(|o| Val::Bool(o.is_gt())),
+        ("_>=_", None, [a, b]) => compare(a, b).map(|o| Val::Bool(o.is_ge())),
+        ("_+_" | "_-_" | "_*_" | "_/_" | "_%_", None, [a, b]) => {
+            arithmetic(function, a.clone(), b.clone())
+        }
+        ("@in", None, [needle, haystack]) => contains(haystack, needle).map(Val::Bool),
+        ("_[_]", None, [Val::List(l), idx]) => match idx.as_index().and_then(|i| l.get(i)) {
+            Some(v) => Ok(v.clone()),
+            None => err("index out of range"),
+        },
+        ("_[_]", None, [m @ Val::Map(_), key]) => select(m, key.as_str()?),
+        ("size", Some(v), []) | ("size", None, [v]) => match v {
+            Val::String(s) => Ok(Val::Int(s.chars().count() as i64)),
+            Val::Bytes(b) => Ok(Val::Int(b.len() as i64)),
+            Val::List(l) => Ok(Val::Int(l.len() as i64)),
+            Val::Map(m) => Ok(Val::Int(m.len() as i64)),
+            v => err(format!("size() not supported on {}", v.type_name())),
+        },
+        ("contains", Some(Val::String(s)), [Val::String(sub)]) => {
+            Ok(Val::Bool(s.contains(sub.as_ref())))
+        }
+        ("startsWith", Some(Val::String(s)), [Val::String(p)]) => {
+            Ok(Val::Bool(s.starts_with(p.as_ref())))
+        }
+        ("endsWith", Some(Val::String(s)), [Val::String(p)]) => {
+            Ok(Val::Bool(s.ends_with(p.as_ref())))
+        }
+        ("matches", Some(Val::String(s)), [Val::String(p)])
+        | ("matches", None, [Val::String(s), Val::String(p)]) => regex::Regex::new(p)
+            .map(|re| Val::Bool(re.is_match(s)))
+            .or_else(|e| err(format!("invalid regex: {e}"))),
+        ("lowerAscii", Some(Val::String(s)), []) => Ok(Val::string(s.to_ascii_lowercase())),
+        ("upperAscii", Some(Val::String(s)), []) => Ok(Val::string(s.to_ascii_uppercase())),
+        ("trim", Some(Val::String(s)), []) => Ok(Val::string(s.trim())),
+        ("format", Some(Val::String(s)), [Val::List(args)]) => format_string(s, args),
+        ("hasIntersection", Some(Val::List(a)), [Val::List(b)])
+        | ("hasIntersection", None, [Val::List(a), Val::List(b)]) => {
+            Ok(Val::Bool(a.iter().any(|x| b.contains(x))))
+        }
+        ("intersect", Some(Val::List(a)), [Val::List(b)])
+        | ("intersect", None, [Val::List(a), Val::List(b)]) => {
+            Ok(Val::list(a.iter().filter(|x| b.contains(x)).cloned()))
+        }
+        ("except", Some(Val::List(a)), [Val::List(b)]) => {
+            Ok(Val::list(a.iter().filter(|x| !b.contains(x)).cloned()))
+        }
+        ("isSubset", Some(Val::List(a)), [Val::List(b)]) => {
+            Ok(Val::Bool(a.iter().all(|x| b.contains(x))))
+        }
+        ("int", None, [v]) => match v {
+            Val::Int(i) => Ok(Val::Int(*i)),
+            Val::Uint(u) => i64::try_from(*u)
+                .map(Val::Int)
+                .or_else(|_| err("integer overflow")),
+            Val::Double(d) => Ok(Val::Int(*d as i64)),
+            Val::String(s) => s
+                .parse()
+                .map(Val::Int)
+                .or_else(|e| err(format!("cannot convert to int: {e}"))),
+            v => err(format!("cannot convert {} to int", v.type_name())),
+        },
+        ("uint", None, [v]) => match v {
+            Val::Uint(u) => Ok(Val::Uint(*u)),
+            Val::Int(i) => u64::try_from(*i)
+                .map(Val::Uint)
+                .or_else(|_| err("integer overflow")),
+            Val::Double(d) if *d >= 0.0 => Ok(Val::Uint(*d as u64)),
+            Val::String(s) => s
+                .parse()
+                .map(Val::Uint)
+                .or_else(|e| err(format!("cannot convert to uint: {e}"))),
+            v => err(format!("cannot convert {} to uint", v.type_name())),
+        },
+        ("double", None, [v]) => match v {
+            Val::String(s) => s
+                .parse()
+                .map(Val::Double)
+                .or_else(|e| err(format!("cannot convert to double: {e}"))),
+            v => v
+                .as_f64()
+                .map(Val::Double)
+                .ok_or_else(|| EvalError(format!("cannot convert {} to double", v.type_name()))),
+        },
+        ("string", None, [v]) => Ok(Val::string(v.to_display_string())),
+        ("bool", None, [Val::Bool(b)]) => Ok(Val::Bool(*b)),
+        ("bool", None, [Val::String(s)]) => match s.as_ref() {
+            "true" => Ok(Val::Bool(true)),
+            "false" => Ok(Val::Bool(false)),
+            _ => err(format!("cannot convert {s:?} to bool")),
+        },
+        (_, _, args) => {
+            if matches!(
+                function,
+                "timestamp" | "duration" | "now" | "inIPAddrRange" | "getField"
+            ) {
+                return err(format!(
+                    "function {function} is not supported by the local engine"
+                ));
+            }
+            overload(args.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdk::cel::parse;
+
+    fn eval(src: &str) -> EvalResult {
+        let request = Val::map([
+            (
+                "principal",
+                Val::map([
+                    ("id", Val::string("alice")),
+                    ("roles", Val::list([Val::string("employee")])),
+                ]),
+            ),
+            (
+                "resource",
+                Val::map([(
+                    "attr",
+                    Val::map([
+                        ("owner", Val::string("alice")),
+                        ("amount", Val::Double(100.0)),
+                        (
+                            "tags",
+                            Val::list([Val::string("public"), Val::string("draft")]),
+                        ),
+                    ]),
+                )]),
+            ),
+        ]);
+        let expr = parse(src).unwrap().expr.unwrap();
+        let var = parse("R.attr.amount > 50").unwrap().expr.unwrap();
+        let activation = Activation::new(
+            request,
+            Val::map::<String>([]),
+            Val::map([("limit", Val::Int(10))]),
+            HashMap::from([("big", &var)]),
+        );
+        activation.eval(&expr)
+    }
+
+    #[test]
+    fn test_eval() {
+        let cases = [
+            ("R.attr.owner == P.id", true),
+            ("R.attr.amount == 100 && R.attr.amount < 100.5", true),
+            (
+                "'public' in R.attr.tags && !('secret' in R.attr.tags)",
+                true,
+            ),
+            ("R.attr.tags.exists(t, t.startsWith('dr'))", true),
+            ("R.attr.tags.all(t, size(t) > 5)", false),
+            ("R.attr.tags.filter(t, t != 'draft') == ['public']", true),
+            ("V.big && C.limit == 10", true),
+            ("R.attr.missing == 1 || P.id == 'alice'", true),
+            ("has(R.attr.owner) && !has(R.attr.missing)", true),
+            ("'id:%s'.format([P.id]) == 'id:alice'", true),
+            ("P.id.matches('^a.*e$')", true),
+        ];
+        for (src, want) in cases {
+            assert!(
+                matches!(eval(src), Ok(Val::Bool(b)) if b == want),
+                "{src}: {:?}",
+                eval(src)
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        assert!(eval("R.attr.missing == 1").is_err());
+        assert!(eval("R.attr.missing == 1 && false").is_ok());
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("timestamp('2021-01-01T00:00:00Z')").is_err());
+    }
+}
@@ -0,0 +1,726 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-process policy evaluation for tests that can't reach a PDP.
+//!
+//! [`LocalEngine`] loads resource policies, principal policies, derived roles and exported
+//! variables/constants, and evaluates `CheckResources` requests using a subset of CEL. It is meant
+//! for unit tests and is not a replacement for a PDP:
+//!
+//! - Role policies and JWT verification are not supported. The `request.aux_data.jwt` map is
+//!   always empty.
+//! - Schemas are not enforced.
+//! - Scope search is lenient: missing scopes in the chain are skipped.
+//! - Conditions that call unsupported functions (`timestamp`, `inIPAddrRange` and friends) evaluate
+//!   to `false`.
+//! - Query planning is not supported. [`Authorizer::plan_resources`] fails with [`Unsupported`],
+//!   so code that plans through the trait must not be run against the local engine.
+//!
+//! [`Authorizer::plan_resources`]: crate::sdk::authorizer::Authorizer::plan_resources
+
+mod eval;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use thiserror::Error;
+
+use crate::genpb::cerbos::{
+    effect::v1::Effect,
+    engine::v1::OutputEntry,
+    policy::v1::{
+        condition, policy::PolicyType, r#match::Op, Condition, Constants, DerivedRoles, Match,
+        Output, Policy, PrincipalPolicy, ResourcePolicy, ScopePermissions, Variables,
+    },
+    request::v1::check_resources_request::ResourceEntry,
+    response::v1::check_resources_response::{
+        result_entry::{meta::EffectMeta, Meta, Resource as ResultResource},
+        ResultEntry,
+    },
+    response::v1::CheckResourcesResponse as CheckResourcesResponsePB,
+};
+use crate::genpb::google::api::expr::v1alpha1::Expr;
+use crate::genpb::google::protobuf::Value;
+use crate::sdk::{cel, gen_uuid, model, Result};
+
+use self::eval::{Activation, Val};
+
+const DEFAULT_VERSION: &str = "default";
+const NO_MATCH: &str = "NO_MATCH";
+
+type PolicyKey = (String, String, String);
+
+/// An operation that [`LocalEngine`] does not implement.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("LocalEngine does not support {operation}")]
+pub struct Unsupported {
+    pub operation: &'static str,
+}
+
+/// Evaluates check requests against policies held in memory.
+pub struct LocalEngine {
+    resource_policies: HashMap<PolicyKey, ResourcePolicy>,
+    principal_policies: HashMap<PolicyKey, PrincipalPolicy>,
+    derived_roles: HashMap<String, DerivedRoles>,
+    export_variables: HashMap<String, HashMap<String, String>>,
+    export_constants: HashMap<String, HashMap<String, Value>>,
+    exprs: HashMap<String, Expr>,
+    request_id_gen: fn() -> String,
+    include_meta: bool,
+}
+
+impl Default for LocalEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalEngine {
+    pub fn new() -> Self {
+        Self {
+            resource_policies: HashMap::new(),
+            principal_policies: HashMap::new(),
+            derived_roles: HashMap::new(),
+            export_variables: HashMap::new(),
+            export_constants: HashMap::new(),
+            exprs: HashMap::new(),
+            request_id_gen: gen_uuid,
+            include_meta: false,
+        }
+    }
+
+    /// Request ID generator to use. Defaults to UUID.
+    pub fn with_request_id_gen(mut self, id_gen: fn() -> String) -> Self {
+        self.request_id_gen = id_gen;
+        self
+    }
+
+    /// Include evaluation metadata such as matched policies and scopes in responses.
+    pub fn with_include_meta(mut self, include_meta: bool) -> Self {
+        self.include_meta = include_meta;
+        self
+    }
+
+    /// Add a policy to the engine. All expressions in the policy are parsed upfront so that syntax
+    /// errors are reported here rather than silently evaluating to `false`.
+    pub fn add_policy(&mut self, policy: Policy) -> Result<()> {
+        if policy.disabled {
+            return Ok(());
+        }
+
+        let Some(policy_type) = policy.policy_type else {
+            bail!("policy has no type");
+        };
+        match policy_type {
+            PolicyType::ResourcePolicy(mut p) => {
+                let key = (
+                    p.resource.clone(),
+                    version_or_default(&p.version).to_string(),
+                    p.scope.clone(),
+                );
+                merge_variables(&mut p.variables, policy.variables);
+                let name = policy_name("resource", &key);
+                for rule in &p.rules {
+                    self.parse_condition(&name, rule.condition.as_ref())?;
+                    self.parse_output(&name, rule.output.as_ref())?;
+                }
+                self.parse_variables(&name, p.variables.as_ref())?;
+                self.resource_policies.insert(key, p);
+            }
+            PolicyType::PrincipalPolicy(mut p) => {
+                let key = (
+                    p.principal.clone(),
+                    version_or_default(&p.version).to_string(),
+                    p.scope.clone(),
+                );
+                merge_variables(&mut p.variables, policy.variables);
+                let name = policy_name("principal", &key);
+                for action in p.rules.iter().flat_map(|r| &r.actions) {
+                    self.parse_condition(&name, action.condition.as_ref())?;
+                    self.parse_output(&name, action.output.as_ref())?;
+                }
+                self.parse_variables(&name, p.variables.as_ref())?;
+                self.principal_policies.insert(key, p);
+            }
+            PolicyType::DerivedRoles(mut p) => {
+                merge_variables(&mut p.variables, policy.variables);
+                let name = format!("derived_roles.{}", p.name);
+                for def in &p.definitions {
+                    self.parse_condition(&name, def.condition.as_ref())?;
+                }
+                self.parse_variables(&name, p.variables.as_ref())?;
+                self.derived_roles.insert(p.name.clone(), p);
+            }
+            PolicyType::ExportVariables(p) => {
+                let name = format!("export_variables.{}", p.name);
+                for src in p.definitions.values() {
+                    self.parse_expr(&name, src)?;
+                }
+                self.export_variables.insert(p.name, p.definitions);
+            }
+            PolicyType::ExportConstants(p) => {
+                self.export_constants.insert(p.name, p.definitions);
+            }
+            PolicyType::RolePolicy(_) => bail!("role policies are not supported by LocalEngine"),
+        }
+        Ok(())
+    }
+
+    /// Read policies in YAML or JSON format and add them to the engine. YAML sources may contain
+    /// multiple documents.
+    pub fn add_policy_from_reader(&mut self, src: impl Read) -> Result<()> {
+        for policy in crate::sdk::deser::read_policies(src)? {
+            self.add_policy(policy)?;
+        }
+        Ok(())
+    }
+
+    /// Read a policy file and add its policies to the engine.
+    pub fn add_policy_from_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+        self.add_policy_from_reader(file)
+            .with_context(|| format!("failed to load policy from {path:?}"))
+    }
+
+    /// Check access to multiple resources
+    pub async fn check_resources(
+        &mut self,
+        principal: model::Principal,
+        resources: model::ResourceList,
+        _aux_data: Option<model::AuxData>,
+    ) -> Result<model::CheckResourcesResponse> {
+        let principal = principal.principal;
+        // JWTs are not verified locally, so the claims are never available to conditions.
+        let aux_data = Val::map([("jwt", Val::map::<String>([]))]);
+
+        let mut results = Vec::with_capacity(resources.resources.len());
+        for entry in &resources.resources {
+            results.push(self.check_resource(&principal, entry, &aux_data)?);
+        }
+
+        Ok(model::CheckResourcesResponse {
+            response: CheckResourcesResponsePB {
+                request_id: (self.request_id_gen)(),
+                results,
+                cerbos_call_id: String::new(),
+            },
+        })
+    }
+
+    /// Check access to a single resource
+    pub async fn is_allowed<S>(
+        &mut self,
+        action: S,
+        principal: model::Principal,
+        resource: model::Resource,
+        aux_data: Option<model::AuxData>,
+    ) -> Result<bool>
+    where
+        S: Into<String> + Clone,
+    {
+        let resp = self
+            .check_resources(
+                principal,
+                model::ResourceList::new().add(resource, [action.clone()]),
+                aux_data,
+            )
+            .await?;
+        Ok(resp
+            .iter()
+            .next()
+            .map(|r| r.is_allowed(action.into()))
+            .unwrap_or(false))
+    }
+
+    fn parse_expr(&mut self, policy: &str, src: &str) -> Result<()> {
+        if self.exprs.contains_key(src) {
+            return Ok(());
+        }
+        let parsed =
+            cel::parse(src).with_context(|| format!("invalid expression {src:?} in {policy}"))?;
+        if let Some(expr) = parsed.expr {
+            self.exprs.insert(src.to_string(), expr);
+        }
+        Ok(())
+    }
+
+    fn parse_condition(&mut self, policy: &str, condition: Option<&Condition>) -> Result<()> {
+        fn collect<'a>(m: &'a Match, out: &mut Vec<&'a str>) {
+            match m.op.as_ref() {
+                Some(Op::Expr(e)) => out.push(e),
+                Some(Op::All(l) | Op::Any(l) | Op::None(l)) => {
+                    l.of.iter().for_each(|m| collect(m, out))
+                }
+                None => {}
+            }
+        }
+
+        let mut srcs = Vec::new();
+        if let Some(condition::Condition::Match(m)) = condition.and_then(|c| c.condition.as_ref()) {
+            collect(m, &mut srcs);
+        }
+        for src in srcs {
+            self.parse_expr(policy, src)?;
+        }
+        Ok(())
+    }
+
+    #[allow(deprecated)]
+    fn parse_output(&mut self, policy: &str, output: Option<&Output>) -> Result<()> {
+        let Some(output) = output else {
+            return Ok(());
+        };
+        let mut srcs = vec![output.expr.as_str()];
+        if let Some(when) = &output.when {
+            srcs.push(&when.rule_activated);
+            srcs.push(&when.condition_not_met);
+        }
+        for src in srcs.into_iter().filter(|s| !s.is_empty()) {
+            self.parse_expr(policy, src)?;
+        }
+        Ok(())
+    }
+
+    fn parse_variables(&mut self, policy: &str, variables: Option<&Variables>) -> Result<()> {
+        for src in variables.iter().flat_map(|v| v.local.values()) {
+            self.parse_expr(policy, src)?;
+        }
+        Ok(())
+    }
+
+    fn activation<'a>(
+        &'a self,
+        request: &Val,
+        runtime: Val,
+        variables: Option<&'a Variables>,
+        constants: Option<&Constants>,
+    ) -> Result<Activation<'a>> {
+        let mut vars: HashMap<&str, &Expr> = HashMap::new();
+        if let Some(variables) = variables {
+            for name in &variables.import {
+                let Some(defs) = self.export_variables.get(name) else {
+                    bail!("variables {name} are imported but not defined");
+                };
+                vars.extend(defs.iter().filter_map(|(k, src)| self.expr(k, src)));
+            }
+            vars.extend(
+                variables
+                    .local
+                    .iter()
+                    .filter_map(|(k, src)| self.expr(k, src)),
+            );
+        }
+
+        let mut consts: Vec<(String, Val)> = Vec::new();
+        if let Some(constants) = constants {
+            for name in &constants.import {
+                let Some(defs) = self.export_constants.get(name) else {
+                    bail!("constants {name} are imported but not defined");
+                };
+                consts.extend(defs.iter().map(|(k, v)| (k.clone(), v.into())));
+            }
+            consts.extend(constants.local.iter().map(|(k, v)| (k.clone(), v.into())));
+        }
+
+        Ok(Activation::new(
+            request.clone(),
+            runtime,
+            Val::map(consts),
+            vars,
+        ))
+    }
+
+    fn expr<'a>(&'a self, name: &'a str, src: &str) -> Option<(&'a str, &'a Expr)> {
+        self.exprs.get(src).map(|e| (name, e))
+    }
+
+    fn condition_met(&self, activation: &Activation, condition: Option<&Condition>) -> bool {
+        match condition.and_then(|c| c.condition.as_ref()) {
+            None => true,
+            Some(condition::Condition::Match(m)) => self.match_met(activation, m),
+            Some(condition::Condition::Script(_)) => false,
+        }
+    }
+
+    fn match_met(&self, activation: &Activation, m: &Match) -> bool {
+        match m.op.as_ref() {
+            Some(Op::Expr(src)) => self
+                .exprs
+                .get(src.as_str())
+                .is_some_and(|e| activation.eval_condition(e)),
+            Some(Op::All(l)) => l.of.iter().all(|m| self.match_met(activation, m)),
+            Some(Op::Any(l)) => l.of.iter().any(|m| self.match_met(activation, m)),
+            Some(Op::None(l)) => !l.of.iter().any(|m| self.match_met(activation, m)),
+            None => true,
+        }
+    }
+
+    #[allow(deprecated)]
+    fn output(
+        &self,
+        activation: &Activation,
+        src: String,
+        output: Option<&Output>,
+        activated: bool,
+    ) -> Option<OutputEntry> {
+        let output = output?;
+        let when = output.when.as_ref();
+        let expr = match (activated, when) {
+            (true, Some(w)) if !w.rule_activated.is_empty() => &w.rule_activated,
+            (true, _) => &output.expr,
+            (false, Some(w)) => &w.condition_not_met,
+            (false, None) => return None,
+        };
+        let val = activation.eval(self.exprs.get(expr.as_str())?).ok()?;
+        Some(OutputEntry {
+            src,
+            val: Some((&val).into()),
+        })
+    }
+
+    fn check_resource(
+        &self,
+        principal: &crate::genpb::cerbos::engine::v1::Principal,
+        entry: &ResourceEntry,
+        aux_data: &Val,
+    ) -> Result<ResultEntry> {
+        let resource = entry.resource.clone().unwrap_or_default();
+        let request = Val::map([
+            (
+                "principal",
+                Val::map([
+                    ("id", Val::string(&principal.id)),
+                    ("roles", Val::list(principal.roles.iter().map(Val::string))),
+                    (
+                        "attr",
+                        Val::map(principal.attr.iter().map(|(k, v)| (k.clone(), v.into()))),
+                    ),
+                    ("policy_version", Val::string(&principal.policy_version)),
+                    ("scope", Val::string(&principal.scope)),
+                ]),
+            ),
+            (
+                "resource",
+                Val::map([
+                    ("kind", Val::string(&resource.kind)),
+                    ("id", Val::string(&resource.id)),
+                    (
+                        "attr",
+                        Val::map(resource.attr.iter().map(|(k, v)| (k.clone(), v.into()))),
+                    ),
+                    ("policy_version", Val::string(&resource.policy_version)),
+                    ("scope", Val::string(&resource.scope)),
+                ]),
+            ),
+            ("aux_data", aux_data.clone()),
+        ]);
+
+        let mut pending: Vec<&str> = entry.actions.iter().map(String::as_str).collect();
+        let mut decided: HashMap<String, (Effect, EffectMeta)> = HashMap::new();
+        let mut outputs = Vec::new();
+        let mut effective_derived_roles = HashSet::new();
+
+        let principal_version = version_or_default(&principal.policy_version);
+        for scope in scope_chain(&principal.scope) {
+            if pending.is_empty() {
+                break;
+            }
+            let key = (
+                principal.id.clone(),
+                principal_version.to_string(),
+                scope.to_string(),
+            );
+            let Some(policy) = self.principal_policies.get(&key) else {
+                continue;
+            };
+            let activation = self.activation(
+                &request,
+                runtime(&HashSet::new()),
+                policy.variables.as_ref(),
+                policy.constants.as_ref(),
+            )?;
+            let name = policy_name("principal", &key);
+            let mut effects = HashMap::new();
+            let mut n = 0;
+            for rule in &policy.rules {
+                for action_rule in &rule.actions {
+                    n += 1;
+                    if !glob_match(&rule.resource, &resource.kind) {
+                        continue;
+                    }
+                    let matched: Vec<&str> = pending
+                        .iter()
+                        .copied()
+                        .filter(|a| glob_match(&action_rule.action, a))
+                        .collect();
+                    if matched.is_empty() {
+                        continue;
+                    }
+                    let met = self.condition_met(&activation, action_rule.condition.as_ref());
+                    let src = output_src(&name, &action_rule.name, n);
+                    outputs.extend(self.output(&activation, src, action_rule.output.as_ref(), met));
+                    if met {
+                        record_effects(&mut effects, &matched, action_rule.effect);
+                    }
+                }
+            }
+            let consent = policy.scope_permissions
+                == ScopePermissions::RequireParentalConsentForAllows as i32;
+            decide(&mut pending, &mut decided, effects, &name, scope, consent);
+        }
+
+        let resource_version = version_or_default(&resource.policy_version);
+        for scope in scope_chain(&resource.scope) {
+            if pending.is_empty() {
+                break;
+            }
+            let key = (
+                resource.kind.clone(),
+                resource_version.to_string(),
+                scope.to_string(),
+            );
+            let Some(policy) = self.resource_policies.get(&key) else {
+                continue;
+            };
+
+            let derived_roles = self.derived_roles(policy, &principal.roles, &request)?;
+            effective_derived_roles.extend(derived_roles.iter().cloned());
+            let activation = self.activation(
+                &request,
+                runtime(&derived_roles),
+                policy.variables.as_ref(),
+                policy.constants.as_ref(),
+            )?;
+            let name = policy_name("resource", &key);
+            let mut effects = HashMap::new();
+            for (i, rule) in policy.rules.iter().enumerate() {
+                let has_role = rule
+                    .roles
+                    .iter()
+                    .any(|r| r == "*" || principal.roles.contains(r))
+                    || rule.derived_roles.iter().any(|r| derived_roles.contains(r));
+                if !has_role {
+                    continue;
+                }
+                let matched: Vec<&str> = pending
+                    .iter()
+                    .copied()
+                    .filter(|a| rule.actions.iter().any(|p| glob_match(p, a)))
+                    .collect();
+                if matched.is_empty() {
+                    continue;
+                }
+                let met = self.condition_met(&activation, rule.condition.as_ref());
+                let src = output_src(&name, &rule.name, i + 1);
+                outputs.extend(self.output(&activation, src, rule.output.as_ref(), met));
+                if met {
+                    record_effects(&mut effects, &matched, rule.effect);
+                }
+            }
+            let consent = policy.scope_permissions
+                == ScopePermissions::RequireParentalConsentForAllows as i32;
+            decide(&mut pending, &mut decided, effects, &name, scope, consent);
+        }
+
+        for action in pending {
+            decided.insert(
+                action.to_string(),
+                (
+                    Effect::Deny,
+                    EffectMeta {
+                        matched_policy: NO_MATCH.to_string(),
+                        matched_scope: String::new(),
+                    },
+                ),
+            );
+        }
+
+        let meta = self.include_meta.then(|| {
+            let mut roles: Vec<String> = effective_derived_roles.into_iter().collect();
+            roles.sort();
+            Meta {
+                actions: decided
+                    .iter()
+                    .map(|(a, (_, m))| (a.clone(), m.clone()))
+                    .collect(),
+                effective_derived_roles: roles,
+            }
+        });
+
+        Ok(ResultEntry {
+            resource: Some(ResultResource {
+                id: resource.id,
+                kind: resource.kind,
+                policy_version: resource.policy_version,
+                scope: resource.scope,
+            }),
+            actions: decided
+                .into_iter()
+                .map(|(a, (e, _))| (a, e as i32))
+                .collect(),
+            validation_errors: Vec::new(),
+            meta,
+            outputs,
+        })
+    }
+
+    fn derived_roles(
+        &self,
+        policy: &ResourcePolicy,
+        roles: &[String],
+        request: &Val,
+    ) -> Result<HashSet<String>> {
+        let mut active = HashSet::new();
+        for name in &policy.import_derived_roles {
+            let Some(set) = self.derived_roles.get(name) else {
+                bail!(
+                    "derived roles {name} are imported by {} but not defined",
+                    policy.resource
+                );
+            };
+            let activation = self.activation(
+                request,
+                runtime(&HashSet::new()),
+                set.variables.as_ref(),
+                set.constants.as_ref(),
+            )?;
+            for def in &set.definitions {
+                let has_parent = def
+                    .parent_roles
+                    .iter()
+                    .any(|r| r == "*" || roles.contains(r));
+                if has_parent && self.condition_met(&activation, def.condition.as_ref()) {
+                    active.insert(def.name.clone());
+                }
+            }
+        }
+        Ok(active)
+    }
+}
+
+fn version_or_default(version: &str) -> &str {
+    if version.is_empty() {
+        DEFAULT_VERSION
+    } else {
+        version
+    }
+}
+
+fn policy_name(kind: &str, (name, version, scope): &PolicyKey) -> String {
+    if scope.is_empty() {
+        format!("{kind}.{name}.v{version}")
+    } else {
+        format!("{kind}.{name}.v{version}/{scope}")
+    }
+}
+
+fn output_src(policy: &str, rule: &str, n: usize) -> String {
+    if rule.is_empty() {
+        format!("{policy}#rule-{n:03}")
+    } else {
+        format!("{policy}#{rule}")
+    }
+}
+
+fn merge_variables(variables: &mut Option<Variables>, top_level: HashMap<String, String>) {
+    if top_level.is_empty() {
+        return;
+    }
+    let variables = variables.get_or_insert_with(Variables::default);
+    for (k, v) in top_level {
+        variables.local.entry(k).or_insert(v);
+    }
+}
+
+fn runtime(derived_roles: &HashSet<String>) -> Val {
+    let mut roles: Vec<&String> = derived_roles.iter().collect();
+    roles.sort();
+    let roles = Val::list(roles.into_iter().map(Val::string));
+    Val::map([
+        ("effectiveDerivedRoles", roles.clone()),
+        ("effective_derived_roles", roles),
+    ])
+}
+
+/// Scopes to search, from the most specific to the root.
+fn scope_chain(scope: &str) -> Vec<&str> {
+    let mut chain = vec![scope];
+    let mut rest = scope;
+    while let Some(i) = rest.rfind('.') {
+        rest = &rest[..i];
+        chain.push(rest);
+    }
+    if !scope.is_empty() {
+        chain.push("");
+    }
+    chain
+}
+
+/// Match Cerbos action and resource globs, where `*` on its own matches everything and otherwise
+/// does not cross `:` separators.
+fn glob_match(pattern: &str, value: &str) -> bool {
+    fn segment(p: &[u8], v: &[u8]) -> bool {
+        match (p.first(), v.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => segment(&p[1..], v) || (!v.is_empty() && segment(p, &v[1..])),
+            (Some(a), Some(b)) if a == b => segment(&p[1..], &v[1..]),
+            _ => false,
+        }
+    }
+
+    if pattern == "*" || pattern == value {
+        return true;
+    }
+    let (mut p, mut v) = (pattern.split(':'), value.split(':'));
+    loop {
+        match (p.next(), v.next()) {
+            (None, None) => return true,
+            (Some(p), Some(v)) if segment(p.as_bytes(), v.as_bytes()) => {}
+            _ => return false,
+        }
+    }
+}
+
+fn record_effects<'a>(effects: &mut HashMap<&'a str, Effect>, actions: &[&'a str], effect: i32) {
+    let effect = Effect::try_from(effect).unwrap_or(Effect::Deny);
+    for action in actions {
+        let e = effects.entry(action).or_insert(effect);
+        if effect == Effect::Deny {
+            *e = Effect::Deny;
+        }
+    }
+}
+
+fn decide(
+    pending: &mut Vec<&str>,
+    decided: &mut HashMap<String, (Effect, EffectMeta)>,
+    effects: HashMap<&str, Effect>,
+    policy: &str,
+    scope: &str,
+    require_parental_consent: bool,
+) {
+    pending.retain(|action| {
+        let final_effect = match effects.get(action) {
+            Some(Effect::Deny) => Effect::Deny,
+            // An allow in a scope that requires parental consent only counts if an ancestor
+            // allows the action as well.
+            Some(Effect::Allow) if !require_parental_consent || scope.is_empty() => Effect::Allow,
+            _ => return true,
+        };
+        decided.insert(
+            action.to_string(),
+            (
+                final_effect,
+                EffectMeta {
+                    matched_policy: policy.to_string(),
+                    matched_scope: scope.to_string(),
+                },
+            ),
+        );
+        false
+    });
+}
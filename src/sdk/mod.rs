@@ -16,6 +16,10 @@ use uuid::Uuid;
 
 use crate::genpb::cerbos::{
     request::v1::{CheckResourcesRequest, PlanResourcesRequest},
+    response::v1::{
+        CheckResourcesResponse as CheckResourcesResponsePB,
+        PlanResourcesResponse as PlanResourcesResponsePB,
+    },
     svc::v1::cerbos_service_client::CerbosServiceClient,
 };
 
@@ -24,6 +28,9 @@ use anyhow::Context;
 use hyper_util::rt::TokioIo;
 
 pub mod attr;
+pub mod authorizer;
+pub mod builder;
+pub mod cel;
 
 #[cfg(feature = "testcontainers")]
 pub mod container;
@@ -37,7 +44,21 @@ pub mod admin;
 #[cfg(feature = "serde")]
 pub mod deser;
 
+#[cfg(feature = "local")]
+pub mod local;
+
+#[cfg(feature = "serde")]
+pub mod test_suite;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "validation")]
+pub mod validation;
+
 pub mod model;
+pub mod playground;
+pub mod query_plan;
+pub mod replay;
 
 pub type Result<T> = anyhow::Result<T>;
 
@@ -61,6 +82,7 @@ where
     tls_config: Option<ClientTlsConfig>,
     timeout: Duration,
     request_id_gen: fn() -> String,
+    include_meta: bool,
     playground_instance: Option<String>,
     user_agent: String,
     #[cfg(feature = "admin")]
@@ -77,6 +99,7 @@ where
             tls_config: Some(ClientTlsConfig::new()),
             timeout: Duration::from_secs(2),
             request_id_gen: gen_uuid,
+            include_meta: false,
             playground_instance: None,
             user_agent: "cerbos-rs".to_string(),
             #[cfg(feature = "admin")]
@@ -122,6 +145,12 @@ where
         self
     }
 
+    /// Ask the PDP to include evaluation metadata such as matched policies and scopes in responses.
+    pub fn with_include_meta(mut self, include_meta: bool) -> Self {
+        self.include_meta = include_meta;
+        self
+    }
+
     /// Configure the client to use the Cerbos playground.
     pub fn with_playground_instance(mut self, id: impl Into<String>) -> Self {
         self.playground_instance = Some(id.into());
@@ -196,6 +225,7 @@ where
 pub struct CerbosAsyncClient {
     stub: CerbosServiceClient<InterceptedService<Channel, CerbosInterceptor>>,
     request_id_gen: fn() -> String,
+    include_meta: bool,
 }
 
 impl CerbosAsyncClient {
@@ -211,6 +241,7 @@ impl CerbosAsyncClient {
 
         let request_timeout = conf.timeout;
         let request_id_gen = conf.request_id_gen;
+        let include_meta = conf.include_meta;
         let channel = conf.build_channel()?;
         let stub = CerbosServiceClient::with_interceptor(
             channel,
@@ -223,6 +254,7 @@ impl CerbosAsyncClient {
         Ok(Self {
             stub,
             request_id_gen,
+            include_meta,
         })
     }
 
@@ -233,23 +265,30 @@ impl CerbosAsyncClient {
         resources: model::ResourceList,
         aux_data: Option<model::AuxData>,
     ) -> Result<model::CheckResourcesResponse> {
-        let req = CheckResourcesRequest {
-            request_id: (self.request_id_gen)(),
-            principal: Some(principal.to_pb()),
-            resources: resources.resources,
-            aux_data: aux_data.map(|a| a.to_pb()),
-            ..Default::default()
-        };
+        let req = check_resources_request(
+            (self.request_id_gen)(),
+            self.include_meta,
+            principal,
+            resources,
+            aux_data,
+        );
+
+        Ok(model::CheckResourcesResponse {
+            response: self.send_check_resources(req).await?,
+        })
+    }
 
+    pub(crate) async fn send_check_resources(
+        &mut self,
+        req: CheckResourcesRequest,
+    ) -> Result<CheckResourcesResponsePB> {
         let resp = self
             .stub
             .check_resources(req)
             .await
             .with_context(|| "CheckResources call failed")?;
 
-        Ok(model::CheckResourcesResponse {
-            response: resp.get_ref().to_owned(),
-        })
+        Ok(resp.into_inner())
     }
 
     /// Check access to a single resource
@@ -289,24 +328,17 @@ impl CerbosAsyncClient {
     where
         S: Into<String> + Clone,
     {
-        #[allow(deprecated)]
-        let req = PlanResourcesRequest {
-            request_id: (self.request_id_gen)(),
-            action: action.into(),
-            principal: Some(principal.to_pb()),
-            resource: Some(resource.to_pb()),
-            aux_data: aux_data.map(|a| a.to_pb()),
-            ..Default::default()
-        };
-
-        let resp = self
-            .stub
-            .plan_resources(req)
-            .await
-            .with_context(|| "PlanResources call failed")?;
+        let req = plan_resources_request(
+            (self.request_id_gen)(),
+            self.include_meta,
+            PlanActions::Single(action.into()),
+            principal,
+            resource,
+            aux_data,
+        );
 
         Ok(model::PlanResourcesResponse {
-            response: resp.get_ref().to_owned(),
+            response: self.send_plan_resources(req).await?,
         })
     }
 
@@ -323,25 +355,79 @@ impl CerbosAsyncClient {
         S: Into<String> + Clone,
         A: IntoIterator<Item = S>,
     {
-        let req = PlanResourcesRequest {
-            request_id: (self.request_id_gen)(),
-            actions: actions.into_iter().map(|a| a.into()).collect(),
-            principal: Some(principal.to_pb()),
-            resource: Some(resource.to_pb()),
-            aux_data: aux_data.map(|a| a.to_pb()),
-            ..Default::default()
-        };
+        let req = plan_resources_request(
+            (self.request_id_gen)(),
+            self.include_meta,
+            PlanActions::Multiple(actions.into_iter().map(|a| a.into()).collect()),
+            principal,
+            resource,
+            aux_data,
+        );
 
+        Ok(model::PlanResourcesResponse {
+            response: self.send_plan_resources(req).await?,
+        })
+    }
+
+    pub(crate) async fn send_plan_resources(
+        &mut self,
+        req: PlanResourcesRequest,
+    ) -> Result<PlanResourcesResponsePB> {
         let resp = self
             .stub
             .plan_resources(req)
             .await
             .with_context(|| "PlanResources call failed")?;
 
-        Ok(model::PlanResourcesResponse {
-            response: resp.get_ref().to_owned(),
-        })
+        Ok(resp.into_inner())
+    }
+}
+
+pub(crate) fn check_resources_request(
+    request_id: String,
+    include_meta: bool,
+    principal: model::Principal,
+    resources: model::ResourceList,
+    aux_data: Option<model::AuxData>,
+) -> CheckResourcesRequest {
+    CheckResourcesRequest {
+        request_id,
+        principal: Some(principal.to_pb()),
+        resources: resources.resources,
+        aux_data: aux_data.map(|a| a.to_pb()),
+        include_meta,
+    }
+}
+
+/// Actions of a plan request. A single action is sent in the deprecated `action` field so that
+/// PDPs older than 0.44.0 keep working.
+pub(crate) enum PlanActions {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+pub(crate) fn plan_resources_request(
+    request_id: String,
+    include_meta: bool,
+    actions: PlanActions,
+    principal: model::Principal,
+    resource: model::ResourceKind,
+    aux_data: Option<model::AuxData>,
+) -> PlanResourcesRequest {
+    let mut req = PlanResourcesRequest {
+        request_id,
+        principal: Some(principal.to_pb()),
+        resource: Some(resource.to_pb()),
+        aux_data: aux_data.map(|a| a.to_pb()),
+        include_meta,
+        ..Default::default()
+    };
+    match actions {
+        #[allow(deprecated)]
+        PlanActions::Single(action) => req.action = action,
+        PlanActions::Multiple(actions) => req.actions = actions,
     }
+    req
 }
 
 pub struct CerbosSyncClient {
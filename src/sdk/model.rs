@@ -1,6 +1,7 @@
 // Copyright 2021-2025 Zenauth Ltd.
 // SPDX-License-Identifier: Apache-2.0
 use super::attr::{AttrVal, Attribute};
+use super::query_plan::Expr;
 use crate::genpb::cerbos::effect::v1::Effect;
 use crate::genpb::cerbos::engine::v1::{
     plan_resources_filter::expression::Operand, plan_resources_filter::Kind,
@@ -10,12 +11,16 @@ use crate::genpb::cerbos::engine::v1::{
 use crate::genpb::cerbos::request::v1::aux_data::Jwt;
 use crate::genpb::cerbos::request::v1::check_resources_request::ResourceEntry;
 use crate::genpb::cerbos::request::v1::AuxData as AuxDataPB;
-use crate::genpb::cerbos::response::v1::check_resources_response::ResultEntry;
+use crate::genpb::cerbos::response::v1::check_resources_response::{
+    result_entry::Resource as ResultResource, ResultEntry,
+};
 use crate::genpb::cerbos::response::v1::{
     CheckResourcesResponse as CheckResourcesResponsePB,
     PlanResourcesResponse as PlanResourcesResponsePB,
 };
+use crate::genpb::cerbos::schema::v1::ValidationError;
 use crate::genpb::google::protobuf::Value;
+use anyhow::{anyhow, bail};
 use prost::Message;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -301,11 +306,72 @@ impl ResourceList {
     }
 }
 
-#[derive(Debug)]
+/// Predicate over the resource a check result refers to. Matchers can be combined with
+/// [`ResourceMatcher::and`], [`ResourceMatcher::or`] and [`ResourceMatcher::not`].
+#[derive(Debug, Clone)]
 pub enum ResourceMatcher {
+    Id(String),
     Kind(String),
     PolicyVersion(String),
     Scope(String),
+    All(Vec<ResourceMatcher>),
+    Any(Vec<ResourceMatcher>),
+    Not(Box<ResourceMatcher>),
+}
+
+impl ResourceMatcher {
+    pub fn id(id: impl Into<String>) -> Self {
+        ResourceMatcher::Id(id.into())
+    }
+
+    pub fn kind(kind: impl Into<String>) -> Self {
+        ResourceMatcher::Kind(kind.into())
+    }
+
+    pub fn policy_version(version: impl Into<String>) -> Self {
+        ResourceMatcher::PolicyVersion(version.into())
+    }
+
+    pub fn scope(scope: impl Into<String>) -> Self {
+        ResourceMatcher::Scope(scope.into())
+    }
+
+    pub fn and(self, other: ResourceMatcher) -> Self {
+        match self {
+            ResourceMatcher::All(mut all) => {
+                all.push(other);
+                ResourceMatcher::All(all)
+            }
+            m => ResourceMatcher::All(vec![m, other]),
+        }
+    }
+
+    pub fn or(self, other: ResourceMatcher) -> Self {
+        match self {
+            ResourceMatcher::Any(mut any) => {
+                any.push(other);
+                ResourceMatcher::Any(any)
+            }
+            m => ResourceMatcher::Any(vec![m, other]),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        ResourceMatcher::Not(Box::new(self))
+    }
+
+    pub fn matches(&self, resource: &ResultResource) -> bool {
+        match self {
+            ResourceMatcher::Id(id) => &resource.id == id,
+            ResourceMatcher::Kind(kind) => &resource.kind == kind,
+            ResourceMatcher::PolicyVersion(version) => &resource.policy_version == version,
+            ResourceMatcher::Scope(scope) => &resource.scope == scope,
+            ResourceMatcher::All(all) => all.iter().all(|m| m.matches(resource)),
+            ResourceMatcher::Any(any) => any.iter().any(|m| m.matches(resource)),
+            ResourceMatcher::Not(m) => !m.matches(resource),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -322,11 +388,40 @@ impl<'a> ResourceResult<'a> {
         }
     }
 
+    /// Resource the result refers to.
+    pub fn resource(&self) -> Option<&'a ResultResource> {
+        self.result.resource.as_ref()
+    }
+
     pub fn is_allowed(&self, action: impl AsRef<str>) -> bool {
+        self.effect(action) == Some(Effect::Allow)
+    }
+
+    /// Effect for the given action, or `None` if the action was not part of the request or the
+    /// effect is not known to this version of the SDK.
+    pub fn effect(&self, action: impl AsRef<str>) -> Option<Effect> {
         self.result
             .actions
             .get(action.as_ref())
-            .is_some_and(|effect| Effect::Allow == Effect::try_from(*effect).unwrap())
+            .and_then(|effect| Effect::try_from(*effect).ok())
+    }
+
+    /// Effects for all actions in the result.
+    pub fn effects(&self) -> HashMap<String, Effect> {
+        self.result
+            .actions
+            .iter()
+            .map(|(action, effect)| {
+                (
+                    action.clone(),
+                    Effect::try_from(*effect).unwrap_or(Effect::Unspecified),
+                )
+            })
+            .collect()
+    }
+
+    pub fn validation_errors(&self) -> &'a [ValidationError] {
+        &self.result.validation_errors
     }
 
     pub fn output(&self, key: &str) -> Option<&'a Value> {
@@ -364,35 +459,42 @@ impl CheckResourcesResponse {
         entry.map(ResourceResult::new)
     }
 
+    /// Find the result for the resource with the given id that satisfies all predicates.
+    #[deprecated(note = "use `CheckResourcesResponse::index` and `ResourceIndex::find` instead")]
     pub fn find_with_predicates(
         &self,
         id: impl AsRef<str>,
         predicates: Vec<ResourceMatcher>,
     ) -> Option<ResourceResult<'_>> {
-        let id_str = id.as_ref();
-        let entry = self.response.results.iter().find(|r| {
-            r.resource.as_ref().is_some_and(|rr| {
-                if rr.id != id_str {
-                    return false;
+        self.index()
+            .find(id, &ResourceMatcher::All(predicates))
+            .map(|r| ResourceResult::new(r.result))
+    }
+
+    /// Build an index over the results for repeated lookups.
+    pub fn index(&self) -> ResourceIndex<'_> {
+        ResourceIndex::new(&self.response.results)
+    }
+
+    /// Effect of each action keyed by resource id. Fails if several results share an id (for
+    /// example resources of different kinds); use [`Self::index`] to tell them apart.
+    pub fn decisions(&self) -> anyhow::Result<HashMap<String, HashMap<String, Effect>>> {
+        let mut decisions = HashMap::with_capacity(self.response.results.len());
+        for result in self.iter() {
+            if let Some(resource) = result.resource() {
+                if decisions
+                    .insert(resource.id.clone(), result.effects())
+                    .is_some()
+                {
+                    bail!("more than one result for resource id {}", resource.id);
                 }
+            }
+        }
+        Ok(decisions)
+    }
 
-                predicates.iter().fold(false, |acc, p| {
-                    if !acc {
-                        return false;
-                    }
-
-                    match p {
-                        ResourceMatcher::Kind(kind) => acc && (&rr.kind == kind),
-                        ResourceMatcher::PolicyVersion(version) => {
-                            acc && (&rr.policy_version == version)
-                        }
-                        ResourceMatcher::Scope(scope) => acc && (&rr.scope == scope),
-                    }
-                })
-            })
-        });
-
-        entry.map(ResourceResult::new)
+    pub fn request_id(&self) -> &str {
+        &self.response.request_id
     }
 
     pub fn iter(&self) -> CheckResourcesResponseIter<'_> {
@@ -402,6 +504,108 @@ impl CheckResourcesResponse {
     }
 }
 
+/// Key identifying a result in a [`ResourceIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceKey<'a> {
+    pub kind: &'a str,
+    pub id: &'a str,
+    pub scope: &'a str,
+    pub policy_version: &'a str,
+}
+
+impl<'a> From<&'a ResultResource> for ResourceKey<'a> {
+    fn from(r: &'a ResultResource) -> Self {
+        Self {
+            kind: &r.kind,
+            id: &r.id,
+            scope: &r.scope,
+            policy_version: &r.policy_version,
+        }
+    }
+}
+
+/// Indexed view over the results of a [`CheckResourcesResponse`].
+#[derive(Debug)]
+pub struct ResourceIndex<'a> {
+    results: &'a [ResultEntry],
+    by_key: HashMap<ResourceKey<'a>, &'a ResultEntry>,
+    by_kind_id: HashMap<(&'a str, &'a str), Vec<&'a ResultEntry>>,
+    by_id: HashMap<&'a str, Vec<&'a ResultEntry>>,
+}
+
+impl<'a> ResourceIndex<'a> {
+    fn new(results: &'a [ResultEntry]) -> Self {
+        let mut index = Self {
+            results,
+            by_key: HashMap::with_capacity(results.len()),
+            by_kind_id: HashMap::with_capacity(results.len()),
+            by_id: HashMap::with_capacity(results.len()),
+        };
+
+        for entry in results {
+            if let Some(r) = entry.resource.as_ref() {
+                index.by_key.entry(r.into()).or_insert(entry);
+                index
+                    .by_kind_id
+                    .entry((r.kind.as_str(), r.id.as_str()))
+                    .or_default()
+                    .push(entry);
+                index.by_id.entry(r.id.as_str()).or_default().push(entry);
+            }
+        }
+        index
+    }
+
+    /// First result for the exact kind, id, scope and policy version.
+    pub fn get(&self, key: &ResourceKey<'_>) -> Option<ResourceResult<'a>> {
+        self.by_key.get(key).map(|e| ResourceResult::new(e))
+    }
+
+    /// First result for a resource of the given kind and id.
+    pub fn get_by_kind(
+        &self,
+        kind: impl AsRef<str>,
+        id: impl AsRef<str>,
+    ) -> Option<ResourceResult<'a>> {
+        self.by_kind_id
+            .get(&(kind.as_ref(), id.as_ref()))
+            .and_then(|entries| entries.first())
+            .map(|e| ResourceResult::new(e))
+    }
+
+    /// First result for the resource with the given id that satisfies the matcher.
+    pub fn find(
+        &self,
+        id: impl AsRef<str>,
+        matcher: &ResourceMatcher,
+    ) -> Option<ResourceResult<'a>> {
+        self.by_id
+            .get(id.as_ref())?
+            .iter()
+            .find(|e| e.resource.as_ref().is_some_and(|r| matcher.matches(r)))
+            .map(|e| ResourceResult::new(e))
+    }
+
+    /// All results that satisfy the matcher, in response order.
+    pub fn filter<'m>(
+        &'m self,
+        matcher: &'m ResourceMatcher,
+    ) -> impl Iterator<Item = ResourceResult<'a>> + 'm {
+        self.results
+            .iter()
+            .filter(|e| e.resource.as_ref().is_some_and(|r| matcher.matches(r)))
+            .map(ResourceResult::new)
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
 pub struct CheckResourcesResponseIter<'a> {
     iter: Iter<'a, ResultEntry>,
 }
@@ -420,19 +624,78 @@ pub struct PlanResourcesResponse {
 }
 
 impl PlanResourcesResponse {
-    pub fn filter(&self) -> PlanResourcesFilter {
-        let f = self.response.filter.as_ref().unwrap();
-        let kind = Kind::try_from(f.kind).unwrap();
-
-        match kind {
-            Kind::AlwaysAllowed => PlanResourcesFilter::AlwaysAllowed,
-            Kind::AlwaysDenied => PlanResourcesFilter::AlwaysDenied,
-            Kind::Conditional => {
-                PlanResourcesFilter::Conditional(f.condition.as_ref().unwrap().clone())
-            }
-            _ => PlanResourcesFilter::AlwaysDenied, // can never happen
+    /// Query plan filter. Fails if the response has no filter, the filter kind is unknown or a
+    /// conditional filter has no condition.
+    pub fn filter(&self) -> anyhow::Result<PlanResourcesFilter> {
+        let f = self
+            .response
+            .filter
+            .as_ref()
+            .ok_or_else(|| anyhow!("response does not contain a filter"))?;
+
+        match Kind::try_from(f.kind) {
+            Ok(Kind::AlwaysAllowed) => Ok(PlanResourcesFilter::AlwaysAllowed),
+            Ok(Kind::AlwaysDenied) => Ok(PlanResourcesFilter::AlwaysDenied),
+            Ok(Kind::Conditional) => f
+                .condition
+                .clone()
+                .map(PlanResourcesFilter::Conditional)
+                .ok_or_else(|| anyhow!("conditional filter does not contain a condition")),
+            Ok(Kind::Unspecified) => bail!("filter kind is unspecified"),
+            Err(_) => bail!("unknown filter kind {}", f.kind),
         }
     }
+
+    pub fn request_id(&self) -> &str {
+        &self.response.request_id
+    }
+
+    pub fn cerbos_call_id(&self) -> &str {
+        &self.response.cerbos_call_id
+    }
+
+    /// Actions the plan was produced for. Falls back to the deprecated single action field when
+    /// talking to older PDPs.
+    pub fn actions(&self) -> Vec<&str> {
+        if self.response.actions.is_empty() {
+            #[allow(deprecated)]
+            let action = self.response.action.as_str();
+            return if action.is_empty() {
+                vec![]
+            } else {
+                vec![action]
+            };
+        }
+        self.response.actions.iter().map(String::as_str).collect()
+    }
+
+    pub fn resource_kind(&self) -> &str {
+        &self.response.resource_kind
+    }
+
+    pub fn policy_version(&self) -> &str {
+        &self.response.policy_version
+    }
+
+    /// Schema validation errors reported for the principal and resource attributes.
+    pub fn validation_errors(&self) -> &[ValidationError] {
+        &self.response.validation_errors
+    }
+
+    /// Scopes matched for each action. Only populated if the request asked for metadata.
+    pub fn matched_scopes(&self) -> Option<&HashMap<String, String>> {
+        self.response.meta.as_ref().map(|m| &m.matched_scopes)
+    }
+
+    /// Human readable representation of the filter. Only populated if the request asked for
+    /// metadata.
+    pub fn filter_debug(&self) -> Option<&str> {
+        self.response
+            .meta
+            .as_ref()
+            .map(|m| m.filter_debug.as_str())
+            .filter(|d| !d.is_empty())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -441,3 +704,110 @@ pub enum PlanResourcesFilter {
     AlwaysDenied,
     Conditional(Operand),
 }
+
+impl PlanResourcesFilter {
+    /// Convert the filter into a typed expression. `AlwaysAllowed` and `AlwaysDenied` become the
+    /// boolean literals `true` and `false`.
+    pub fn to_expr(&self) -> anyhow::Result<Expr> {
+        match self {
+            PlanResourcesFilter::AlwaysAllowed => Ok(Expr::bool(true)),
+            PlanResourcesFilter::AlwaysDenied => Ok(Expr::bool(false)),
+            PlanResourcesFilter::Conditional(operand) => Expr::try_from(operand),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: &str, id: &str, scope: &str, actions: &[(&str, Effect)]) -> ResultEntry {
+        ResultEntry {
+            resource: Some(ResultResource {
+                id: id.to_string(),
+                kind: kind.to_string(),
+                policy_version: "default".to_string(),
+                scope: scope.to_string(),
+            }),
+            actions: actions
+                .iter()
+                .map(|(a, e)| (a.to_string(), *e as i32))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn response() -> CheckResourcesResponse {
+        CheckResourcesResponse {
+            response: CheckResourcesResponsePB {
+                request_id: "1".to_string(),
+                results: vec![
+                    entry("leave_request", "XX125", "", &[("view", Effect::Allow)]),
+                    entry("album", "XX125", "acme", &[("view", Effect::Deny)]),
+                    entry("album", "XX200", "", &[("view", Effect::Allow)]),
+                ],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_index_lookup() {
+        let resp = response();
+        let index = resp.index();
+        assert_eq!(index.len(), 3);
+
+        let r = index.get_by_kind("album", "XX125").unwrap();
+        assert!(!r.is_allowed("view"));
+        assert_eq!(r.effect("view"), Some(Effect::Deny));
+        assert_eq!(r.effect("edit"), None);
+
+        let key = ResourceKey {
+            kind: "leave_request",
+            id: "XX125",
+            scope: "",
+            policy_version: "default",
+        };
+        assert!(index.get(&key).unwrap().is_allowed("view"));
+
+        let acme = ResourceMatcher::kind("album").and(ResourceMatcher::scope("acme"));
+        assert!(!index.find("XX125", &acme).unwrap().is_allowed("view"));
+        assert!(index.find("XX200", &acme).is_none());
+
+        let not_acme = ResourceMatcher::scope("acme").not();
+        let kinds: Vec<_> = index
+            .filter(&not_acme)
+            .map(|r| r.resource().unwrap().kind.as_str())
+            .collect();
+        assert_eq!(kinds, ["leave_request", "album"]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_find_with_predicates() {
+        let resp = response();
+        let r = resp
+            .find_with_predicates(
+                "XX125",
+                vec![
+                    ResourceMatcher::kind("album"),
+                    ResourceMatcher::policy_version("default"),
+                ],
+            )
+            .unwrap();
+        assert_eq!(r.resource().unwrap().kind, "album");
+        assert!(resp.find_with_predicates("XX125", vec![]).is_some());
+    }
+
+    #[test]
+    fn test_decisions() {
+        assert!(response().decisions().is_err());
+
+        let mut resp = response();
+        resp.response.results.remove(0);
+        let decisions = resp.decisions().unwrap();
+        assert_eq!(decisions.len(), 2);
+        assert_eq!(decisions["XX125"]["view"], Effect::Deny);
+        assert_eq!(decisions["XX200"]["view"], Effect::Allow);
+    }
+}
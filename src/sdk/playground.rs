@@ -0,0 +1,407 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt;
+
+use thiserror::Error;
+use tonic::{codegen::InterceptedService, transport::Channel};
+
+use crate::genpb::cerbos::{
+    policy::v1::TestResults,
+    request::v1::{
+        playground_proxy_request::ProxyRequest, File, PlaygroundEvaluateRequest,
+        PlaygroundProxyRequest, PlaygroundTestRequest, PlaygroundValidateRequest,
+    },
+    response::v1::{
+        playground_evaluate_response::{self, EvalResultList},
+        playground_failure::Error as FileError,
+        playground_proxy_response, playground_test_response, playground_validate_response,
+        PlaygroundFailure,
+    },
+    svc::v1::cerbos_playground_service_client::CerbosPlaygroundServiceClient,
+};
+
+use super::model::{self, ProtobufWrapper};
+use super::{
+    check_resources_request, plan_resources_request, CerbosClientOptions, CerbosInterceptor,
+    PlanActions,
+};
+
+/// Error returned by [`PlaygroundClient`] calls.
+#[derive(Error, Debug, Clone)]
+pub enum PlaygroundError {
+    /// The playground rejected the uploaded files.
+    #[error("{}", FileErrors(.errors))]
+    Failure { errors: Vec<FileError> },
+    #[error("{message:?}")]
+    Rpc {
+        message: String,
+        underlying: tonic::Status,
+    },
+    #[error("{message:?}")]
+    InvalidResponse { message: String },
+}
+
+impl From<tonic::Status> for PlaygroundError {
+    fn from(status: tonic::Status) -> Self {
+        PlaygroundError::Rpc {
+            message: status.message().to_string(),
+            underlying: status,
+        }
+    }
+}
+
+impl From<PlaygroundFailure> for PlaygroundError {
+    fn from(failure: PlaygroundFailure) -> Self {
+        PlaygroundError::Failure {
+            errors: failure.errors,
+        }
+    }
+}
+
+struct FileErrors<'a>(&'a [FileError]);
+
+impl fmt::Display for FileErrors<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "playground reported {} error(s)", self.0.len())?;
+        for e in self.0 {
+            write!(f, "; {}", e.file)?;
+            if let Some(d) = &e.details {
+                write!(f, ":{}:{}", d.line, d.column)?;
+            }
+            write!(f, ": {}", e.error)?;
+        }
+        Ok(())
+    }
+}
+
+fn missing_outcome() -> PlaygroundError {
+    PlaygroundError::InvalidResponse {
+        message: "response has no outcome".to_string(),
+    }
+}
+
+/// Client for the Cerbos playground service. Policies, schemas and test suites are uploaded as
+/// files with every call.
+pub struct PlaygroundClient {
+    stub: CerbosPlaygroundServiceClient<InterceptedService<Channel, CerbosInterceptor>>,
+    playground_id: String,
+    request_id_gen: fn() -> String,
+    files: Vec<File>,
+}
+
+impl PlaygroundClient {
+    /// Create a new playground client. The playground instance is taken from
+    /// [`CerbosClientOptions::with_playground_instance`].
+    pub async fn new<S>(conf: CerbosClientOptions<S>) -> anyhow::Result<Self>
+    where
+        S: Into<String> + Send,
+    {
+        let playground_id = conf.playground_instance.clone().unwrap_or_default();
+        let playground_instance = match conf.playground_instance {
+            Some(ref instance) => Some(instance.parse()?),
+            None => None,
+        };
+        let request_timeout = conf.timeout;
+        let request_id_gen = conf.request_id_gen;
+        let channel = conf.build_channel()?;
+        let stub = CerbosPlaygroundServiceClient::with_interceptor(
+            channel,
+            CerbosInterceptor {
+                playground_instance,
+                request_timeout,
+            },
+        );
+
+        Ok(Self {
+            stub,
+            playground_id,
+            request_id_gen,
+            files: Vec::new(),
+        })
+    }
+
+    /// Add a file to upload. Schemas must be placed under `_schemas/` and test fixtures under
+    /// `testdata/`.
+    pub fn add_file(
+        &mut self,
+        file_name: impl Into<String>,
+        contents: impl Into<Vec<u8>>,
+    ) -> &mut Self {
+        self.files.push(File {
+            file_name: file_name.into(),
+            contents: contents.into(),
+        });
+        self
+    }
+
+    /// Add all policies of a policy set. Policies read from a file or reader are uploaded as is,
+    /// others are written out as YAML.
+    #[cfg(feature = "admin")]
+    pub fn add_policy_set(
+        &mut self,
+        policies: &super::admin::model::PolicySet,
+    ) -> anyhow::Result<&mut Self> {
+        use super::deser::{write_policy, PolicyFormat};
+
+        let mut names: std::collections::HashSet<String> =
+            self.files.iter().map(|f| f.file_name.clone()).collect();
+        for (i, policy) in policies.get_policies().iter().enumerate() {
+            let (name, contents) = match policies.source(i) {
+                Some(source) => {
+                    let name = source.file_name.clone().unwrap_or_else(|| {
+                        let json = source.contents.trim_ascii_start().starts_with(b"{");
+                        format!("policy_{i:03}.{}", if json { "json" } else { "yaml" })
+                    });
+                    (name, source.contents.clone())
+                }
+                None => {
+                    let mut contents = Vec::new();
+                    write_policy(policy, PolicyFormat::Yaml, &mut contents)?;
+                    (format!("policy_{i:03}.yaml"), contents)
+                }
+            };
+            let name = if names.contains(&name) {
+                format!("{i:03}_{name}")
+            } else {
+                name
+            };
+            names.insert(name.clone());
+            self.add_file(name, contents);
+        }
+        Ok(self)
+    }
+
+    /// Add all schemas of a schema set under `_schemas/`.
+    #[cfg(feature = "admin")]
+    pub fn add_schema_set(&mut self, schemas: &super::admin::model::SchemaSet) -> &mut Self {
+        for schema in schemas.get_schemas() {
+            self.add_file(format!("_schemas/{}", schema.id), schema.definition.clone());
+        }
+        self
+    }
+
+    pub fn files(&self) -> &[File] {
+        &self.files
+    }
+
+    /// Check that the uploaded files are valid policies.
+    pub async fn validate(&mut self) -> Result<(), PlaygroundError> {
+        let req = PlaygroundValidateRequest {
+            playground_id: self.playground_id.clone(),
+            files: self.files.clone(),
+        };
+        let resp = self.stub.playground_validate(req).await?.into_inner();
+        match resp.outcome.ok_or_else(missing_outcome)? {
+            playground_validate_response::Outcome::Success(_) => Ok(()),
+            playground_validate_response::Outcome::Failure(f) => Err(f.into()),
+        }
+    }
+
+    /// Run the test suites among the uploaded files.
+    pub async fn test(&mut self) -> Result<TestResults, PlaygroundError> {
+        let req = PlaygroundTestRequest {
+            playground_id: self.playground_id.clone(),
+            files: self.files.clone(),
+        };
+        let resp = self.stub.playground_test(req).await?.into_inner();
+        match resp.outcome.ok_or_else(missing_outcome)? {
+            playground_test_response::Outcome::Success(r) => Ok(r.results.unwrap_or_default()),
+            playground_test_response::Outcome::Failure(f) => Err(f.into()),
+        }
+    }
+
+    /// Evaluate the uploaded policies for the given principal, resource and actions.
+    pub async fn evaluate<A, S>(
+        &mut self,
+        principal: model::Principal,
+        resource: model::Resource,
+        actions: A,
+        aux_data: Option<model::AuxData>,
+    ) -> Result<EvalResultList, PlaygroundError>
+    where
+        S: Into<String>,
+        A: IntoIterator<Item = S>,
+    {
+        let req = PlaygroundEvaluateRequest {
+            playground_id: self.playground_id.clone(),
+            files: self.files.clone(),
+            principal: Some(principal.to_pb()),
+            resource: Some(resource.to_pb()),
+            actions: actions.into_iter().map(Into::into).collect(),
+            aux_data: aux_data.map(|a| a.to_pb()),
+        };
+        let resp = self.stub.playground_evaluate(req).await?.into_inner();
+        match resp.outcome.ok_or_else(missing_outcome)? {
+            playground_evaluate_response::Outcome::Success(r) => Ok(r),
+            playground_evaluate_response::Outcome::Failure(f) => Err(f.into()),
+        }
+    }
+
+    /// Check access to multiple resources using the uploaded policies.
+    pub async fn check_resources(
+        &mut self,
+        principal: model::Principal,
+        resources: model::ResourceList,
+        aux_data: Option<model::AuxData>,
+    ) -> Result<model::CheckResourcesResponse, PlaygroundError> {
+        let req = check_resources_request(
+            (self.request_id_gen)(),
+            false,
+            principal,
+            resources,
+            aux_data,
+        );
+        match self.proxy(ProxyRequest::CheckResources(req)).await? {
+            playground_proxy_response::Outcome::CheckResources(response) => {
+                Ok(model::CheckResourcesResponse { response })
+            }
+            _ => Err(PlaygroundError::InvalidResponse {
+                message: "expected a CheckResources response".to_string(),
+            }),
+        }
+    }
+
+    /// Produce a query plan using the uploaded policies.
+    pub async fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: model::Principal,
+        resource: model::ResourceKind,
+        aux_data: Option<model::AuxData>,
+    ) -> Result<model::PlanResourcesResponse, PlaygroundError>
+    where
+        S: Into<String>,
+    {
+        let req = plan_resources_request(
+            (self.request_id_gen)(),
+            false,
+            PlanActions::Single(action.into()),
+            principal,
+            resource,
+            aux_data,
+        );
+        match self.proxy(ProxyRequest::PlanResources(req)).await? {
+            playground_proxy_response::Outcome::PlanResources(response) => {
+                Ok(model::PlanResourcesResponse { response })
+            }
+            _ => Err(PlaygroundError::InvalidResponse {
+                message: "expected a PlanResources response".to_string(),
+            }),
+        }
+    }
+
+    async fn proxy(
+        &mut self,
+        request: ProxyRequest,
+    ) -> Result<playground_proxy_response::Outcome, PlaygroundError> {
+        let req = PlaygroundProxyRequest {
+            playground_id: self.playground_id.clone(),
+            files: self.files.clone(),
+            proxy_request: Some(request),
+        };
+        let resp = self.stub.playground_proxy(req).await?.into_inner();
+        match resp.outcome.ok_or_else(missing_outcome)? {
+            playground_proxy_response::Outcome::Failure(f) => Err(f.into()),
+            outcome => Ok(outcome),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genpb::cerbos::response::v1::playground_failure::ErrorDetails;
+
+    #[test]
+    fn test_file_errors() {
+        let err: PlaygroundError = PlaygroundFailure {
+            errors: vec![
+                FileError {
+                    file: "resource.yaml".to_string(),
+                    error: "unknown field".to_string(),
+                    details: Some(ErrorDetails {
+                        line: 4,
+                        column: 3,
+                        context: String::new(),
+                    }),
+                },
+                FileError {
+                    file: "principal.yaml".to_string(),
+                    error: "missing import".to_string(),
+                    details: None,
+                },
+            ],
+        }
+        .into();
+        assert_eq!(
+            err.to_string(),
+            "playground reported 2 error(s); resource.yaml:4:3: unknown field; principal.yaml: missing import"
+        );
+    }
+
+    #[cfg(feature = "admin")]
+    #[tokio::test]
+    async fn test_add_policy_set() -> anyhow::Result<()> {
+        use crate::sdk::{admin::model::PolicySet, CerbosEndpoint};
+
+        const YAML: &str = "apiVersion: api.cerbos.dev/v1
+resourcePolicy:
+  resource: yaml_doc
+  version: default
+  rules:
+    - actions: [view]
+      effect: EFFECT_ALLOW
+      roles: [user]
+";
+        const JSON: &str = r#"{"apiVersion":"api.cerbos.dev/v1","resourcePolicy":{"resource":"json_doc","version":"default","rules":[{"actions":["view"],"effect":"EFFECT_ALLOW","roles":["user"]}]}}"#;
+
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/testdata/policies/resource_policies");
+        let mut policies = PolicySet::new();
+        policies.add_policy_from_file(dir.join("policy_06.yaml"))?;
+        policies.add_policy_from_file(dir.join("policy_08.yaml"))?;
+        policies.add_policy_from_reader(YAML.as_bytes())?;
+        policies.add_policy_from_reader(JSON.as_bytes())?;
+        let generated = policies.get_policies()[2].clone();
+        policies.add_policy(generated.clone());
+
+        let opts =
+            CerbosClientOptions::new(CerbosEndpoint::HostPort("localhost", 3593)).with_plaintext();
+        let mut client = PlaygroundClient::new(opts).await?;
+        client
+            .add_file("policy_06.yaml", "existing")
+            .add_file("policy_002.yaml", "existing")
+            .add_policy_set(&policies)?;
+
+        let names: Vec<&str> = client
+            .files()
+            .iter()
+            .map(|f| f.file_name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "policy_06.yaml",
+                "policy_002.yaml",
+                "000_policy_06.yaml",
+                "policy_08.yaml",
+                "002_policy_002.yaml",
+                "policy_003.json",
+                "policy_004.yaml",
+            ]
+        );
+
+        let files = client.files();
+        assert_eq!(
+            files[2].contents,
+            std::fs::read(dir.join("policy_06.yaml"))?
+        );
+        assert_eq!(files[4].contents, YAML.as_bytes());
+        assert_eq!(files[5].contents, JSON.as_bytes());
+        let mut written = PolicySet::new();
+        written.add_policy_from_reader(files[6].contents.as_slice())?;
+        assert_eq!(written.get_policies(), [generated]);
+        Ok(())
+    }
+}
@@ -0,0 +1,589 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Typed representation of the conditional filters returned by `PlanResources`.
+//!
+//! The raw [`Operand`] tree uses operator strings and nested oneofs. [`Expr`] converts it into an
+//! enum that can be matched on, walked with a [`Visitor`], simplified with [`Expr::normalize`] and
+//! rendered back to CEL through its `Display` implementation.
+
+use std::fmt;
+
+use anyhow::{anyhow, bail};
+
+use crate::genpb::cerbos::engine::v1::plan_resources_filter::expression::{operand::Node, Operand};
+use crate::genpb::google::protobuf::{value::Kind, Value};
+
+use super::Result;
+
+/// Query plan expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Literal value.
+    Value(Value),
+    /// Reference to a field such as `request.resource.attr.owner`.
+    Field(String),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    Ne(Box<Expr>, Box<Expr>),
+    Lt(Box<Expr>, Box<Expr>),
+    Le(Box<Expr>, Box<Expr>),
+    Gt(Box<Expr>, Box<Expr>),
+    Ge(Box<Expr>, Box<Expr>),
+    /// Membership test: `lhs in rhs`.
+    In(Box<Expr>, Box<Expr>),
+    /// Any other operator, for example `hasIntersection`, `exists` or `add`. Operators CEL writes
+    /// differently from a function call, such as `index`, `list`, comprehensions and methods, are
+    /// rendered in their CEL form.
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    /// Literal boolean.
+    pub fn bool(b: bool) -> Self {
+        Expr::Value(Value {
+            kind: Some(Kind::BoolValue(b)),
+        })
+    }
+
+    /// Returns the boolean if this expression is a boolean literal.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Expr::Value(Value {
+                kind: Some(Kind::BoolValue(b)),
+            }) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Flatten nested `and`/`or` expressions, fold boolean constants and remove double negation.
+    pub fn normalize(self) -> Self {
+        let normalize_box = |e: Box<Expr>| Box::new(e.normalize());
+        match self {
+            Expr::And(args) => normalize_logical(args, true),
+            Expr::Or(args) => normalize_logical(args, false),
+            Expr::Not(inner) => match inner.normalize() {
+                Expr::Not(e) => *e,
+                e => match e.as_bool() {
+                    Some(b) => Expr::bool(!b),
+                    None => Expr::Not(Box::new(e)),
+                },
+            },
+            Expr::Eq(l, r) => Expr::Eq(normalize_box(l), normalize_box(r)),
+            Expr::Ne(l, r) => Expr::Ne(normalize_box(l), normalize_box(r)),
+            Expr::Lt(l, r) => Expr::Lt(normalize_box(l), normalize_box(r)),
+            Expr::Le(l, r) => Expr::Le(normalize_box(l), normalize_box(r)),
+            Expr::Gt(l, r) => Expr::Gt(normalize_box(l), normalize_box(r)),
+            Expr::Ge(l, r) => Expr::Ge(normalize_box(l), normalize_box(r)),
+            Expr::In(l, r) => Expr::In(normalize_box(l), normalize_box(r)),
+            Expr::Call { name, args } => Expr::Call {
+                name,
+                args: args.into_iter().map(Expr::normalize).collect(),
+            },
+            e @ (Expr::Value(_) | Expr::Field(_)) => e,
+        }
+    }
+
+    /// Walk the expression with the given visitor.
+    pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        visitor.visit_expr(self)
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(_) => 1,
+            Expr::And(_) => 2,
+            Expr::Eq(..)
+            | Expr::Ne(..)
+            | Expr::Lt(..)
+            | Expr::Le(..)
+            | Expr::Gt(..)
+            | Expr::Ge(..)
+            | Expr::In(..) => 3,
+            Expr::Call { name, args } if args.len() == 2 => match name.as_str() {
+                "add" | "sub" => 4,
+                "mult" | "div" | "mod" => 5,
+                _ => 7,
+            },
+            Expr::Not(_) => 6,
+            _ => 7,
+        }
+    }
+}
+
+// `identity` is the neutral element of the operator: `true` for `and`, `false` for `or`.
+fn normalize_logical(args: Vec<Expr>, identity: bool) -> Expr {
+    let mut flat = Vec::with_capacity(args.len());
+    for arg in args.into_iter().map(Expr::normalize) {
+        match (arg, identity) {
+            (Expr::And(inner), true) | (Expr::Or(inner), false) => flat.extend(inner),
+            (arg, _) => match arg.as_bool() {
+                Some(b) if b == identity => {}
+                Some(_) => return Expr::bool(!identity),
+                None => flat.push(arg),
+            },
+        }
+    }
+
+    match flat.len() {
+        0 => Expr::bool(identity),
+        1 => flat.remove(0),
+        _ if identity => Expr::And(flat),
+        _ => Expr::Or(flat),
+    }
+}
+
+impl TryFrom<&Operand> for Expr {
+    type Error = anyhow::Error;
+
+    fn try_from(operand: &Operand) -> Result<Self> {
+        match operand.node.as_ref() {
+            Some(Node::Value(v)) => Ok(Expr::Value(v.clone())),
+            Some(Node::Variable(name)) => Ok(Expr::Field(name.clone())),
+            Some(Node::Expression(expr)) => {
+                let mut args = expr
+                    .operands
+                    .iter()
+                    .map(Expr::try_from)
+                    .collect::<Result<Vec<_>>>()?;
+                let op = expr.operator.as_str();
+
+                let binary = |args: &mut Vec<Expr>| -> Result<(Box<Expr>, Box<Expr>)> {
+                    if args.len() != 2 {
+                        bail!("operator {op} expects 2 operands, got {}", args.len());
+                    }
+                    let r = args.pop().map(Box::new);
+                    let l = args.pop().map(Box::new);
+                    l.zip(r).ok_or_else(|| anyhow!("missing operands for {op}"))
+                };
+
+                let e = match op {
+                    "and" => Expr::And(args),
+                    "or" => Expr::Or(args),
+                    "not" => {
+                        if args.len() != 1 {
+                            bail!("operator not expects 1 operand, got {}", args.len());
+                        }
+                        Expr::Not(Box::new(args.remove(0)))
+                    }
+                    "eq" => binary(&mut args).map(|(l, r)| Expr::Eq(l, r))?,
+                    "ne" => binary(&mut args).map(|(l, r)| Expr::Ne(l, r))?,
+                    "lt" => binary(&mut args).map(|(l, r)| Expr::Lt(l, r))?,
+                    "le" => binary(&mut args).map(|(l, r)| Expr::Le(l, r))?,
+                    "gt" => binary(&mut args).map(|(l, r)| Expr::Gt(l, r))?,
+                    "ge" => binary(&mut args).map(|(l, r)| Expr::Ge(l, r))?,
+                    "in" => binary(&mut args).map(|(l, r)| Expr::In(l, r))?,
+                    "" => bail!("expression without an operator"),
+                    _ => Expr::Call {
+                        name: op.to_string(),
+                        args,
+                    },
+                };
+                Ok(e)
+            }
+            None => bail!("operand without a value, variable or expression"),
+        }
+    }
+}
+
+impl TryFrom<Operand> for Expr {
+    type Error = anyhow::Error;
+
+    fn try_from(operand: Operand) -> Result<Self> {
+        Expr::try_from(&operand)
+    }
+}
+
+/// Visitor over [`Expr`] trees. Override the methods of interest; the defaults walk into children.
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_value(&mut self, _value: &Value) {}
+
+    fn visit_field(&mut self, _name: &str) {}
+
+    fn visit_call(&mut self, _name: &str, args: &[Expr]) {
+        args.iter().for_each(|a| self.visit_expr(a))
+    }
+}
+
+/// Visit the children of `expr`. Used by the default [`Visitor::visit_expr`].
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Value(v) => visitor.visit_value(v),
+        Expr::Field(f) => visitor.visit_field(f),
+        Expr::And(args) | Expr::Or(args) => args.iter().for_each(|a| visitor.visit_expr(a)),
+        Expr::Not(e) => visitor.visit_expr(e),
+        Expr::Eq(l, r)
+        | Expr::Ne(l, r)
+        | Expr::Lt(l, r)
+        | Expr::Le(l, r)
+        | Expr::Gt(l, r)
+        | Expr::Ge(l, r)
+        | Expr::In(l, r) => {
+            visitor.visit_expr(l);
+            visitor.visit_expr(r);
+        }
+        Expr::Call { name, args } => visitor.visit_call(name, args),
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let child = |f: &mut fmt::Formatter<'_>, e: &Expr, right: bool| -> fmt::Result {
+            let associative = matches!(self, Expr::And(_) | Expr::Or(_));
+            if e.precedence() < self.precedence()
+                || (right && !associative && e.precedence() == self.precedence())
+            {
+                write!(f, "({e})")
+            } else {
+                write!(f, "{e}")
+            }
+        };
+        let infix = |f: &mut fmt::Formatter<'_>, op: &str, args: &[&Expr]| -> fmt::Result {
+            for (i, a) in args.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {op} ")?;
+                }
+                child(f, a, i > 0)?;
+            }
+            Ok(())
+        };
+
+        match self {
+            Expr::Value(v) => write_value(f, v),
+            Expr::Field(name) => f.write_str(name),
+            Expr::And(args) if args.is_empty() => f.write_str("true"),
+            Expr::Or(args) if args.is_empty() => f.write_str("false"),
+            Expr::And(args) => infix(f, "&&", &args.iter().collect::<Vec<_>>()),
+            Expr::Or(args) => infix(f, "||", &args.iter().collect::<Vec<_>>()),
+            Expr::Not(e) => {
+                f.write_str("!")?;
+                child(f, e, false)
+            }
+            Expr::Eq(l, r) => infix(f, "==", &[l, r]),
+            Expr::Ne(l, r) => infix(f, "!=", &[l, r]),
+            Expr::Lt(l, r) => infix(f, "<", &[l, r]),
+            Expr::Le(l, r) => infix(f, "<=", &[l, r]),
+            Expr::Gt(l, r) => infix(f, ">", &[l, r]),
+            Expr::Ge(l, r) => infix(f, ">=", &[l, r]),
+            Expr::In(l, r) => infix(f, "in", &[l, r]),
+            Expr::Call { name, args } => {
+                let args_ref = args.iter().collect::<Vec<_>>();
+                match (name.as_str(), args.len()) {
+                    ("add", 2) => infix(f, "+", &args_ref),
+                    ("sub", 2) => infix(f, "-", &args_ref),
+                    ("mult", 2) => infix(f, "*", &args_ref),
+                    ("div", 2) => infix(f, "/", &args_ref),
+                    ("mod", 2) => infix(f, "%", &args_ref),
+                    ("index", 2) => {
+                        receiver(f, &args[0])?;
+                        write!(f, "[{}]", args[1])
+                    }
+                    ("list", _) => {
+                        f.write_str("[")?;
+                        write_args(f, args)?;
+                        f.write_str("]")
+                    }
+                    (macro_name, 2) if COMPREHENSIONS.contains(&macro_name) => match &args[1] {
+                        Expr::Call {
+                            name: lambda,
+                            args: lambda_args,
+                        } if lambda == "lambda" && lambda_args.len() == 2 => {
+                            receiver(f, &args[0])?;
+                            write!(f, ".{name}({}, {})", lambda_args[1], lambda_args[0])
+                        }
+                        _ => call(f, name, args),
+                    },
+                    (method, 1..) if METHODS.contains(&method) => {
+                        receiver(f, &args[0])?;
+                        write!(f, ".{name}(")?;
+                        write_args(f, &args[1..])?;
+                        f.write_str(")")
+                    }
+                    _ => call(f, name, args),
+                }
+            }
+        }
+    }
+}
+
+/// Macros whose second operand is a `lambda` of the body and the bound variable, rendered as
+/// `list.exists(x, body)`.
+const COMPREHENSIONS: [&str; 5] = ["all", "exists", "exists_one", "filter", "map"];
+
+/// Functions that CEL only accepts as methods, rendered as `receiver.name(args)`.
+const METHODS: [&str; 7] = [
+    "contains",
+    "endsWith",
+    "except",
+    "inIPAddrRange",
+    "isSubset",
+    "matches",
+    "startsWith",
+];
+
+// Receivers bind tighter than any operator, so anything but an atom needs parentheses.
+fn receiver(f: &mut fmt::Formatter<'_>, e: &Expr) -> fmt::Result {
+    if e.precedence() < 7 {
+        write!(f, "({e})")
+    } else {
+        write!(f, "{e}")
+    }
+}
+
+fn call(f: &mut fmt::Formatter<'_>, name: &str, args: &[Expr]) -> fmt::Result {
+    write!(f, "{name}(")?;
+    write_args(f, args)?;
+    f.write_str(")")
+}
+
+fn write_args(f: &mut fmt::Formatter<'_>, args: &[Expr]) -> fmt::Result {
+    for (i, a) in args.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{a}")?;
+    }
+    Ok(())
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, v: &Value) -> fmt::Result {
+    match v.kind.as_ref() {
+        None | Some(Kind::NullValue(_)) => f.write_str("null"),
+        Some(Kind::BoolValue(b)) => write!(f, "{b}"),
+        Some(Kind::NumberValue(n)) if n.is_nan() => f.write_str(r#"double("NaN")"#),
+        Some(Kind::NumberValue(n)) if n.is_infinite() => {
+            let sign = if *n < 0.0 { "-" } else { "" };
+            write!(f, r#"double("{sign}Infinity")"#)
+        }
+        Some(Kind::NumberValue(n)) => write!(f, "{n}"),
+        Some(Kind::StringValue(s)) => write_string(f, s),
+        Some(Kind::ListValue(l)) => {
+            f.write_str("[")?;
+            for (i, v) in l.values.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_value(f, v)?;
+            }
+            f.write_str("]")
+        }
+        Some(Kind::StructValue(s)) => {
+            let mut keys: Vec<_> = s.fields.keys().collect();
+            keys.sort();
+            f.write_str("{")?;
+            for (i, k) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_string(f, k)?;
+                f.write_str(": ")?;
+                write_value(f, &s.fields[k])?;
+            }
+            f.write_str("}")
+        }
+    }
+}
+
+// Quoted CEL string literal. Rust's `{:?}` escapes such as `\u{1b}` are not valid CEL, so control
+// characters are written as `\xHH` or `\uHHHH`.
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_ascii_control() => write!(f, "\\x{:02x}", c as u32)?,
+            c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+                write!(f, "\\u{:04x}", c as u32)?
+            }
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genpb::cerbos::engine::v1::plan_resources_filter::Expression;
+    use crate::sdk::attr::AttrVal;
+
+    fn var(name: &str) -> Operand {
+        Operand {
+            node: Some(Node::Variable(name.to_string())),
+        }
+    }
+
+    fn val(v: impl AttrVal) -> Operand {
+        Operand {
+            node: Some(Node::Value(v.to_value())),
+        }
+    }
+
+    fn op(operator: &str, operands: Vec<Operand>) -> Operand {
+        Operand {
+            node: Some(Node::Expression(Expression {
+                operator: operator.to_string(),
+                operands,
+            })),
+        }
+    }
+
+    #[test]
+    fn test_convert_and_display() {
+        let operand = op(
+            "or",
+            vec![
+                op(
+                    "and",
+                    vec![
+                        op("eq", vec![var("request.resource.attr.owner"), val("alice")]),
+                        op(
+                            "not",
+                            vec![op("in", vec![val("GB"), var("request.resource.attr.geos")])],
+                        ),
+                    ],
+                ),
+                op("gt", vec![var("request.resource.attr.count"), val(3)]),
+            ],
+        );
+
+        let expr = Expr::try_from(&operand).unwrap();
+        assert_eq!(
+            expr.to_string(),
+            r#"request.resource.attr.owner == "alice" && !("GB" in request.resource.attr.geos) || request.resource.attr.count > 3"#
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let operand = op(
+            "and",
+            vec![
+                val(true),
+                op(
+                    "and",
+                    vec![
+                        op("eq", vec![var("a"), val(1)]),
+                        op(
+                            "or",
+                            vec![val(false), op("not", vec![op("not", vec![var("b")])])],
+                        ),
+                    ],
+                ),
+            ],
+        );
+
+        let expr = Expr::try_from(operand).unwrap().normalize();
+        assert_eq!(expr.to_string(), "a == 1 && b");
+
+        let expr = Expr::try_from(op("and", vec![var("a"), val(false)])).unwrap();
+        assert_eq!(expr.normalize().as_bool(), Some(false));
+    }
+
+    #[test]
+    fn test_visitor() {
+        struct Fields(Vec<String>);
+        impl Visitor for Fields {
+            fn visit_field(&mut self, name: &str) {
+                self.0.push(name.to_string());
+            }
+        }
+
+        let expr = Expr::try_from(op(
+            "hasIntersection",
+            vec![var("x"), op("add", vec![var("y"), val(1)])],
+        ))
+        .unwrap();
+        assert_eq!(expr.to_string(), "hasIntersection(x, y + 1)");
+
+        let mut fields = Fields(vec![]);
+        expr.accept(&mut fields);
+        assert_eq!(fields.0, ["x", "y"]);
+    }
+
+    #[test]
+    fn test_display_cel_forms() {
+        let expr = Expr::try_from(op(
+            "exists",
+            vec![
+                var("request.resource.attr.tags"),
+                op(
+                    "lambda",
+                    vec![
+                        op(
+                            "eq",
+                            vec![
+                                op("index", vec![var("t"), val("name")]),
+                                op("list", vec![val(1), val(2)]),
+                            ],
+                        ),
+                        var("t"),
+                    ],
+                ),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(
+            expr.to_string(),
+            r#"request.resource.attr.tags.exists(t, t["name"] == [1, 2])"#
+        );
+
+        let expr = Expr::try_from(op(
+            "startsWith",
+            vec![op("add", vec![var("a"), var("b")]), val("x")],
+        ))
+        .unwrap();
+        assert_eq!(expr.to_string(), r#"(a + b).startsWith("x")"#);
+
+        let expr = Expr::try_from(op(
+            "eq",
+            vec![
+                var("request.resource.attr.note"),
+                val("say \"hi\"\\\n\t\u{1b}\u{7f}\u{85}\u{2028}é"),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(
+            expr.to_string(),
+            r#"request.resource.attr.note == "say \"hi\"\\\n\t\x1b\x7f\u0085\u2028é""#
+        );
+
+        let mut fields = crate::genpb::google::protobuf::Struct::default();
+        fields
+            .fields
+            .insert("a\"b\u{1}".to_string(), "v".to_value());
+        let expr = Expr::Value(Value {
+            kind: Some(Kind::StructValue(fields)),
+        });
+        assert_eq!(expr.to_string(), r#"{"a\"b\x01": "v"}"#);
+
+        for (n, want) in [
+            (f64::NAN, r#"double("NaN")"#),
+            (f64::INFINITY, r#"double("Infinity")"#),
+            (f64::NEG_INFINITY, r#"double("-Infinity")"#),
+            (1.5, "1.5"),
+        ] {
+            let expr = Expr::try_from(op("gt", vec![var("x"), val(n)])).unwrap();
+            assert_eq!(expr.to_string(), format!("x > {want}"));
+        }
+    }
+
+    #[test]
+    fn test_malformed_operand() {
+        assert!(Expr::try_from(Operand { node: None }).is_err());
+        assert!(Expr::try_from(op("eq", vec![var("a")])).is_err());
+    }
+}
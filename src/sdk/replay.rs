@@ -0,0 +1,535 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Record calls made against a real PDP and replay them offline.
+//!
+//! Wrap a [`CerbosAsyncClient`] in a [`RecordingClient`], run the tests once against a PDP and
+//! [`save`](Recording::save) the resulting [`Recording`] as a fixture. A [`ReplayClient`] loaded
+//! from that fixture answers the same requests without a PDP and fails on any request that was not
+//! recorded. Each recorded response is replayed once, so a test that sends a request more often
+//! than the recorded run fails unless [`ReplayClient::with_repeat_last_response`] is set.
+//!
+//! Request IDs and Cerbos call IDs differ between runs, so they are stripped from recorded requests
+//! and responses. Requests are otherwise compared field by field, which means the replaying client
+//! must be configured the same way as the recorded one (for example with
+//! [`ReplayClient::with_include_meta`]).
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use prost::Message;
+
+use crate::genpb::cerbos::{
+    request::v1::{CheckResourcesRequest, PlanResourcesRequest},
+    response::v1::{
+        CheckResourcesResponse as CheckResourcesResponsePB,
+        PlanResourcesResponse as PlanResourcesResponsePB,
+    },
+};
+
+use super::authorizer::Authorizer;
+use super::model::{
+    AuxData, CheckResourcesResponse, PlanResourcesResponse, Principal, ResourceKind, ResourceList,
+};
+use super::{
+    check_resources_request, gen_uuid, plan_resources_request, CerbosAsyncClient, PlanActions,
+    Result,
+};
+
+#[derive(Clone, PartialEq, Message)]
+struct Interaction {
+    #[prost(message, optional, tag = "1")]
+    check_request: Option<CheckResourcesRequest>,
+    #[prost(message, optional, tag = "2")]
+    check_response: Option<CheckResourcesResponsePB>,
+    #[prost(message, optional, tag = "3")]
+    plan_request: Option<PlanResourcesRequest>,
+    #[prost(message, optional, tag = "4")]
+    plan_response: Option<PlanResourcesResponsePB>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Interactions {
+    #[prost(message, repeated, tag = "1")]
+    interactions: Vec<Interaction>,
+}
+
+/// Requests and responses captured by a [`RecordingClient`].
+///
+/// Fixtures are binary protobuf, not text: a single message whose repeated field 1 holds one entry
+/// per call, in call order. Each entry has the `cerbos.request.v1.CheckResourcesRequest` and
+/// `cerbos.response.v1.CheckResourcesResponse` as fields 1 and 2, or the
+/// `cerbos.request.v1.PlanResourcesRequest` and `cerbos.response.v1.PlanResourcesResponse` as
+/// fields 3 and 4. `protoc --decode_raw` prints a fixture for inspection; to change one, record it
+/// again.
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    interactions: Vec<Interaction>,
+}
+
+impl Recording {
+    /// Read a recording previously written with [`Recording::write_to`].
+    pub fn read_from(mut src: impl Read) -> Result<Self> {
+        let mut buf = Vec::new();
+        src.read_to_end(&mut buf)
+            .with_context(|| "failed to read recording")?;
+        let decoded =
+            Interactions::decode(buf.as_slice()).with_context(|| "failed to decode recording")?;
+        Ok(Self {
+            interactions: decoded.interactions,
+        })
+    }
+
+    /// Load a recording from a fixture file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+        Self::read_from(file).with_context(|| format!("failed to load recording from {path:?}"))
+    }
+
+    pub fn write_to(&self, mut dst: impl Write) -> Result<()> {
+        let encoded = Interactions {
+            interactions: self.interactions.clone(),
+        }
+        .encode_to_vec();
+        dst.write_all(&encoded)
+            .with_context(|| "failed to write recording")
+    }
+
+    /// Save the recording to a fixture file, replacing any existing file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path).with_context(|| format!("failed to create {path:?}"))?;
+        self.write_to(file)
+            .with_context(|| format!("failed to save recording to {path:?}"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.interactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interactions.is_empty()
+    }
+}
+
+/// Wraps a [`CerbosAsyncClient`] and records every request it sends along with the response.
+pub struct RecordingClient {
+    client: CerbosAsyncClient,
+    recording: Recording,
+}
+
+impl RecordingClient {
+    pub fn new(client: CerbosAsyncClient) -> Self {
+        Self {
+            client,
+            recording: Recording::default(),
+        }
+    }
+
+    /// Interactions recorded so far.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn into_recording(self) -> Recording {
+        self.recording
+    }
+
+    /// Check access to multiple resources
+    pub async fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> Result<CheckResourcesResponse> {
+        let req = check_resources_request(
+            (self.client.request_id_gen)(),
+            self.client.include_meta,
+            principal,
+            resources,
+            aux_data,
+        );
+        let resp = self.client.send_check_resources(req.clone()).await?;
+        self.recording.interactions.push(Interaction {
+            check_request: Some(CheckResourcesRequest {
+                request_id: String::new(),
+                ..req
+            }),
+            check_response: Some(CheckResourcesResponsePB {
+                request_id: String::new(),
+                cerbos_call_id: String::new(),
+                ..resp.clone()
+            }),
+            ..Default::default()
+        });
+        Ok(CheckResourcesResponse { response: resp })
+    }
+
+    /// Produce a query plan for selecting resources that the principal can perform the given
+    /// action on.
+    pub async fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> Result<PlanResourcesResponse>
+    where
+        S: Into<String> + Clone,
+    {
+        self.plan(
+            PlanActions::Single(action.into()),
+            principal,
+            resource,
+            aux_data,
+        )
+        .await
+    }
+
+    /// Produce a query plan for selecting resources that the principal can perform the given
+    /// actions on. Requires Cerbos 0.44.0 and above.
+    pub async fn plan_resources_for_actions<A, S>(
+        &mut self,
+        actions: A,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> Result<PlanResourcesResponse>
+    where
+        S: Into<String> + Clone,
+        A: IntoIterator<Item = S>,
+    {
+        let actions = PlanActions::Multiple(actions.into_iter().map(Into::into).collect());
+        self.plan(actions, principal, resource, aux_data).await
+    }
+
+    async fn plan(
+        &mut self,
+        actions: PlanActions,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> Result<PlanResourcesResponse> {
+        let req = plan_resources_request(
+            (self.client.request_id_gen)(),
+            self.client.include_meta,
+            actions,
+            principal,
+            resource,
+            aux_data,
+        );
+        let resp = self.client.send_plan_resources(req.clone()).await?;
+        self.recording.interactions.push(Interaction {
+            plan_request: Some(PlanResourcesRequest {
+                request_id: String::new(),
+                ..req
+            }),
+            plan_response: Some(PlanResourcesResponsePB {
+                request_id: String::new(),
+                cerbos_call_id: String::new(),
+                ..resp.clone()
+            }),
+            ..Default::default()
+        });
+        Ok(PlanResourcesResponse { response: resp })
+    }
+}
+
+/// Answers requests from a [`Recording`] without contacting a PDP. Requests that were not recorded
+/// fail with an error describing the request.
+pub struct ReplayClient {
+    recording: Recording,
+    replayed: Vec<bool>,
+    request_id_gen: fn() -> String,
+    include_meta: bool,
+    repeat_last_response: bool,
+}
+
+impl ReplayClient {
+    pub fn new(recording: Recording) -> Self {
+        let replayed = vec![false; recording.len()];
+        Self {
+            recording,
+            replayed,
+            request_id_gen: gen_uuid,
+            include_meta: false,
+            repeat_last_response: false,
+        }
+    }
+
+    /// Load the recording to replay from a fixture file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(Recording::load(path)?))
+    }
+
+    /// Request ID generator to use. Defaults to UUID.
+    pub fn with_request_id_gen(mut self, id_gen: fn() -> String) -> Self {
+        self.request_id_gen = id_gen;
+        self
+    }
+
+    /// Must match the setting of the client the recording was made with.
+    pub fn with_include_meta(mut self, include_meta: bool) -> Self {
+        self.include_meta = include_meta;
+        self
+    }
+
+    /// Answer a request again with its last recorded response once all recorded responses to it
+    /// have been replayed, instead of failing. Defaults to false.
+    pub fn with_repeat_last_response(mut self, repeat: bool) -> Self {
+        self.repeat_last_response = repeat;
+        self
+    }
+
+    /// Check access to multiple resources
+    pub async fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> Result<CheckResourcesResponse> {
+        let req = check_resources_request(
+            String::new(),
+            self.include_meta,
+            principal,
+            resources,
+            aux_data,
+        );
+        let i = self.find(
+            |i| i.check_request.as_ref() == Some(&req),
+            || format!("CheckResources request {req:?}"),
+        )?;
+        let response = self.recording.interactions[i]
+            .check_response
+            .clone()
+            .unwrap_or_default();
+        Ok(CheckResourcesResponse {
+            response: CheckResourcesResponsePB {
+                request_id: (self.request_id_gen)(),
+                ..response
+            },
+        })
+    }
+
+    /// Produce a query plan for selecting resources that the principal can perform the given
+    /// action on.
+    pub async fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> Result<PlanResourcesResponse>
+    where
+        S: Into<String> + Clone,
+    {
+        self.plan(
+            PlanActions::Single(action.into()),
+            principal,
+            resource,
+            aux_data,
+        )
+    }
+
+    /// Produce a query plan for selecting resources that the principal can perform the given
+    /// actions on.
+    pub async fn plan_resources_for_actions<A, S>(
+        &mut self,
+        actions: A,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> Result<PlanResourcesResponse>
+    where
+        S: Into<String> + Clone,
+        A: IntoIterator<Item = S>,
+    {
+        let actions = PlanActions::Multiple(actions.into_iter().map(Into::into).collect());
+        self.plan(actions, principal, resource, aux_data)
+    }
+
+    fn plan(
+        &mut self,
+        actions: PlanActions,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> Result<PlanResourcesResponse> {
+        let req = plan_resources_request(
+            String::new(),
+            self.include_meta,
+            actions,
+            principal,
+            resource,
+            aux_data,
+        );
+        let i = self.find(
+            |i| i.plan_request.as_ref() == Some(&req),
+            || format!("PlanResources request {req:?}"),
+        )?;
+        let response = self.recording.interactions[i]
+            .plan_response
+            .clone()
+            .unwrap_or_default();
+        Ok(PlanResourcesResponse {
+            response: PlanResourcesResponsePB {
+                request_id: (self.request_id_gen)(),
+                ..response
+            },
+        })
+    }
+
+    /// Identical requests are answered in the order they were recorded. Once all of them have been
+    /// replayed, the last response is repeated if [`ReplayClient::with_repeat_last_response`] is
+    /// set.
+    fn find(
+        &mut self,
+        pred: impl Fn(&Interaction) -> bool,
+        request: impl FnOnce() -> String,
+    ) -> Result<usize> {
+        let matching: Vec<usize> = self
+            .recording
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| pred(i))
+            .map(|(n, _)| n)
+            .collect();
+        let Some(&last) = matching.last() else {
+            bail!("no recorded response for {}", request());
+        };
+        let i = match matching.into_iter().find(|&n| !self.replayed[n]) {
+            Some(i) => i,
+            None if self.repeat_last_response => last,
+            None => bail!("all recorded responses for {} were replayed", request()),
+        };
+        self.replayed[i] = true;
+        Ok(i)
+    }
+}
+
+impl Authorizer for RecordingClient {
+    fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> impl std::future::Future<Output = Result<CheckResourcesResponse>> + Send {
+        RecordingClient::check_resources(self, principal, resources, aux_data)
+    }
+
+    fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> impl std::future::Future<Output = Result<PlanResourcesResponse>> + Send
+    where
+        S: Into<String> + Clone + Send,
+    {
+        RecordingClient::plan_resources(self, action, principal, resource, aux_data)
+    }
+}
+
+impl Authorizer for ReplayClient {
+    fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> impl std::future::Future<Output = Result<CheckResourcesResponse>> + Send {
+        ReplayClient::check_resources(self, principal, resources, aux_data)
+    }
+
+    fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> impl std::future::Future<Output = Result<PlanResourcesResponse>> + Send
+    where
+        S: Into<String> + Clone + Send,
+    {
+        ReplayClient::plan_resources(self, action, principal, resource, aux_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genpb::cerbos::response::v1::check_resources_response::ResultEntry;
+    use crate::sdk::model::Resource;
+
+    #[tokio::test]
+    async fn test_replay() -> Result<()> {
+        let principal = Principal::new("alice", ["employee"]);
+        let resources = ResourceList::new().add(Resource::new("XX125", "leave_request"), ["view"]);
+        let req = check_resources_request(String::new(), false, principal.clone(), resources, None);
+        let recording = Recording {
+            interactions: vec![Interaction {
+                check_request: Some(req),
+                check_response: Some(CheckResourcesResponsePB {
+                    results: vec![ResultEntry {
+                        actions: [("view".to_string(), 1)].into(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+        };
+
+        let mut buf = Vec::new();
+        recording.write_to(&mut buf)?;
+        let mut client = ReplayClient::new(Recording::read_from(buf.as_slice())?)
+            .with_request_id_gen(|| "replayed".to_string());
+
+        let resp = client
+            .check_resources(
+                principal.clone(),
+                ResourceList::new().add(Resource::new("XX125", "leave_request"), ["view"]),
+                None,
+            )
+            .await?;
+        assert_eq!(resp.response.request_id, "replayed");
+        assert!(resp.iter().next().unwrap().is_allowed("view"));
+
+        let err = client
+            .check_resources(
+                principal.clone(),
+                ResourceList::new().add(Resource::new("XX125", "leave_request"), ["view"]),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("all recorded responses for"));
+
+        let err = client
+            .check_resources(
+                principal.clone(),
+                ResourceList::new().add(Resource::new("XX125", "leave_request"), ["delete"]),
+                None,
+            )
+            .await;
+        assert!(err.is_err());
+
+        let mut client = ReplayClient::new(recording).with_repeat_last_response(true);
+        for _ in 0..2 {
+            let resp = client
+                .check_resources(
+                    principal.clone(),
+                    ResourceList::new().add(Resource::new("XX125", "leave_request"), ["view"]),
+                    None,
+                )
+                .await?;
+            assert!(resp.iter().next().unwrap().is_allowed("view"));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,866 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Run Cerbos policy test suites against a PDP.
+//!
+//! Test suites are the `*_test.yaml` files of a policy repository. Each test table is expanded into
+//! `check_resources` calls, one per principal, and the effects and outputs returned by the PDP are
+//! compared with the expectations of the table.
+//!
+//! ```rust,no_run
+//! use cerbos::sdk::test_suite::{ensure_passed, TestRunner};
+//! use cerbos::sdk::{CerbosAsyncClient, CerbosClientOptions, CerbosEndpoint, Result};
+//!
+//! #[tokio::test]
+//! async fn policy_tests() -> Result<()> {
+//!     let opt = CerbosClientOptions::new(CerbosEndpoint::HostPort("localhost", 3593));
+//!     let client = CerbosAsyncClient::new(opt).await?;
+//!     let results = TestRunner::new(client).run_directory("policies").await?;
+//!     ensure_passed(&results)
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use walkdir::WalkDir;
+
+use crate::genpb::cerbos::{
+    effect::v1::Effect,
+    engine::v1::{
+        AuxData as AuxDataPB, OutputEntry, Principal as PrincipalPB, Resource as ResourcePB,
+    },
+    policy::v1::{
+        test_fixture, test_fixture_group,
+        test_results::{
+            details::Outcome, output_failure, Action, Details, Failure, OutputFailure,
+            Principal as PrincipalResult, Resource as ResourceResult, Result as TestResult,
+            Success, Suite, Summary, Tally, TestCase,
+        },
+        test_table::Expectation,
+        TestOptions,
+    },
+};
+use crate::sdk::deser::read_document;
+
+use super::authorizer::Authorizer;
+use super::model::{Principal, Resource, ResourceList};
+use super::Result;
+
+pub use crate::genpb::cerbos::policy::v1::{TestResults, TestSuite};
+
+const FIXTURES_DIR: &str = "testdata";
+const TEST_SUFFIX: &str = "_test";
+const EXTENSIONS: [&str; 3] = ["yaml", "yml", "json"];
+
+/// Read a test suite from a JSON or YAML document.
+pub fn read_test_suite(src: impl Read) -> Result<TestSuite> {
+    read_document(src)
+}
+
+/// Principals, resources and groups shared by the test suites of a directory, read from the
+/// `principals`, `resources` and `auxdata` files of its `testdata/` directory.
+#[derive(Debug, Clone, Default)]
+pub struct TestFixtures {
+    pub principals: HashMap<String, PrincipalPB>,
+    pub principal_groups: HashMap<String, test_fixture_group::Principals>,
+    pub resources: HashMap<String, ResourcePB>,
+    pub resource_groups: HashMap<String, test_fixture_group::Resources>,
+    pub aux_data: HashMap<String, AuxDataPB>,
+}
+
+impl TestFixtures {
+    /// Read the fixture files of `dir`. Missing files are treated as empty.
+    pub fn from_directory(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut fixtures = TestFixtures::default();
+        if let Some(p) = read_fixture::<test_fixture::Principals>(dir, "principals")? {
+            fixtures.principals = p.principals;
+            fixtures.principal_groups = p.principal_groups;
+        }
+        if let Some(r) = read_fixture::<test_fixture::Resources>(dir, "resources")? {
+            fixtures.resources = r.resources;
+            fixtures.resource_groups = r.resource_groups;
+        }
+        if let Some(a) = read_fixture::<test_fixture::AuxData>(dir, "auxdata")? {
+            fixtures.aux_data = a.aux_data;
+        }
+        Ok(fixtures)
+    }
+}
+
+fn read_fixture<T: serde::de::DeserializeOwned>(dir: &Path, name: &str) -> Result<Option<T>> {
+    for ext in EXTENSIONS {
+        let path = dir.join(format!("{name}.{ext}"));
+        if path.is_file() {
+            let file =
+                File::open(&path).with_context(|| format!("failed to open {}", path.display()))?;
+            let fixture = read_document(BufReader::new(file))
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            return Ok(Some(fixture));
+        }
+    }
+    Ok(None)
+}
+
+/// Runs test suites against an [`Authorizer`], usually a [`CerbosAsyncClient`] connected to a PDP
+/// that has the policies under test loaded.
+///
+/// The PDP evaluates requests at the current time with its own configuration, so test options
+/// that change the engine (`now`, `globals` and `lenientScopeSearch`) and tests with auxiliary
+/// data cannot be run and are reported as errored. `defaultPolicyVersion` is applied to the
+/// principals and resources that do not set a policy version.
+///
+/// [`CerbosAsyncClient`]: super::CerbosAsyncClient
+pub struct TestRunner<A> {
+    authorizer: A,
+}
+
+impl<A: Authorizer> TestRunner<A> {
+    pub fn new(authorizer: A) -> Self {
+        Self { authorizer }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.authorizer
+    }
+
+    /// Run every test suite under `dir`. Suites are the YAML and JSON files whose name ends with
+    /// `_test`, and their fixtures are read from the `testdata/` directory next to them. Suites
+    /// that cannot be read are reported as errored rather than failing the whole run.
+    pub async fn run_directory(&mut self, dir: impl AsRef<Path>) -> Result<TestResults> {
+        let dir = dir.as_ref();
+        let mut suites = Vec::new();
+        let walker = WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'));
+        for entry in walker {
+            let entry = entry.with_context(|| format!("failed to walk {}", dir.display()))?;
+            let path = entry.path();
+            if entry.file_type().is_file() && is_test_suite(path) {
+                let mut suite = self.run_file(path).await;
+                if let Ok(rel) = path.strip_prefix(dir) {
+                    suite.file = to_slash(rel);
+                }
+                suites.push(suite);
+            }
+        }
+        Ok(test_results(suites))
+    }
+
+    /// Run the test suite in `path` with the fixtures from the `testdata/` directory next to it.
+    pub async fn run_file(&mut self, path: impl AsRef<Path>) -> Suite {
+        let path = path.as_ref();
+        let loaded = (|| {
+            let file =
+                File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+            let suite = read_test_suite(BufReader::new(file))?;
+            let fixtures = match path.parent() {
+                Some(parent) => TestFixtures::from_directory(parent.join(FIXTURES_DIR))?,
+                None => TestFixtures::default(),
+            };
+            anyhow::Ok((suite, fixtures))
+        })();
+
+        let mut suite = match loaded {
+            Ok((suite, fixtures)) => self.run_suite(&suite, &fixtures).await,
+            Err(err) => Suite {
+                error: format!("{err:#}"),
+                summary: Some(empty_summary(TestResult::Errored)),
+                ..Default::default()
+            },
+        };
+        suite.file = path.display().to_string();
+        suite
+    }
+
+    /// Run a test suite. Principals and resources of the suite take precedence over fixtures with
+    /// the same name.
+    pub async fn run_suite(&mut self, suite: &TestSuite, fixtures: &TestFixtures) -> Suite {
+        let mut result = Suite {
+            name: suite.name.clone(),
+            description: suite.description.clone(),
+            ..Default::default()
+        };
+        if suite.skip {
+            result.skip_reason = suite.skip_reason.clone();
+            result.summary = Some(empty_summary(TestResult::Skipped));
+            return result;
+        }
+
+        let fixtures = Fixtures { suite, fixtures };
+        let tables: Result<Vec<_>> = suite
+            .tests
+            .iter()
+            .map(|table| {
+                let input = table.input.clone().unwrap_or_default();
+                let principals =
+                    fixtures.principal_keys(&input.principals, &input.principal_groups)?;
+                let resources = fixtures.resource_keys(&input.resources, &input.resource_groups)?;
+                Ok((table, input.actions, input.aux_data, principals, resources))
+            })
+            .collect();
+        let tables = match tables.with_context(|| format!("invalid test suite {:?}", suite.name)) {
+            Ok(tables) => tables,
+            Err(err) => {
+                result.error = format!("{err:#}");
+                result.summary = Some(empty_summary(TestResult::Errored));
+                return result;
+            }
+        };
+
+        for (table, actions, aux_data, principals, resources) in tables {
+            let mut test_case = TestCase {
+                name: table.name.clone(),
+                ..Default::default()
+            };
+            let options = table.options.as_ref().or(suite.options.as_ref());
+            let not_runnable = if table.skip {
+                Some((
+                    TestResult::Skipped,
+                    Outcome::SkipReason(table.skip_reason.clone()),
+                ))
+            } else {
+                unsupported(&aux_data, options)
+                    .map(|reason| (TestResult::Errored, Outcome::Error(reason)))
+            };
+            let version = options.map_or("", |o| o.default_policy_version.as_str());
+
+            for principal_key in &principals {
+                let checked = match &not_runnable {
+                    Some(_) => Ok(Vec::new()),
+                    None => {
+                        self.check(principal_key, &resources, &actions, version, &fixtures)
+                            .await
+                    }
+                };
+                let mut principal_result = PrincipalResult {
+                    name: principal_key.clone(),
+                    ..Default::default()
+                };
+                for (i, resource_key) in resources.iter().enumerate() {
+                    let expectation =
+                        expectation_for(&table.expected, principal_key, resource_key, &fixtures);
+                    let actions = actions.iter().map(|action| {
+                        let (result, outcome) = match (&not_runnable, &checked) {
+                            (Some((result, outcome)), _) => (*result, outcome.clone()),
+                            (None, Ok(actual)) => match actual.get(i) {
+                                Some((effects, outputs)) => {
+                                    compare(expectation, action, effects, outputs)
+                                }
+                                None => (
+                                    TestResult::Errored,
+                                    Outcome::Error("no result for resource".to_string()),
+                                ),
+                            },
+                            (None, Err(err)) => (TestResult::Errored, Outcome::Error(err.clone())),
+                        };
+                        action_result(action, result, outcome)
+                    });
+                    principal_result.resources.push(ResourceResult {
+                        name: resource_key.clone(),
+                        actions: actions.collect(),
+                    });
+                }
+                test_case.principals.push(principal_result);
+            }
+            result.test_cases.push(test_case);
+        }
+        result.summary = Some(summary(results(&result.test_cases)));
+        result
+    }
+
+    // Checks all resources of a table for one principal. Results are returned in the order of
+    // `resource_keys`.
+    async fn check(
+        &mut self,
+        principal_key: &str,
+        resource_keys: &[String],
+        actions: &[String],
+        version: &str,
+        fixtures: &Fixtures<'_>,
+    ) -> std::result::Result<Vec<(HashMap<String, i32>, Vec<OutputEntry>)>, String> {
+        let mut principal = fixtures
+            .principal(principal_key)
+            .ok_or_else(|| format!("principal {principal_key:?} not found"))?;
+        if principal.policy_version.is_empty() {
+            principal.policy_version = version.to_string();
+        }
+
+        let mut resources = ResourceList::new();
+        for key in resource_keys {
+            let mut resource = fixtures
+                .resource(key)
+                .ok_or_else(|| format!("resource {key:?} not found"))?;
+            if resource.policy_version.is_empty() {
+                resource.policy_version = version.to_string();
+            }
+            resources = resources.add(Resource { resource }, actions.iter().cloned());
+        }
+
+        let resp = self
+            .authorizer
+            .check_resources(Principal { principal }, resources, None)
+            .await
+            .map_err(|err| format!("{err:#}"))?;
+        Ok(resp
+            .response
+            .results
+            .into_iter()
+            .map(|r| (r.actions, r.outputs))
+            .collect())
+    }
+}
+
+/// Fail with a description of every test that did not pass. Use it to turn a test run into the
+/// result of a `#[test]` function.
+pub fn ensure_passed(results: &TestResults) -> Result<()> {
+    let mut failures = Vec::new();
+    for suite in &results.suites {
+        if !suite.error.is_empty() {
+            failures.push(format!("{}: {}", suite.file, suite.error));
+        }
+        for test_case in &suite.test_cases {
+            for principal in &test_case.principals {
+                for resource in &principal.resources {
+                    for action in &resource.actions {
+                        let Some(details) = &action.details else {
+                            continue;
+                        };
+                        let problem = match &details.outcome {
+                            Some(Outcome::Failure(failure)) => describe_failure(failure),
+                            Some(Outcome::Error(err)) => err.clone(),
+                            _ => continue,
+                        };
+                        failures.push(format!(
+                            "{} > {} > {} > {} > {}: {problem}",
+                            suite.name, test_case.name, principal.name, resource.name, action.name
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    if !failures.is_empty() {
+        bail!(
+            "{} policy tests did not pass:\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+    Ok(())
+}
+
+// Lookup of principals, resources and groups, with the suite taking precedence over fixtures.
+struct Fixtures<'a> {
+    suite: &'a TestSuite,
+    fixtures: &'a TestFixtures,
+}
+
+impl Fixtures<'_> {
+    fn principal(&self, key: &str) -> Option<PrincipalPB> {
+        self.suite
+            .principals
+            .get(key)
+            .or_else(|| self.fixtures.principals.get(key))
+            .cloned()
+    }
+
+    fn resource(&self, key: &str) -> Option<ResourcePB> {
+        self.suite
+            .resources
+            .get(key)
+            .or_else(|| self.fixtures.resources.get(key))
+            .cloned()
+    }
+
+    fn principal_group(&self, group: &str) -> Option<&[String]> {
+        self.suite
+            .principal_groups
+            .get(group)
+            .or_else(|| self.fixtures.principal_groups.get(group))
+            .map(|g| g.principals.as_slice())
+    }
+
+    fn resource_group(&self, group: &str) -> Option<&[String]> {
+        self.suite
+            .resource_groups
+            .get(group)
+            .or_else(|| self.fixtures.resource_groups.get(group))
+            .map(|g| g.resources.as_slice())
+    }
+
+    fn principal_keys(&self, keys: &[String], groups: &[String]) -> Result<Vec<String>> {
+        expand(keys, groups, |g| self.principal_group(g), "principal")
+    }
+
+    fn resource_keys(&self, keys: &[String], groups: &[String]) -> Result<Vec<String>> {
+        expand(keys, groups, |g| self.resource_group(g), "resource")
+    }
+}
+
+fn expand<'a>(
+    keys: &[String],
+    groups: &[String],
+    group: impl Fn(&str) -> Option<&'a [String]>,
+    kind: &str,
+) -> Result<Vec<String>> {
+    let mut expanded = keys.to_vec();
+    for name in groups {
+        let Some(members) = group(name) else {
+            bail!("{kind} group {name:?} not found");
+        };
+        expanded.extend(members.iter().cloned());
+    }
+    let mut seen = std::collections::HashSet::new();
+    expanded.retain(|k| seen.insert(k.clone()));
+    Ok(expanded)
+}
+
+fn expectation_for<'a>(
+    expected: &'a [Expectation],
+    principal: &str,
+    resource: &str,
+    fixtures: &Fixtures<'_>,
+) -> Option<&'a Expectation> {
+    let covers =
+        |keys: Result<Vec<String>>, key: &str| keys.is_ok_and(|k| k.iter().any(|k| k == key));
+    expected.iter().find(|e| {
+        let principals = [e.principal.clone()]
+            .into_iter()
+            .chain(e.principals.iter().cloned());
+        let resources = [e.resource.clone()]
+            .into_iter()
+            .chain(e.resources.iter().cloned());
+        covers(
+            fixtures.principal_keys(&principals.collect::<Vec<_>>(), &e.principal_groups),
+            principal,
+        ) && covers(
+            fixtures.resource_keys(&resources.collect::<Vec<_>>(), &e.resource_groups),
+            resource,
+        )
+    })
+}
+
+fn unsupported(aux_data: &str, options: Option<&TestOptions>) -> Option<String> {
+    let option = options.and_then(|o| {
+        if o.now.is_some() {
+            Some("now")
+        } else if !o.globals.is_empty() {
+            Some("globals")
+        } else if o.lenient_scope_search {
+            Some("lenientScopeSearch")
+        } else {
+            None
+        }
+    });
+    match option {
+        Some(option) => Some(format!("option {option} cannot be applied to a PDP")),
+        None if !aux_data.is_empty() => {
+            Some("auxiliary data cannot be sent to a PDP without a JWT".to_string())
+        }
+        None => None,
+    }
+}
+
+// Actions without an expected effect are expected to be denied.
+fn compare(
+    expectation: Option<&Expectation>,
+    action: &str,
+    effects: &HashMap<String, i32>,
+    outputs: &[OutputEntry],
+) -> (TestResult, Outcome) {
+    let expected = expectation
+        .and_then(|e| e.actions.get(action).copied())
+        .unwrap_or(Effect::Deny as i32);
+    let actual = effects
+        .get(action)
+        .copied()
+        .unwrap_or(Effect::Unspecified as i32);
+
+    let mut output_failures = Vec::new();
+    let expected_outputs = expectation
+        .into_iter()
+        .flat_map(|e| &e.outputs)
+        .filter(|o| o.action == action)
+        .flat_map(|o| &o.expected);
+    for entry in expected_outputs {
+        let outcome = match outputs.iter().find(|o| o.src == entry.src) {
+            Some(found) if found.val == entry.val => continue,
+            Some(found) => output_failure::Outcome::Mismatched(output_failure::MismatchedValue {
+                expected: entry.val.clone(),
+                actual: found.val.clone(),
+            }),
+            None => output_failure::Outcome::Missing(output_failure::MissingValue {
+                expected: entry.val.clone(),
+            }),
+        };
+        output_failures.push(OutputFailure {
+            src: entry.src.clone(),
+            outcome: Some(outcome),
+        });
+    }
+
+    if expected == actual && output_failures.is_empty() {
+        let outputs = outputs.to_vec();
+        (
+            TestResult::Passed,
+            Outcome::Success(Success {
+                effect: actual,
+                outputs,
+            }),
+        )
+    } else {
+        (
+            TestResult::Failed,
+            Outcome::Failure(Failure {
+                expected,
+                actual,
+                outputs: output_failures,
+            }),
+        )
+    }
+}
+
+fn describe_failure(failure: &Failure) -> String {
+    let effect = |e: i32| Effect::try_from(e).map_or("UNKNOWN", |e| e.as_str_name());
+    let value = |v: &Option<crate::genpb::google::protobuf::Value>| {
+        v.as_ref()
+            .and_then(|v| serde_json::to_string(v).ok())
+            .unwrap_or_else(|| "null".to_string())
+    };
+    let mut problems = Vec::new();
+    if failure.expected != failure.actual {
+        problems.push(format!(
+            "expected {}, got {}",
+            effect(failure.expected),
+            effect(failure.actual)
+        ));
+    }
+    for output in &failure.outputs {
+        problems.push(match &output.outcome {
+            Some(output_failure::Outcome::Mismatched(m)) => format!(
+                "output {}: expected {}, got {}",
+                output.src,
+                value(&m.expected),
+                value(&m.actual)
+            ),
+            Some(output_failure::Outcome::Missing(m)) => {
+                format!(
+                    "output {}: missing, expected {}",
+                    output.src,
+                    value(&m.expected)
+                )
+            }
+            None => format!("output {}", output.src),
+        });
+    }
+    problems.join("; ")
+}
+
+fn action_result(action: &str, result: TestResult, outcome: Outcome) -> Action {
+    Action {
+        name: action.to_string(),
+        details: Some(Details {
+            result: result as i32,
+            outcome: Some(outcome),
+            ..Default::default()
+        }),
+    }
+}
+
+fn results(test_cases: &[TestCase]) -> impl Iterator<Item = TestResult> + '_ {
+    test_cases
+        .iter()
+        .flat_map(|t| &t.principals)
+        .flat_map(|p| &p.resources)
+        .flat_map(|r| &r.actions)
+        .filter_map(|a| a.details.as_ref())
+        .map(|d| TestResult::try_from(d.result).unwrap_or(TestResult::Unspecified))
+}
+
+// The overall result is the worst of the individual results, in the order skipped, passed,
+// failed and errored.
+fn summary(results: impl IntoIterator<Item = TestResult>) -> Summary {
+    let mut counts: Vec<Tally> = Vec::new();
+    let mut overall = TestResult::Unspecified;
+    let mut tests_count = 0;
+    for result in results {
+        overall = overall.max(result);
+        tests_count += 1;
+        match counts.iter_mut().find(|t| t.result == result as i32) {
+            Some(tally) => tally.count += 1,
+            None => counts.push(Tally {
+                result: result as i32,
+                count: 1,
+            }),
+        }
+    }
+    counts.sort_by_key(|t| t.result);
+    Summary {
+        overall_result: overall as i32,
+        tests_count,
+        result_counts: counts,
+    }
+}
+
+fn empty_summary(result: TestResult) -> Summary {
+    Summary {
+        overall_result: result as i32,
+        ..Default::default()
+    }
+}
+
+fn test_results(suites: Vec<Suite>) -> TestResults {
+    let mut overall = TestResult::Unspecified;
+    let mut tests_count = 0;
+    let mut counts: Vec<Tally> = Vec::new();
+    for s in suites.iter().filter_map(|s| s.summary.as_ref()) {
+        overall = overall.max(TestResult::try_from(s.overall_result).unwrap_or_default());
+        tests_count += s.tests_count;
+        for tally in &s.result_counts {
+            match counts.iter_mut().find(|t| t.result == tally.result) {
+                Some(t) => t.count += tally.count,
+                None => counts.push(*tally),
+            }
+        }
+    }
+    counts.sort_by_key(|t| t.result);
+    TestResults {
+        suites,
+        summary: Some(Summary {
+            overall_result: overall as i32,
+            tests_count,
+            result_counts: counts,
+        }),
+    }
+}
+
+fn is_test_suite(path: &Path) -> bool {
+    let is_test = path
+        .file_stem()
+        .is_some_and(|s| s.to_string_lossy().ends_with(TEST_SUFFIX));
+    let has_extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.contains(&e));
+    is_test && has_extension
+}
+
+fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genpb::cerbos::response::v1::{
+        check_resources_response::ResultEntry, CheckResourcesResponse as CheckResourcesResponsePB,
+    };
+    use crate::genpb::google::protobuf::{value::Kind, Value};
+    use crate::sdk::model::{AuxData, CheckResourcesResponse, PlanResourcesResponse, ResourceKind};
+
+    // Allows everything for admins and outputs the resource ID.
+    struct AdminOnly;
+
+    impl Authorizer for AdminOnly {
+        async fn check_resources(
+            &mut self,
+            principal: Principal,
+            resources: ResourceList,
+            _aux_data: Option<AuxData>,
+        ) -> Result<CheckResourcesResponse> {
+            let effect = if principal.principal.roles.iter().any(|r| r == "admin") {
+                Effect::Allow
+            } else {
+                Effect::Deny
+            };
+            let results = resources
+                .resources
+                .into_iter()
+                .map(|entry| ResultEntry {
+                    actions: entry
+                        .actions
+                        .into_iter()
+                        .map(|a| (a, effect as i32))
+                        .collect(),
+                    outputs: vec![OutputEntry {
+                        src: "id".to_string(),
+                        val: Some(Value {
+                            kind: Some(Kind::StringValue(entry.resource.unwrap().id)),
+                        }),
+                    }],
+                    ..Default::default()
+                })
+                .collect();
+            Ok(CheckResourcesResponse {
+                response: CheckResourcesResponsePB {
+                    results,
+                    ..Default::default()
+                },
+            })
+        }
+
+        async fn plan_resources<S>(
+            &mut self,
+            _action: S,
+            _principal: Principal,
+            _resource: ResourceKind,
+            _aux_data: Option<AuxData>,
+        ) -> Result<PlanResourcesResponse>
+        where
+            S: Into<String> + Clone + Send,
+        {
+            bail!("not implemented")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_suite() -> Result<()> {
+        let suite = read_test_suite(
+            r#"
+name: documents
+principals:
+  alice: {id: alice, roles: [admin]}
+principalGroups:
+  users: {principals: [bob]}
+resources:
+  doc1: {id: doc1, kind: document}
+tests:
+  - name: admins and users
+    input:
+      principals: [alice]
+      principalGroups: [users]
+      resources: [doc1, doc2]
+      actions: [view, delete]
+    expected:
+      - principal: alice
+        resources: [doc1, doc2]
+        actions: {view: EFFECT_ALLOW, delete: EFFECT_ALLOW}
+        outputs:
+          - action: view
+            expected:
+              - {src: id, val: doc1}
+      - principalGroups: [users]
+        resource: doc1
+        actions: {view: EFFECT_ALLOW}
+  - name: frozen time
+    options:
+      now: "2022-08-02T15:00:00Z"
+    input: {principals: [alice], resources: [doc1], actions: [view]}
+    expected: []
+  - name: skipped
+    skip: true
+    skipReason: not ready
+    input: {principals: [alice], resources: [doc1], actions: [view]}
+"#
+            .as_bytes(),
+        )?;
+        assert_eq!(
+            suite.tests[1]
+                .options
+                .as_ref()
+                .unwrap()
+                .now
+                .unwrap()
+                .seconds,
+            1659452400
+        );
+
+        let fixtures = TestFixtures {
+            principals: [(
+                "bob".to_string(),
+                PrincipalPB {
+                    id: "bob".to_string(),
+                    roles: vec!["user".to_string()],
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            resources: [(
+                "doc2".to_string(),
+                ResourcePB {
+                    id: "doc2".to_string(),
+                    kind: "document".to_string(),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let result = TestRunner::new(AdminOnly)
+            .run_suite(&suite, &fixtures)
+            .await;
+
+        let outcome = |table: usize, principal: usize, resource: usize, action: usize| {
+            let details = result.test_cases[table].principals[principal].resources[resource]
+                .actions[action]
+                .details
+                .as_ref()
+                .unwrap();
+            (
+                TestResult::try_from(details.result).unwrap(),
+                details.outcome.clone().unwrap(),
+            )
+        };
+        // The output of doc2 is not the expected doc1.
+        assert_eq!(outcome(0, 0, 0, 0).0, TestResult::Passed);
+        assert!(matches!(
+            outcome(0, 0, 1, 0),
+            (TestResult::Failed, Outcome::Failure(Failure { expected, actual, outputs }))
+                if expected == actual && outputs.len() == 1
+        ));
+        assert_eq!(outcome(0, 0, 1, 1).0, TestResult::Passed);
+        // Bob is denied, which is expected for the unlisted delete action.
+        assert!(matches!(
+            outcome(0, 1, 0, 0),
+            (
+                TestResult::Failed,
+                Outcome::Failure(Failure {
+                    expected: 1,
+                    actual: 2,
+                    ..
+                })
+            )
+        ));
+        assert_eq!(outcome(0, 1, 0, 1).0, TestResult::Passed);
+        assert_eq!(outcome(0, 1, 1, 0).0, TestResult::Passed);
+        assert!(matches!(
+            outcome(1, 0, 0, 0),
+            (TestResult::Errored, Outcome::Error(err)) if err.contains("now")
+        ));
+        assert_eq!(
+            outcome(2, 0, 0, 0),
+            (
+                TestResult::Skipped,
+                Outcome::SkipReason("not ready".to_string())
+            )
+        );
+
+        let results = test_results(vec![result]);
+        let summary = results.summary.as_ref().unwrap();
+        assert_eq!(summary.overall_result, TestResult::Errored as i32);
+        assert_eq!(summary.tests_count, 10);
+        let counts: Vec<_> = summary
+            .result_counts
+            .iter()
+            .map(|t| (t.result, t.count))
+            .collect();
+        assert_eq!(counts, [(1, 1), (2, 6), (3, 2), (4, 1)]);
+
+        let err = ensure_passed(&results).unwrap_err().to_string();
+        assert!(err.starts_with("3 policy tests did not pass"));
+        assert!(err.contains(
+            "documents > admins and users > bob > doc1 > view: expected EFFECT_ALLOW, got EFFECT_DENY"
+        ));
+        assert!(err.contains(r#"output id: expected "doc1", got "doc2""#));
+        Ok(())
+    }
+}
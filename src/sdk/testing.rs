@@ -0,0 +1,310 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Test doubles for code that depends on [`Authorizer`].
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::genpb::cerbos::{
+    effect::v1::Effect,
+    engine::v1::{plan_resources_filter::Kind, PlanResourcesFilter},
+    response::v1::{
+        check_resources_response::{result_entry::Resource as ResultResource, ResultEntry},
+        CheckResourcesResponse as CheckResourcesResponsePB,
+        PlanResourcesResponse as PlanResourcesResponsePB,
+    },
+};
+
+use super::authorizer::Authorizer;
+use super::model::{
+    AuxData, CheckResourcesResponse, PlanResourcesResponse, Principal, Resource, ResourceKind,
+    ResourceList,
+};
+use super::Result;
+
+const WILDCARD: &str = "*";
+
+/// A row in the rule table of a [`FakeAuthorizer`]. `*` matches any role, action or kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeRule {
+    pub role: String,
+    pub action: String,
+    pub kind: String,
+    pub effect: Effect,
+}
+
+impl FakeRule {
+    fn matches(&self, roles: &[String], action: &str, kind: &str) -> bool {
+        (self.role == WILDCARD || roles.contains(&self.role))
+            && (self.action == WILDCARD || self.action == action)
+            && (self.kind == WILDCARD || self.kind == kind)
+    }
+}
+
+/// A call received by a [`FakeAuthorizer`].
+#[derive(Debug, Clone)]
+pub enum FakeCall {
+    CheckResources {
+        principal: Principal,
+        resources: Vec<(Resource, Vec<String>)>,
+        aux_data: Option<AuxData>,
+    },
+    PlanResources {
+        action: String,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    },
+}
+
+/// In-memory [`Authorizer`] driven by a table of role/action/kind rules.
+///
+/// An action is allowed if at least one `allow` rule matches one of the principal's roles and no
+/// `deny` rule does. Everything else is denied. Clones share the log of received calls, so a clone
+/// can be handed to the code under test while the original is used for assertions.
+///
+/// ```rust
+/// use cerbos::sdk::testing::FakeAuthorizer;
+///
+/// let authz = FakeAuthorizer::new()
+///     .allow("employee", "view", "leave_request")
+///     .allow("admin", "*", "*")
+///     .deny("*", "delete", "leave_request");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FakeAuthorizer {
+    rules: Vec<FakeRule>,
+    calls: Arc<Mutex<Vec<FakeCall>>>,
+}
+
+impl FakeAuthorizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow principals with `role` to perform `action` on resources of `kind`.
+    pub fn allow(
+        self,
+        role: impl Into<String>,
+        action: impl Into<String>,
+        kind: impl Into<String>,
+    ) -> Self {
+        self.with_rule(FakeRule {
+            role: role.into(),
+            action: action.into(),
+            kind: kind.into(),
+            effect: Effect::Allow,
+        })
+    }
+
+    /// Deny principals with `role` from performing `action` on resources of `kind`. Denials take
+    /// precedence over allows.
+    pub fn deny(
+        self,
+        role: impl Into<String>,
+        action: impl Into<String>,
+        kind: impl Into<String>,
+    ) -> Self {
+        self.with_rule(FakeRule {
+            role: role.into(),
+            action: action.into(),
+            kind: kind.into(),
+            effect: Effect::Deny,
+        })
+    }
+
+    pub fn with_rule(mut self, rule: FakeRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_rules(mut self, rules: impl IntoIterator<Item = FakeRule>) -> Self {
+        self.rules.extend(rules);
+        self
+    }
+
+    /// Calls received so far, in order.
+    pub fn calls(&self) -> Vec<FakeCall> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Forget the calls received so far.
+    pub fn clear_calls(&self) {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    fn effect(&self, roles: &[String], action: &str, kind: &str) -> Effect {
+        let mut effect = Effect::Deny;
+        for rule in self.rules.iter().filter(|r| r.matches(roles, action, kind)) {
+            if rule.effect == Effect::Deny {
+                return Effect::Deny;
+            }
+            effect = rule.effect;
+        }
+        effect
+    }
+
+    fn record(&self, call: FakeCall) {
+        self.calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(call);
+    }
+}
+
+impl Authorizer for FakeAuthorizer {
+    fn check_resources(
+        &mut self,
+        principal: Principal,
+        resources: ResourceList,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<CheckResourcesResponse>> + Send {
+        let results = resources
+            .resources
+            .iter()
+            .map(|entry| {
+                let resource = entry.resource.clone().unwrap_or_default();
+                let actions = entry
+                    .actions
+                    .iter()
+                    .map(|a| {
+                        let effect = self.effect(&principal.principal.roles, a, &resource.kind);
+                        (a.clone(), effect as i32)
+                    })
+                    .collect();
+                ResultEntry {
+                    resource: Some(ResultResource {
+                        id: resource.id,
+                        kind: resource.kind,
+                        policy_version: resource.policy_version,
+                        scope: resource.scope,
+                    }),
+                    actions,
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        self.record(FakeCall::CheckResources {
+            principal,
+            resources: resources
+                .resources
+                .into_iter()
+                .map(|entry| {
+                    let resource = Resource {
+                        resource: entry.resource.unwrap_or_default(),
+                    };
+                    (resource, entry.actions)
+                })
+                .collect(),
+            aux_data,
+        });
+
+        let response = CheckResourcesResponse {
+            response: CheckResourcesResponsePB {
+                results,
+                ..Default::default()
+            },
+        };
+        async move { Ok(response) }
+    }
+
+    /// Returns an unconditional plan: always allowed if the rule table allows the action, always
+    /// denied otherwise.
+    fn plan_resources<S>(
+        &mut self,
+        action: S,
+        principal: Principal,
+        resource: ResourceKind,
+        aux_data: Option<AuxData>,
+    ) -> impl Future<Output = Result<PlanResourcesResponse>> + Send
+    where
+        S: Into<String> + Clone + Send,
+    {
+        let action: String = action.into();
+        let kind = match self.effect(&principal.principal.roles, &action, &resource.resource.kind) {
+            Effect::Allow => Kind::AlwaysAllowed,
+            _ => Kind::AlwaysDenied,
+        };
+
+        #[allow(deprecated)]
+        let response = PlanResourcesResponse {
+            response: PlanResourcesResponsePB {
+                action: action.clone(),
+                actions: vec![action.clone()],
+                resource_kind: resource.resource.kind.clone(),
+                policy_version: resource.resource.policy_version.clone(),
+                filter: Some(PlanResourcesFilter {
+                    kind: kind as i32,
+                    condition: None,
+                }),
+                ..Default::default()
+            },
+        };
+
+        self.record(FakeCall::PlanResources {
+            action,
+            principal,
+            resource,
+            aux_data,
+        });
+        async move { Ok(response) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdk::model::PlanResourcesFilter as Filter;
+
+    #[tokio::test]
+    async fn test_fake_authorizer() -> Result<()> {
+        let mut authz = FakeAuthorizer::new()
+            .allow("employee", "view", "leave_request")
+            .allow("admin", "*", "*")
+            .deny("*", "delete", "leave_request");
+        let observer = authz.clone();
+
+        let employee = Principal::new("alice", ["employee"]);
+        let admin = Principal::new("bob", ["admin"]);
+        let resource = Resource::new("XX125", "leave_request");
+
+        assert!(
+            authz
+                .is_allowed("view", employee.clone(), resource.clone(), None)
+                .await?
+        );
+        assert!(
+            !authz
+                .is_allowed("approve", employee.clone(), resource.clone(), None)
+                .await?
+        );
+        assert!(
+            authz
+                .is_allowed("approve", admin.clone(), resource.clone(), None)
+                .await?
+        );
+        assert!(
+            !authz
+                .is_allowed("delete", admin.clone(), resource, None)
+                .await?
+        );
+
+        let plan = authz
+            .plan_resources("view", employee, ResourceKind::new("leave_request"), None)
+            .await?;
+        assert!(matches!(plan.filter()?, Filter::AlwaysAllowed));
+
+        let calls = observer.calls();
+        assert_eq!(calls.len(), 5);
+        assert!(matches!(
+            &calls[0],
+            FakeCall::CheckResources { principal, resources, .. }
+                if principal.id() == "alice" && resources[0].0.id() == "XX125" && resources[0].1 == ["view"]
+        ));
+        assert!(matches!(&calls[4], FakeCall::PlanResources { action, .. } if action == "view"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Client-side validation of principal and resource attributes against the JSON schemas referenced
+//! by resource policies.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+use jsonschema::{Resource as SchemaResource, Validator};
+use thiserror::Error;
+
+use crate::genpb::cerbos::{
+    policy::v1::{policy::PolicyType, schemas},
+    schema::v1::{validation_error::Source, ValidationError},
+};
+
+use super::admin::model::{PolicySet, SchemaSet};
+use super::model::{Principal, Resource, ResourceList};
+
+const SCHEMA_URL_PREFIX: &str = "cerbos:///";
+const DEFAULT_VERSION: &str = "default";
+
+/// Attributes that do not conform to their schemas. Paths are JSON pointers into the principal or
+/// resource attributes.
+#[derive(Error, Debug, Clone)]
+#[error("{}", Violations(.errors))]
+pub struct SchemaValidationError {
+    pub errors: Vec<ValidationError>,
+}
+
+struct Violations<'a>(&'a [ValidationError]);
+
+impl fmt::Display for Violations<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} schema violation(s)", self.0.len())?;
+        for e in self.0 {
+            let source = match e.source() {
+                Source::Principal => "principal",
+                Source::Resource => "resource",
+                Source::Unspecified => "attributes",
+            };
+            write!(f, "; {source} {}: {}", e.path, e.message)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SchemaRef {
+    id: String,
+    ignore_actions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PolicySchemas {
+    principal: Option<SchemaRef>,
+    resource: Option<SchemaRef>,
+}
+
+/// Validates principal and resource attributes the way the PDP does when schema enforcement is
+/// enabled, so that mistakes surface before a request is sent.
+///
+/// Schemas are looked up from the resource policy for the resource kind, version and scope,
+/// falling back to parent scopes.
+///
+/// ```rust,no_run
+/// use cerbos::sdk::admin::model::PolicySet;
+/// use cerbos::sdk::model::{Principal, Resource, ResourceList};
+/// use cerbos::sdk::validation::SchemaValidator;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let (policies, schemas) = PolicySet::from_directory("policies")?;
+/// let validator = SchemaValidator::new(&schemas)?.with_policies(&policies)?;
+///
+/// let principal = Principal::new("alice", ["employee"]).add_attr("department", "marketing");
+/// let resources = ResourceList::new().add(Resource::new("XX125", "leave_request"), ["view"]);
+/// if cfg!(debug_assertions) {
+///     validator.validate_resources(&principal, &resources)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct SchemaValidator {
+    validators: HashMap<String, Validator>,
+    policies: HashMap<(String, String, String), PolicySchemas>,
+}
+
+impl SchemaValidator {
+    /// Compile all schemas of the set. Schemas can reference each other as `cerbos:///<id>`.
+    pub fn new(schemas: &SchemaSet) -> Result<Self> {
+        let mut documents = Vec::new();
+        for schema in schemas.get_schemas() {
+            let document: serde_json::Value = serde_json::from_slice(&schema.definition)
+                .with_context(|| format!("schema {} is not valid JSON", schema.id))?;
+            documents.push((schema.id.clone(), document));
+        }
+
+        let mut validators = HashMap::new();
+        for (id, document) in &documents {
+            let mut options = jsonschema::options();
+            for (other_id, other) in &documents {
+                if other_id != id {
+                    let resource = SchemaResource::from_contents(other.clone())
+                        .with_context(|| format!("failed to load schema {other_id}"))?;
+                    options =
+                        options.with_resource(format!("{SCHEMA_URL_PREFIX}{other_id}"), resource);
+                }
+            }
+            let validator = options
+                .with_base_uri(format!("{SCHEMA_URL_PREFIX}{id}"))
+                .build(document)
+                .map_err(|e| anyhow::anyhow!("failed to compile schema {id}: {e}"))?;
+            validators.insert(id.clone(), validator);
+        }
+
+        Ok(Self {
+            validators,
+            policies: HashMap::new(),
+        })
+    }
+
+    /// Learn which schemas apply to which resources from the `schemas` section of resource
+    /// policies.
+    pub fn with_policies(mut self, policies: &PolicySet) -> Result<Self> {
+        for policy in policies.get_policies() {
+            let Some(PolicyType::ResourcePolicy(p)) = &policy.policy_type else {
+                continue;
+            };
+            let Some(schemas) = &p.schemas else {
+                continue;
+            };
+            let entry = PolicySchemas {
+                principal: self.schema_ref(schemas.principal_schema.as_ref())?,
+                resource: self.schema_ref(schemas.resource_schema.as_ref())?,
+            };
+            let version = if p.version.is_empty() {
+                DEFAULT_VERSION
+            } else {
+                &p.version
+            };
+            self.policies.insert(
+                (p.resource.clone(), version.to_string(), p.scope.clone()),
+                entry,
+            );
+        }
+        Ok(self)
+    }
+
+    fn schema_ref(&self, schema: Option<&schemas::Schema>) -> Result<Option<SchemaRef>> {
+        let Some(schema) = schema else {
+            return Ok(None);
+        };
+        let id = schema
+            .r#ref
+            .strip_prefix(SCHEMA_URL_PREFIX)
+            .unwrap_or(&schema.r#ref);
+        if !self.validators.contains_key(id) {
+            bail!("schema {} is referenced but not loaded", schema.r#ref);
+        }
+        Ok(Some(SchemaRef {
+            id: id.to_string(),
+            ignore_actions: schema
+                .ignore_when
+                .as_ref()
+                .map(|i| i.actions.clone())
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// Validate the attributes of a principal and a resource for the given actions.
+    pub fn validate<S: AsRef<str>>(
+        &self,
+        principal: &Principal,
+        resource: &Resource,
+        actions: &[S],
+    ) -> std::result::Result<(), SchemaValidationError> {
+        let mut errors = Vec::new();
+        self.collect_errors(principal, resource, actions, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaValidationError { errors })
+        }
+    }
+
+    /// Validate the attributes of the principal and every resource of a `check_resources` request.
+    pub fn validate_resources(
+        &self,
+        principal: &Principal,
+        resources: &ResourceList,
+    ) -> std::result::Result<(), SchemaValidationError> {
+        let mut errors = Vec::new();
+        for entry in &resources.resources {
+            let resource = Resource {
+                resource: entry.resource.clone().unwrap_or_default(),
+            };
+            self.collect_errors(principal, &resource, &entry.actions, &mut errors);
+        }
+        // The same principal errors are found for every resource.
+        let mut seen = std::collections::HashSet::new();
+        errors.retain(|e| seen.insert(e.clone()));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaValidationError { errors })
+        }
+    }
+
+    fn collect_errors<S: AsRef<str>>(
+        &self,
+        principal: &Principal,
+        resource: &Resource,
+        actions: &[S],
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let r = &resource.resource;
+        let Some(schemas) = self.lookup(&r.kind, &r.policy_version, &r.scope) else {
+            return;
+        };
+
+        let checks = [
+            (
+                &schemas.principal,
+                &principal.principal.attr,
+                Source::Principal,
+            ),
+            (&schemas.resource, &r.attr, Source::Resource),
+        ];
+        for (schema, attr, source) in checks {
+            let Some(schema) = schema else {
+                continue;
+            };
+            let ignored = !actions.is_empty()
+                && actions
+                    .iter()
+                    .all(|a| schema.ignore_actions.iter().any(|i| i == a.as_ref()));
+            if ignored {
+                continue;
+            }
+            let Some(validator) = self.validators.get(&schema.id) else {
+                continue;
+            };
+            let instance = match serde_json::to_value(attr) {
+                Ok(instance) => instance,
+                Err(e) => {
+                    errors.push(ValidationError {
+                        path: String::new(),
+                        message: e.to_string(),
+                        source: source as i32,
+                    });
+                    continue;
+                }
+            };
+            errors.extend(validator.iter_errors(&instance).map(|e| ValidationError {
+                path: e.instance_path.as_str().to_string(),
+                message: e.to_string(),
+                source: source as i32,
+            }));
+        }
+    }
+
+    fn lookup(&self, kind: &str, version: &str, scope: &str) -> Option<&PolicySchemas> {
+        let version = if version.is_empty() {
+            DEFAULT_VERSION
+        } else {
+            version
+        };
+        let mut scope = scope;
+        loop {
+            let key = (kind.to_string(), version.to_string(), scope.to_string());
+            if let Some(schemas) = self.policies.get(&key) {
+                return Some(schemas);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope
+                .rsplit_once('.')
+                .map(|(parent, _)| parent)
+                .unwrap_or("");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_validator() -> Result<()> {
+        let store = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/store");
+        let (policies, schemas) = PolicySet::from_directory(store)?;
+        let validator = SchemaValidator::new(&schemas)?.with_policies(&policies)?;
+
+        let principal = Principal::new("alice", ["employee"])
+            .add_attr("department", "marketing")
+            .add_attr("geography", "GB")
+            .add_attr("team", "design");
+        let resource = Resource::new("XX125", "leave_request")
+            .add_attr("department", "marketing")
+            .add_attr("geography", "GB")
+            .add_attr("team", "design")
+            .add_attr("id", "XX125");
+        validator.validate(&principal, &resource, &["view"])?;
+
+        let bad_principal = principal.add_attr("department", "sales");
+        let bad_resource = Resource::new("XX125", "leave_request").add_attr("dev_record", "yes");
+        let err = validator
+            .validate(&bad_principal, &bad_resource, &["view"])
+            .unwrap_err();
+        let paths: Vec<(Source, &str)> = err
+            .errors
+            .iter()
+            .map(|e| (e.source(), e.path.as_str()))
+            .collect();
+        assert!(paths.contains(&(Source::Principal, "/department")));
+        assert!(paths.contains(&(Source::Resource, "/dev_record")));
+        assert!(paths.contains(&(Source::Resource, "")));
+
+        Ok(())
+    }
+}
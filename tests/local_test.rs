@@ -0,0 +1,170 @@
+// Copyright 2021-2025 Zenauth Ltd.
+// SPDX-License-Identifier: Apache-2.0
+#![cfg(feature = "local")]
+
+use std::path::PathBuf;
+
+use cerbos::{
+    genpb::{
+        cerbos::{effect::v1::Effect, policy::v1::test_results::Result as TestResult},
+        google::protobuf::{value, Value},
+    },
+    sdk::{
+        attr::attr,
+        authorizer::Authorizer,
+        local::{LocalEngine, Unsupported},
+        model::*,
+        test_suite::{ensure_passed, TestRunner},
+        Result,
+    },
+};
+
+fn local_engine() -> Result<LocalEngine> {
+    let mut store_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    store_dir.push("resources");
+    store_dir.push("store");
+
+    let mut engine = LocalEngine::new().with_include_meta(true);
+    for dir in ["derived_roles", "principal_policies", "resource_policies"] {
+        for entry in std::fs::read_dir(store_dir.join(dir))? {
+            engine.add_policy_from_file(entry?.path())?;
+        }
+    }
+    Ok(engine)
+}
+
+#[tokio::test]
+async fn check_resources_local() -> Result<()> {
+    let mut engine = local_engine()?;
+    let principal = Principal::new("alice", ["employee"])
+        .with_policy_version("20210210")
+        .with_attributes([attr("department", "marketing"), attr("team", "design")]);
+
+    let resource = Resource::new("XX125", "leave_request")
+        .with_policy_version("20210210")
+        .with_attributes([
+            attr("owner", "alice"),
+            attr("id", "XX125"),
+            attr("status", "PENDING_APPROVAL"),
+        ]);
+
+    let resp = engine
+        .check_resources(
+            principal,
+            ResourceList::new_from([ResourceAction(
+                resource,
+                ["view:public", "create", "approve", "defer"],
+            )]),
+            None,
+        )
+        .await?;
+
+    let decisions = &resp.decisions()?["XX125"];
+    assert_eq!(decisions["view:public"], Effect::Allow);
+    assert_eq!(decisions["create"], Effect::Allow);
+    // Only direct managers can approve.
+    assert_eq!(decisions["approve"], Effect::Deny);
+    // The JWT is not available to the local engine.
+    assert_eq!(decisions["defer"], Effect::Deny);
+
+    let meta = resp.response.results[0].meta.as_ref().unwrap();
+    assert_eq!(
+        meta.actions["create"].matched_policy,
+        "resource.leave_request.v20210210"
+    );
+    assert!(meta
+        .effective_derived_roles
+        .contains(&"employee_that_owns_the_record".to_string()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn plan_resources_local_is_unsupported() -> Result<()> {
+    let mut engine = local_engine()?;
+    let err = Authorizer::plan_resources(
+        &mut engine,
+        "view",
+        Principal::new("alice", ["employee"]),
+        ResourceKind::new("leave_request"),
+        None,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Unsupported>(),
+        Some(&Unsupported {
+            operation: "plan_resources"
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn check_resources_local_with_output() -> Result<()> {
+    let mut engine = local_engine()?;
+    let principal = Principal::new("donald_duck", ["employee"]).with_policy_version("20210210");
+    let resource = Resource::new("XX125", "leave_request")
+        .with_policy_version("20210210")
+        .with_attributes([attr("id", "XX125")]);
+
+    let resp = engine
+        .check_resources(
+            principal,
+            ResourceList::new_from([ResourceAction(resource, ["view:public"])]),
+            None,
+        )
+        .await?;
+
+    let xx125 = resp.find("XX125").unwrap();
+    assert!(xx125.is_allowed("view:public"));
+
+    let Some(Value {
+        kind: Some(value::Kind::StructValue(output)),
+    }) = xx125.output("resource.leave_request.v20210210#public-view")
+    else {
+        panic!("missing output");
+    };
+    let string_field = |name: &str| match output.fields[name].kind.as_ref() {
+        Some(value::Kind::StringValue(s)) => s.clone(),
+        other => panic!("unexpected value for {name}: {other:?}"),
+    };
+    assert_eq!(string_field("pID"), "donald_duck");
+    assert_eq!(string_field("keys"), "XX125");
+    assert_eq!(string_field("formatted_string"), "id:donald_duck");
+    assert!(output.fields.contains_key("something_nested"));
+
+    // The principal policy allows everything on dev records.
+    let resource = Resource::new("XX126", "leave_request")
+        .with_policy_version("20210210")
+        .with_attributes([attr("dev_record", true)]);
+    let allowed = engine
+        .is_allowed(
+            "delete",
+            Principal::new("donald_duck", ["employee"]).with_policy_version("20210210"),
+            resource,
+            None,
+        )
+        .await?;
+    assert!(allowed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn policy_test_suites_local() -> Result<()> {
+    let mut store_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    store_dir.push("resources");
+    store_dir.push("store");
+
+    let results = TestRunner::new(local_engine()?)
+        .run_directory(store_dir)
+        .await?;
+    ensure_passed(&results)?;
+
+    let summary = results.summary.unwrap();
+    assert_eq!(summary.overall_result, TestResult::Passed as i32);
+    assert_eq!(summary.tests_count, 1);
+    assert_eq!(results.suites[0].file, "tests/policy_04_test.yaml");
+    Ok(())
+}
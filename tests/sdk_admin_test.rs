@@ -178,6 +178,89 @@ async fn test_admin_client_schemas() -> Result<()> {
     assert!(eq(result, schemas.into_keys()));
     container.stop().await
 }
+#[cfg(all(feature = "testcontainers", feature = "admin"))]
+#[tokio::test]
+async fn test_reconcile() -> Result<()> {
+    use cerbos::sdk::admin::{model::SchemaSet, reconcile::ReconcileAction};
+
+    let account = "resource.account.vdefault";
+    let global = "resource.global.vdefault";
+    let donald = "principal.donald_duck.vdefault";
+    let load = |files: &[&str]| -> Result<PolicySet> {
+        let mut ps = PolicySet::new();
+        for f in files {
+            ps.add_policy_from_file(get_test_data_path(&["policies", f]))?;
+        }
+        Ok(ps)
+    };
+    let mut schemas = SchemaSet::new();
+    schemas.add_schema_from_file(
+        get_test_data_path(&["policies", "_schemas", "principal.json"]),
+        "principal.json",
+    )?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let (mut client, container) = async_tls_client(&temp_dir).await?;
+
+    let policies = load(&[
+        "resource_policies/policy_06.yaml",
+        "resource_policies/policy_08.yaml",
+    ])?;
+    let plan = client.plan_reconcile(&policies, &schemas).await?;
+    assert_eq!(
+        plan.actions(),
+        [
+            ReconcileAction::AddSchema("principal.json".to_string()),
+            ReconcileAction::AddPolicy(account.to_string()),
+            ReconcileAction::AddPolicy(global.to_string()),
+        ]
+    );
+    client.apply_reconcile(&plan).await?;
+    assert!(client.plan_reconcile(&policies, &schemas).await?.is_empty());
+
+    // Change one policy, drop another and the schema, and add a new policy.
+    let mut updated = load(&["resource_policies/policy_06.yaml"])?.get_policies()[0].clone();
+    updated.description = "updated".to_string();
+    let mut policies = load(&["principal_policies/policy_02.yaml"])?;
+    policies.add_policy(updated);
+    let no_schemas = SchemaSet::new();
+    let plan = client.plan_reconcile(&policies, &no_schemas).await?;
+    assert_eq!(
+        plan.actions(),
+        [
+            ReconcileAction::AddPolicy(donald.to_string()),
+            ReconcileAction::UpdatePolicy(account.to_string()),
+            ReconcileAction::DisablePolicy(global.to_string()),
+            ReconcileAction::DeleteSchema("principal.json".to_string()),
+        ]
+    );
+    client.apply_reconcile(&plan).await?;
+    assert!(eq(client.list_policies(None).await?, [account, donald]));
+    let plan = client.plan_reconcile(&policies, &no_schemas).await?;
+    assert!(plan.is_empty(), "unexpected changes after apply: {plan}");
+
+    // A local copy of a disabled policy re-enables it.
+    policies.add_policies(
+        load(&["resource_policies/policy_08.yaml"])?
+            .get_policies()
+            .to_vec(),
+    );
+    let plan = client.plan_reconcile(&policies, &no_schemas).await?;
+    assert_eq!(
+        plan.actions(),
+        [ReconcileAction::UpdatePolicy(global.to_string())]
+    );
+    client.apply_reconcile(&plan).await?;
+    assert!(eq(
+        client.list_policies(None).await?,
+        [account, donald, global]
+    ));
+    assert!(client
+        .plan_reconcile(&policies, &no_schemas)
+        .await?
+        .is_empty());
+    container.stop().await
+}
 async fn add_or_update_policies(
     client: &mut cerbos::sdk::admin::CerbosAdminClient,
     policies: &HashMap<&'static str, &'static str>,
@@ -443,14 +526,19 @@ async fn inspect_policies(client: &mut cerbos::sdk::admin::CerbosAdminClient) ->
 
     for (name, tc) in test_cases {
         let have = client.inspect_policies(tc.options).await?;
-        assert!(!have.results.is_empty(), "{name} test case results empty");
+        assert!(!have.is_empty(), "{name} test case results empty");
         for (fqn, actions) in tc.want {
+            let Some(policy) = have.get(fqn) else {
+                panic!("{name} test case result no fqn: {fqn}");
+            };
+            for action in &actions {
+                assert!(
+                    have.with_action(action).any(|p| p.policy_id == fqn),
+                    "{name} test case no {fqn} for action {action}"
+                );
+            }
             assert!(
-                have.results.contains_key(fqn),
-                "{name} test case result no fqn: {fqn}"
-            );
-            assert!(
-                eq(&have.results[fqn].actions, actions),
+                eq(&policy.actions, actions),
                 "{name} test case results mismtach"
             );
         }
@@ -458,6 +546,31 @@ async fn inspect_policies(client: &mut cerbos::sdk::admin::CerbosAdminClient) ->
     Ok(())
 }
 #[test]
+fn test_policy_set_from_directory() -> Result<()> {
+    let (ps, ss) = PolicySet::from_directory(get_test_data_path(&["policies"]))?;
+
+    let ids = ss.get_schemas().iter().map(|s| s.id.as_str());
+    assert!(eq(
+        ids,
+        [
+            "principal.json",
+            "resources/leave_request.json",
+            "resources/purchase_order.json",
+            "resources/salary_record.json",
+        ]
+    ));
+    assert_eq!(ps.size(), 32);
+
+    let dir = std::env::temp_dir().join(format!("cerbos-sdk-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("resource_policies"))?;
+    std::fs::write(dir.join("resource_policies/bad_1.yaml"), "nope")?;
+    std::fs::write(dir.join("resource_policies/bad_2.yaml"), "- 1")?;
+    let result = PolicySet::from_directory(&dir);
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(result.unwrap_err().errors.len(), 2);
+    Ok(())
+}
+#[test]
 pub fn test_google_protobuf_value_de() {
     use google::protobuf::{value::Kind, Value};
     let v: Value = serde_json::from_str("42").unwrap();
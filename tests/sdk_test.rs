@@ -298,16 +298,19 @@ async fn do_plan_resources(mut client: CerbosAsyncClient) -> Result<()> {
         .plan_resources("approve", principal.clone(), resource.clone(), None)
         .await?;
     assert!(matches!(
-        response.filter(),
+        response.filter()?,
         PlanResourcesFilter::Conditional(..)
     ));
+    assert_eq!(response.actions(), ["approve"]);
+    assert_eq!(response.resource_kind(), "leave_request");
+    assert!(response.validation_errors().is_empty());
 
     let response = client
         .plan_resources_for_actions(["approve", "view:public"], principal, resource, None)
         .await?;
 
     assert!(matches!(
-        response.filter(),
+        response.filter()?,
         PlanResourcesFilter::Conditional(..)
     ));
 
@@ -3,14 +3,14 @@
 #![cfg(feature = "hub")]
 
 use anyhow::{Context, Result};
-use cerbos::genpb::cerbos::cloud::store::v1::GetFilesRequest;
+use cerbos::genpb::cerbos::cloud::store::v1::{file_op::Op, File, FileOp, GetFilesRequest};
 use cerbos::sdk::hub::auth::AuthMiddleware;
 use cerbos::sdk::hub::rpc_error::RPCError;
 use cerbos::sdk::hub::store::{
-    FileFilterBuilder, GetFilesRequestBuilder, ListFilesRequestBuilder, ModifyFilesRequestBuilder,
-    ReplaceFilesRequestBuilder, StoreClient,
+    ChangeDetailsBuilder, FileFilterBuilder, GetFilesRequestBuilder, ListFilesRequestBuilder,
+    ModifyFilesRequestBuilder, ReplaceFilesRequestBuilder, StoreClient, StoreManifest,
 };
-use cerbos::sdk::hub::utils::zip_directory;
+use cerbos::sdk::hub::utils::{read_directory, zip_directory};
 use cerbos::sdk::hub::HubClientBuilder;
 use std::path::PathBuf;
 use std::{env, str};
@@ -156,15 +156,9 @@ async fn test_auth_error() -> Result<(), Box<dyn std::error::Error>> {
     let files = vec!["wibble.yaml".to_string()];
     let request = GetFilesRequest { store_id, files };
     let result = store_client.get_files(request.clone()).await;
-    if let Err(RPCError::Unknown {
-        message: _,
-        underlying: ref e,
-    }) = result
-    {
-        if e.code() == tonic::Code::ResourceExhausted {
-            eprintln!("\x1b[91mSkipping test due to too many requests response\x1b[0m");
-            return Ok(());
-        }
+    if let Err(RPCError::ResourceExhausted { .. }) = result {
+        eprintln!("\x1b[91mSkipping test due to too many requests response\x1b[0m");
+        return Ok(());
     }
     assert!(
         matches!(
@@ -186,6 +180,42 @@ async fn test_auth_error() -> Result<(), Box<dyn std::error::Error>> {
     ));
     Ok(())
 }
+#[tokio::test]
+async fn test_set_credentials() -> Result<(), Box<dyn std::error::Error>> {
+    let api_endpoint = env::var("CERBOS_HUB_API_ENDPOINT")
+        .unwrap_or_else(|_| "https://api.cerbos.cloud".to_string());
+
+    let store_id = env::var("CERBOS_HUB_STORE_ID")
+        .expect("CERBOS_HUB_STORE_ID environment variable must be set for integration tests");
+
+    let hub_client = HubClientBuilder::new()
+        .with_api_endpoint(api_endpoint)
+        .with_client_credentials("not a client", "not a secret")
+        .build()
+        .await?;
+
+    let mut store_client = hub_client.store_client();
+    let request = ListFilesRequestBuilder::new(&store_id).build();
+    let result = store_client.list_files(request.clone()).await;
+    if let Err(RPCError::ResourceExhausted { .. }) = result {
+        eprintln!("\x1b[91mSkipping test due to too many requests response\x1b[0m");
+        return Ok(());
+    }
+    assert!(
+        matches!(&result, Err(RPCError::AuthenticationFailed { .. })),
+        "{result:?}"
+    );
+
+    hub_client
+        .set_credentials(
+            env::var("CERBOS_HUB_CLIENT_ID")?,
+            env::var("CERBOS_HUB_CLIENT_SECRET")?,
+        )
+        .await;
+    store_client.list_files(request).await?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_replace_files() -> Result<(), Box<dyn std::error::Error>> {
     let mut setup = TestSetup::new().await?;
@@ -272,13 +302,7 @@ async fn test_replace_files_unsuccessful_condition(
         .build();
 
     let result = setup.store_client.replace_files(request).await;
-    assert!(matches!(
-        result,
-        Err(RPCError::ConditionUnsatisfied {
-            message: _,
-            underlying: _
-        })
-    ));
+    assert!(matches!(result, Err(RPCError::ConditionUnsatisfied { .. })));
 
     setup.check_store_has_expected_files().await?;
     Ok(())
@@ -392,13 +416,7 @@ async fn test_modify_files_unsuccessful_condition(
     let result = setup.store_client.modify_files(request).await;
 
     assert!(
-        matches!(
-            result,
-            Err(RPCError::ConditionUnsatisfied {
-                message: _,
-                underlying: _
-            },)
-        ),
+        matches!(result, Err(RPCError::ConditionUnsatisfied { .. })),
         "Expected ConditionUnsatisfied, got: {result:?}"
     );
     Ok(())
@@ -495,3 +513,133 @@ async fn test_get_files_invalid_request(
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_sync_files() -> Result<(), Box<dyn std::error::Error>> {
+    let mut setup = TestSetup::new().await?;
+    setup.reset_store().await?;
+
+    let test_data_path = get_test_data_path(&["replace_files", "success"]);
+    let mut files = read_directory(&test_data_path)?;
+
+    // Nothing to do when the store is in sync
+    let result = setup
+        .store_client
+        .sync_directory(
+            &setup.store_id,
+            &test_data_path,
+            ChangeDetailsBuilder::new("No-op sync").build(),
+        )
+        .await?;
+    assert!(result.is_empty(), "{result:?}");
+
+    let example_content = std::fs::read(get_test_data_path(&[
+        "modify_files",
+        "success",
+        "example.yaml",
+    ]))?;
+    files.push(File {
+        path: "example.yaml".to_string(),
+        contents: example_content,
+    });
+    let added = setup
+        .store_client
+        .sync_files(
+            &setup.store_id,
+            files,
+            ChangeDetailsBuilder::new("Sync with added file").build(),
+        )
+        .await?;
+    assert_eq!(added.added, ["example.yaml"]);
+    assert!(added.updated.is_empty() && added.deleted.is_empty());
+    assert!(added.store_version > result.store_version);
+
+    let deleted = setup
+        .store_client
+        .sync_directory(
+            &setup.store_id,
+            &test_data_path,
+            ChangeDetailsBuilder::new("Sync with deleted file").build(),
+        )
+        .await?;
+    assert_eq!(deleted.deleted, ["example.yaml"]);
+    setup.check_store_has_expected_files().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_download_store() -> Result<(), Box<dyn std::error::Error>> {
+    let mut setup = TestSetup::new().await?;
+    setup.reset_store().await?;
+
+    let dir = env::temp_dir().join(format!("cerbos-store-{}", setup.store_id));
+    let manifest = setup
+        .store_client
+        .download_store(&setup.store_id, &dir)
+        .await?;
+    assert_eq!(manifest.store_id, setup.store_id);
+    assert_eq!(StoreManifest::read(&dir)?, manifest);
+
+    let mut have_files: Vec<String> = read_directory(&dir)?.into_iter().map(|f| f.path).collect();
+    have_files.sort();
+    assert_eq!(have_files, WANT_FILES_LIST);
+
+    // A store that was just downloaded is in sync
+    let result = setup
+        .store_client
+        .sync_directory(
+            &setup.store_id,
+            &dir,
+            ChangeDetailsBuilder::new("No-op sync").build(),
+        )
+        .await?;
+    assert!(result.is_empty(), "{result:?}");
+    assert_eq!(result.store_version, manifest.store_version);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_with() -> Result<(), Box<dyn std::error::Error>> {
+    let mut setup = TestSetup::new().await?;
+    setup.reset_store().await?;
+
+    let example_content = std::fs::read(get_test_data_path(&[
+        "modify_files",
+        "success",
+        "example.yaml",
+    ]))?;
+    let add_example = |files: &[File]| {
+        assert!(!files.is_empty());
+        vec![FileOp {
+            op: Some(Op::AddOrUpdate(File {
+                path: "example.yaml".to_string(),
+                contents: example_content.clone(),
+            })),
+        }]
+    };
+
+    let response = setup
+        .store_client
+        .update_with(
+            &setup.store_id,
+            ChangeDetailsBuilder::new("Add example").build(),
+            add_example,
+        )
+        .await?;
+
+    // Re-applying the same change is discarded and reported as success
+    let again = setup
+        .store_client
+        .update_with(
+            &setup.store_id,
+            ChangeDetailsBuilder::new("Add example again").build(),
+            add_example,
+        )
+        .await?;
+    assert_eq!(again.new_store_version, response.new_store_version);
+
+    Ok(())
+}